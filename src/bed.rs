@@ -1,7 +1,10 @@
 //! An interface to the BED track format file as specified in
 //! https://genome.ucsc.edu/FAQ/FAQformat.html#format1
 
-use crate::util::{get_buf, Strand};
+use crate::{
+    error::Error,
+    util::{get_file_buf, natural_chrom_cmp, Strand},
+};
 use math::{
     partition::integer_interval_map::IntegerIntervalMap,
     set::{
@@ -15,7 +18,7 @@ use std::{
     collections::HashMap,
     fmt::Debug,
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, BufWriter, Write},
     marker::PhantomData,
     str::FromStr,
 };
@@ -23,7 +26,7 @@ use std::{
 pub mod bed_writer;
 pub mod paired_end_collator;
 
-use crate::iter::{ChromIntervalValue, ToChromIntervalValueIter};
+use crate::iter::{chunk_by, ChromIntervalValue, ToChromIntervalValueIter};
 pub use bed_writer::BedWriter;
 
 pub struct Bed {
@@ -42,6 +45,25 @@ impl Bed {
         }
     }
 
+    /// Same as `new` without binarizing the scores, except that every data
+    /// line is parsed first, so that a malformed line is reported as an
+    /// `Error::BadFormat` with its line number. The lines can have any number
+    /// of columns from 3 to 12, where the absent name, score, and strand are
+    /// `None`.
+    pub fn from_path(filepath: &str) -> Result<Bed, Error> {
+        for (i, line) in get_file_buf(filepath)?.lines().enumerate() {
+            if let Err(why) = parse_data_line::<f64, _>(&line?, false) {
+                return Err(Error::BadFormat(format!(
+                    "line {} of {}: {}",
+                    i + 1,
+                    filepath,
+                    why
+                )));
+            }
+        }
+        Ok(Bed::new(filepath, false))
+    }
+
     #[inline]
     pub fn get_filepath(&self) -> &str {
         &self.filepath
@@ -74,10 +96,355 @@ impl Bed {
 
     pub fn to_coord_iter(&self) -> BedCoordinateIter {
         BedCoordinateIter {
-            buf: get_buf(&self.filepath).unwrap(),
+            buf: get_file_buf(&self.filepath).unwrap(),
             filename: self.filepath.clone(),
         }
     }
+
+    /// Sorts the data lines in the BED file in place by chromosome, start and
+    /// end, where the chromosomes are compared by `natural_chrom_cmp` so that
+    /// chr2 comes before chr10. The header lines, i.e. the comments and the
+    /// track and browser lines, are kept at the top in their original order.
+    ///
+    /// Note that this modifies the BED file. The sorted lines are first
+    /// written to the file with the suffix `.sort.tmp` in the same directory,
+    /// which then replaces the BED file, so that the BED file is left intact
+    /// if the writing fails.
+    pub fn sort(&mut self) -> Result<(), Error> {
+        let mut header_lines = Vec::new();
+        let mut data_lines = Vec::new();
+        for (i, line) in get_file_buf(&self.filepath)?.lines().enumerate() {
+            let line = line?;
+            let mut toks = line.split_whitespace();
+            let chrom = match toks.next() {
+                None => continue,
+                Some(chrom) => chrom,
+            };
+            if chrom.starts_with('#') || chrom == "track" || chrom == "browser"
+            {
+                header_lines.push(line);
+                continue;
+            }
+            let mut parse_coord = || {
+                toks.next()
+                    .and_then(|t| t.parse::<Coordinate>().ok())
+                    .ok_or_else(|| {
+                        Error::BadFormat(format!(
+                            "invalid coordinates on line {} of {}",
+                            i + 1,
+                            self.filepath
+                        ))
+                    })
+            };
+            let start = parse_coord()?;
+            let end = parse_coord()?;
+            data_lines.push((chrom.to_string(), start, end, line));
+        }
+        data_lines.sort_by(|a, b| {
+            natural_chrom_cmp(&a.0, &b.0)
+                .then(a.1.cmp(&b.1))
+                .then(a.2.cmp(&b.2))
+        });
+
+        let tmp_path = format!("{}.sort.tmp", self.filepath);
+        let write_sorted_lines = || -> Result<(), Error> {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            for line in header_lines.iter() {
+                writeln!(writer, "{}", line)?;
+            }
+            for (_, _, _, line) in data_lines.iter() {
+                writeln!(writer, "{}", line)?;
+            }
+            writer.flush()?;
+            Ok(())
+        };
+        if let Err(error) = write_sorted_lines() {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(error);
+        }
+        std::fs::rename(&tmp_path, &self.filepath)?;
+        Ok(())
+    }
+
+    /// Merges, per chromosome, the intervals that overlap or are separated by
+    /// at most `max_gap` base pairs into a single interval, e.g. with a
+    /// `max_gap` of 0, [100, 200) and [200, 300) are merged into [100, 300).
+    ///
+    /// The scores of the merged intervals are combined according to
+    /// `merge_score`, while the name and the strand of the merged intervals
+    /// are set to `None`. The returned data lines are sorted by chromosome,
+    /// using `natural_chrom_cmp`, and then by the start coordinate.
+    pub fn merge<D, E>(
+        &self,
+        max_gap: Coordinate,
+        merge_score: MergeScore,
+    ) -> Vec<BedDataLine<D>>
+    where
+        D: Float + FromStr<Err = E>,
+        E: Debug, {
+        let mut merged = Vec::new();
+        for (chrom, lines) in self.get_sorted_chrom_lines::<D, E>() {
+            let mut current: Option<BedDataLine<D>> = None;
+            for line in lines.into_iter() {
+                current = match current {
+                    Some(mut c) if line.start <= c.end + max_gap => {
+                        c.end = std::cmp::max(c.end, line.end);
+                        c.score = merge_score.combine(c.score, line.score);
+                        Some(c)
+                    }
+                    previous => {
+                        if let Some(p) = previous {
+                            merged.push(p);
+                        }
+                        Some(BedDataLine {
+                            chrom: chrom.clone(),
+                            start: line.start,
+                            end: line.end,
+                            name: None,
+                            score: line.score,
+                            strand: None,
+                        })
+                    }
+                };
+            }
+            if let Some(c) = current {
+                merged.push(c);
+            }
+        }
+        merged
+    }
+
+    /// Returns the number of distinct base pairs covered by the intervals,
+    /// where the overlapping base pairs are counted once.
+    pub fn total_coverage(&self) -> u64 {
+        self.coverage_per_chromosome()
+            .into_iter()
+            .map(|(_, coverage)| coverage)
+            .sum()
+    }
+
+    /// Returns the number of distinct base pairs covered by the intervals on
+    /// each chromosome, where the chromosomes are sorted by
+    /// `natural_chrom_cmp`.
+    pub fn coverage_per_chromosome(&self) -> Vec<(Chrom, u64)> {
+        chunk_by(self.merge::<f64, _>(0, MergeScore::Sum), |line| {
+            line.chrom.clone()
+        })
+        .map(|(chrom, lines)| {
+            let coverage = lines
+                .iter()
+                .map(|line| std::cmp::max(line.end - line.start, 0) as u64)
+                .sum();
+            (chrom, coverage)
+        })
+        .collect()
+    }
+
+    /// Returns, for each pair of overlapping intervals from `self` and
+    /// `other`, the overlapping region together with the two source data
+    /// lines. Following the half-open convention, book-ended intervals such
+    /// as [100, 200) and [200, 300) do not overlap.
+    ///
+    /// The intersection is computed with a sweep line over the sorted start
+    /// coordinates on each chromosome, in O(n + m + k) time after sorting,
+    /// where k is the number of overlapping pairs. The returned records are
+    /// sorted by chromosome, using `natural_chrom_cmp`.
+    pub fn intersect<D, E>(&self, other: &Bed) -> Vec<IntersectionRecord<D>>
+    where
+        D: Float + FromStr<Err = E>,
+        E: Debug, {
+        let mut other_chrom_to_lines: HashMap<Chrom, Vec<BedDataLine<D>>> =
+            other.get_sorted_chrom_lines::<D, E>().into_iter().collect();
+
+        let mut records = Vec::new();
+        for (chrom, lines) in self.get_sorted_chrom_lines::<D, E>() {
+            let other_lines = match other_chrom_to_lines.remove(&chrom) {
+                Some(other_lines) => other_lines,
+                None => continue,
+            };
+            let lines: Vec<&BedDataLine<D>> =
+                lines.iter().filter(|l| l.start < l.end).collect();
+            let other_lines: Vec<&BedDataLine<D>> =
+                other_lines.iter().filter(|l| l.start < l.end).collect();
+
+            // the intervals whose start has been visited and whose end has
+            // not yet been passed by the sweep line
+            let mut active: Vec<&BedDataLine<D>> = Vec::new();
+            let mut other_active: Vec<&BedDataLine<D>> = Vec::new();
+            let (mut i, mut j) = (0, 0);
+            while i < lines.len() || j < other_lines.len() {
+                if j == other_lines.len()
+                    || (i < lines.len()
+                        && lines[i].start <= other_lines[j].start)
+                {
+                    let line = lines[i];
+                    other_active.retain(|l| l.end > line.start);
+                    for other_line in other_active.iter() {
+                        records.push(IntersectionRecord::new(line, other_line));
+                    }
+                    active.push(line);
+                    i += 1;
+                } else {
+                    let other_line = other_lines[j];
+                    active.retain(|l| l.end > other_line.start);
+                    for line in active.iter() {
+                        records.push(IntersectionRecord::new(line, other_line));
+                    }
+                    other_active.push(other_line);
+                    j += 1;
+                }
+            }
+        }
+        records
+    }
+
+    /// Returns, for each interval in `self`, the portions not covered by any
+    /// interval in `other`, as in `bedtools subtract`. An interval is split
+    /// into pieces around each interval of `other` inside it, and the pieces
+    /// keep the name, the score, and the strand of the interval. The empty
+    /// pieces are dropped.
+    ///
+    /// The returned data lines are sorted by chromosome, using
+    /// `natural_chrom_cmp`, and then by the start coordinate.
+    pub fn subtract<D, E>(&self, other: &Bed) -> Vec<BedDataLine<D>>
+    where
+        D: Float + FromStr<Err = E>,
+        E: Debug, {
+        // the sorted and coalesced intervals of other on each chromosome
+        let other_chrom_to_intervals: HashMap<
+            Chrom,
+            Vec<(Coordinate, Coordinate)>,
+        > = other
+            .get_sorted_chrom_lines::<D, E>()
+            .into_iter()
+            .map(|(chrom, lines)| {
+                let mut intervals: Vec<(Coordinate, Coordinate)> = Vec::new();
+                for line in lines.iter().filter(|l| l.start < l.end) {
+                    match intervals.last_mut() {
+                        Some(last) if line.start <= last.1 => {
+                            last.1 = std::cmp::max(last.1, line.end);
+                        }
+                        _ => intervals.push((line.start, line.end)),
+                    }
+                }
+                (chrom, intervals)
+            })
+            .collect();
+
+        let mut pieces = Vec::new();
+        for (chrom, lines) in self.get_sorted_chrom_lines::<D, E>() {
+            let other_intervals: &[(Coordinate, Coordinate)] =
+                match other_chrom_to_intervals.get(&chrom) {
+                    Some(intervals) => intervals,
+                    None => &[],
+                };
+            // the first interval of other that can overlap the current line,
+            // which only moves forward as the lines are sorted by start
+            let mut j = 0;
+            for line in lines.into_iter().filter(|l| l.start < l.end) {
+                while j < other_intervals.len()
+                    && other_intervals[j].1 <= line.start
+                {
+                    j += 1;
+                }
+                let mut cursor = line.start;
+                for &(other_start, other_end) in other_intervals[j..].iter() {
+                    if other_start >= line.end {
+                        break;
+                    }
+                    if cursor < other_start {
+                        pieces.push(BedDataLine {
+                            start: cursor,
+                            end: other_start,
+                            ..line.clone()
+                        });
+                    }
+                    cursor = std::cmp::max(cursor, other_end);
+                }
+                if cursor < line.end {
+                    pieces.push(BedDataLine {
+                        start: cursor,
+                        ..line
+                    });
+                }
+            }
+        }
+        pieces
+    }
+
+    /// Returns the data lines grouped by chromosome, where the chromosomes
+    /// are sorted by `natural_chrom_cmp` and the lines on each chromosome are
+    /// sorted by (start, end).
+    fn get_sorted_chrom_lines<D, E>(
+        &self,
+    ) -> Vec<(Chrom, Vec<BedDataLine<D>>)>
+    where
+        D: Float + FromStr<Err = E>,
+        E: Debug, {
+        let mut chrom_to_lines: HashMap<Chrom, Vec<BedDataLine<D>>> =
+            HashMap::new();
+        for line in self.to_iter() {
+            chrom_to_lines
+                .entry(line.chrom.clone())
+                .or_default()
+                .push(line);
+        }
+        let mut chrom_lines: Vec<(Chrom, Vec<BedDataLine<D>>)> =
+            chrom_to_lines.into_iter().collect();
+        chrom_lines.sort_by(|a, b| natural_chrom_cmp(&a.0, &b.0));
+        for (_, lines) in chrom_lines.iter_mut() {
+            lines.sort_by_key(|l| (l.start, l.end));
+        }
+        chrom_lines
+    }
+}
+
+/// An overlap between a data line from the `Bed` on which `Bed::intersect` is
+/// called and a data line from the other `Bed`, where [start, end) is the
+/// overlapping region.
+#[derive(Debug, PartialEq, Clone)]
+pub struct IntersectionRecord<D> {
+    pub chrom: Chrom,
+    pub start: Coordinate,
+    pub end: Coordinate,
+    pub line: BedDataLine<D>,
+    pub other_line: BedDataLine<D>,
+}
+
+impl<D: Clone> IntersectionRecord<D> {
+    fn new(
+        line: &BedDataLine<D>,
+        other_line: &BedDataLine<D>,
+    ) -> IntersectionRecord<D> {
+        IntersectionRecord {
+            chrom: line.chrom.clone(),
+            start: std::cmp::max(line.start, other_line.start),
+            end: std::cmp::min(line.end, other_line.end),
+            line: line.clone(),
+            other_line: other_line.clone(),
+        }
+    }
+}
+
+/// Determines how the scores of the intervals merged by `Bed::merge` are
+/// combined. Missing scores are ignored.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MergeScore {
+    Sum,
+    Max,
+}
+
+impl MergeScore {
+    fn combine<D: Float>(&self, a: Option<D>, b: Option<D>) -> Option<D> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(match self {
+                MergeScore::Sum => a + b,
+                MergeScore::Max => a.max(b),
+            }),
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
 }
 
 impl<D, E>
@@ -89,9 +456,10 @@ where
 {
     fn to_iter(&self) -> BedDataLineIter<D> {
         BedDataLineIter {
-            buf: get_buf(&self.filepath).unwrap(),
+            buf: get_file_buf(&self.filepath).unwrap(),
             filename: self.filepath.clone(),
             binarize_score: self.binarize_score,
+            line_num: 0,
             phantom: PhantomData,
         }
     }
@@ -151,6 +519,9 @@ pub struct BedDataLineIter<D> {
     /// Every line in the BED file will contribute a unit score for the
     /// corresponding interval.
     binarize_score: bool,
+
+    /// The number of lines read so far.
+    line_num: usize,
     phantom: PhantomData<D>,
 }
 
@@ -163,48 +534,80 @@ impl<D> BedDataLineIter<D> {
 impl<D: Float + FromStr<Err = E>, E: Debug> Iterator for BedDataLineIter<D> {
     type Item = BedDataLine<D>;
 
+    /// Panics if a data line is malformed. Use `Bed::from_path` to check the
+    /// lines beforehand.
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             let mut line = String::new();
-            return if self.buf.read_line(&mut line).unwrap() == 0 {
-                None
-            } else {
-                let mut toks = line.split_whitespace();
-                let chrom = {
-                    let chrom = toks.next().unwrap();
-                    if chrom.starts_with('#') || chrom == "track" {
-                        continue;
-                    }
-                    chrom.to_string()
-                };
-                let start = toks.next().unwrap().parse::<Coordinate>().unwrap();
-                let end = toks.next().unwrap().parse::<Coordinate>().unwrap();
-
-                // optional fields
-                let name = toks.next().map(|name| name.to_string());
-                let score = if self.binarize_score {
-                    toks.next();
-                    Some(D::one())
-                } else {
-                    toks.next().map(|score| score.parse::<D>().unwrap())
-                };
-                let strand = toks.next().and_then(|strand| {
-                    Strand::new(strand)
-                        .expect("failed to parse the strand symbol")
-                });
-                Some(BedDataLine {
-                    chrom,
-                    start,
-                    end,
-                    name,
-                    score,
-                    strand,
-                })
-            };
+            if self.buf.read_line(&mut line).unwrap() == 0 {
+                return None;
+            }
+            self.line_num += 1;
+            match parse_data_line(&line, self.binarize_score) {
+                Ok(Some(data_line)) => return Some(data_line),
+                Ok(None) => continue,
+                Err(why) => panic!(
+                    "line {} of {}: {}",
+                    self.line_num, self.filename, why
+                ),
+            }
         }
     }
 }
 
+/// Parses a line of the form `chrom start end [name [score [strand ...]]]`,
+/// where the score is `None` if it is `.`. Returns `None` for the blank lines,
+/// the comments, and the track and browser lines.
+fn parse_data_line<D: Float + FromStr<Err = E>, E: Debug>(
+    line: &str,
+    binarize_score: bool,
+) -> Result<Option<BedDataLine<D>>, String> {
+    let mut toks = line.split_whitespace();
+    let chrom = match toks.next() {
+        None => return Ok(None),
+        Some(chrom)
+            if chrom.starts_with('#')
+                || chrom == "track"
+                || chrom == "browser" =>
+        {
+            return Ok(None)
+        }
+        Some(chrom) => chrom.to_string(),
+    };
+    let mut parse_coord = |field: &str| match toks.next() {
+        None => Err(format!("missing the {} coordinate", field)),
+        Some(tok) => tok.parse::<Coordinate>().map_err(|e| {
+            format!("invalid {} coordinate {}: {}", field, tok, e)
+        }),
+    };
+    let start = parse_coord("start")?;
+    let end = parse_coord("end")?;
+
+    // optional fields
+    let name = toks.next().map(|name| name.to_string());
+    let score = match toks.next() {
+        _ if binarize_score => Some(D::one()),
+        None | Some(".") => None,
+        Some(score) => Some(
+            score
+                .parse::<D>()
+                .map_err(|e| format!("invalid score {}: {:?}", score, e))?,
+        ),
+    };
+    let strand = match toks.next() {
+        None => None,
+        Some(strand) => Strand::new(strand).map_err(|e| e.to_string())?,
+    };
+    Ok(Some(BedDataLine {
+        chrom,
+        start,
+        end,
+        name,
+        score,
+        strand,
+    }))
+}
+
 pub struct BedCoordinateIter {
     buf: BufReader<File>,
     filename: String,
@@ -244,8 +647,12 @@ impl Iterator for BedCoordinateIter {
 #[cfg(test)]
 mod tests {
     use crate::{
-        bed::{Bed, Chrom, Coordinate},
+        bed::{
+            Bed, BedDataLine, Chrom, Coordinate, IntersectionRecord, MergeScore,
+        },
+        error::Error,
         iter::{ChromIntervalValue, ToChromIntervalValueIter},
+        util::{natural_chrom_cmp, Strand},
     };
     use math::{
         partition::integer_interval_map::IntegerIntervalMap,
@@ -253,6 +660,7 @@ mod tests {
             contiguous_integer_set::ContiguousIntegerSet,
             ordered_integer_set::OrderedIntegerSet,
         },
+        traits::ToIterator,
     };
     use std::{
         collections::HashMap,
@@ -437,4 +845,344 @@ mod tests {
         );
         assert_eq!(iter.next(), None);
     }
+
+    #[test]
+    fn test_from_path() {
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer
+                .write_fmt(format_args!(
+                    "track name=mixed\n\
+                    chr1 100 200\n\
+                    \n\
+                    chr1 150 250 name_2 2 -\n\
+                    chr2 10 20 name_3 . +\n\
+                    chr2 30 40 name_4\n\
+                    chr3 0 5 name_5 1 . 0 5 0 1 5, 0,\n"
+                ))
+                .unwrap();
+        }
+        let bed = Bed::from_path(file.path().to_str().unwrap()).unwrap();
+        let lines: Vec<BedDataLine<f64>> = bed.to_iter().collect();
+        let line =
+            |chrom: &str, start, end, name: Option<&str>, score, strand| {
+                BedDataLine {
+                    chrom: chrom.to_string(),
+                    start,
+                    end,
+                    name: name.map(|n| n.to_string()),
+                    score,
+                    strand,
+                }
+            };
+        assert_eq!(lines, vec![
+            line("chr1", 100, 200, None, None, None),
+            line(
+                "chr1",
+                150,
+                250,
+                Some("name_2"),
+                Some(2.),
+                Some(Strand::Negative)
+            ),
+            line("chr2", 10, 20, Some("name_3"), None, Some(Strand::Positive)),
+            line("chr2", 30, 40, Some("name_4"), None, None),
+            line("chr3", 0, 5, Some("name_5"), Some(1.), None),
+        ]);
+
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer
+                .write_fmt(format_args!(
+                    "chr1 100 200\n\
+                    chr1 150 250 name_2 2 x\n"
+                ))
+                .unwrap();
+        }
+        match Bed::from_path(file.path().to_str().unwrap()) {
+            Err(Error::BadFormat(why)) => assert!(why.starts_with("line 2 ")),
+            _ => panic!("expected a BadFormat error"),
+        }
+
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer.write_fmt(format_args!("chr1 100\n")).unwrap();
+        }
+        assert!(Bed::from_path(file.path().to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_subtract() {
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer
+                .write_fmt(format_args!(
+                    "chr1 100 200 a 1 +\n\
+                    chr1 300 400 b 2 -\n\
+                    chr1 500 600 c 3\n\
+                    chr1 700 700 d 4\n\
+                    chr2 0 50 e 5\n\
+                    chr3 10 20 f 6\n"
+                ))
+                .unwrap();
+        }
+        let other_file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&other_file);
+            writer
+                .write_fmt(format_args!(
+                    "chr1 150 160\n\
+                    chr1 155 170\n\
+                    chr1 180 190\n\
+                    chr1 250 320\n\
+                    chr1 500 600\n\
+                    chr3 0 15\n\
+                    chr4 0 100\n"
+                ))
+                .unwrap();
+        }
+        let bed = Bed::new(file.path().to_str().unwrap(), false);
+        let other = Bed::new(other_file.path().to_str().unwrap(), false);
+        let line = |chrom: &str, start, end, name: &str, score: f64| {
+            (chrom.to_string(), start, end, name.to_string(), score)
+        };
+        let pieces: Vec<(Chrom, Coordinate, Coordinate, String, f64)> = bed
+            .subtract::<f64, _>(&other)
+            .into_iter()
+            .map(|l| {
+                (l.chrom, l.start, l.end, l.name.unwrap(), l.score.unwrap())
+            })
+            .collect();
+        assert_eq!(pieces, vec![
+            line("chr1", 100, 150, "a", 1.),
+            line("chr1", 170, 180, "a", 1.),
+            line("chr1", 190, 200, "a", 1.),
+            line("chr1", 320, 400, "b", 2.),
+            line("chr2", 0, 50, "e", 5.),
+            line("chr3", 15, 20, "f", 6.),
+        ]);
+        assert_eq!(
+            bed.subtract::<f64, _>(&other)[3].strand,
+            Some(Strand::Negative)
+        );
+        assert!(bed.subtract::<f64, _>(&bed).is_empty());
+    }
+
+    #[test]
+    fn test_coverage() {
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer
+                .write_fmt(format_args!(
+                    "chr10 0 100\n\
+                    chr1 100 200\n\
+                    chr1 150 250\n\
+                    chr2 5 5\n\
+                    chr1 300 310\n\
+                    chr1 305 308\n"
+                ))
+                .unwrap();
+        }
+        let bed = Bed::new(file.path().to_str().unwrap(), false);
+        assert_eq!(bed.coverage_per_chromosome(), vec![
+            ("chr1".to_string(), 160),
+            ("chr2".to_string(), 0),
+            ("chr10".to_string(), 100),
+        ]);
+        assert_eq!(bed.total_coverage(), 260);
+
+        let empty_file = NamedTempFile::new().unwrap();
+        let empty = Bed::new(empty_file.path().to_str().unwrap(), false);
+        assert!(empty.coverage_per_chromosome().is_empty());
+        assert_eq!(empty.total_coverage(), 0);
+    }
+
+    #[test]
+    fn test_merge() {
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer
+                .write_fmt(format_args!(
+                    "chr1 200 350 name_3 4.0\n\
+                    chr1 100 200 name_1 3.5\n\
+                    chr1 150 250 name_2 2\n\
+                    chr3 1000 3000 name_4 -0.3\n\
+                    chr1 400 450 name_5 -0.9\n\
+                    chr1 460 460 name_6 1\n\
+                    chr3 2500 3000 name_7 0.3\n\
+                    chr2 10 10 name_8 7\n"
+                ))
+                .unwrap();
+        }
+        let bed = Bed::new(file.path().to_str().unwrap(), false);
+        let to_tuples = |lines: Vec<BedDataLine<f64>>| {
+            lines
+                .into_iter()
+                .map(|l| (l.chrom, l.start, l.end, l.score.unwrap()))
+                .collect::<Vec<(Chrom, Coordinate, Coordinate, f64)>>()
+        };
+        assert_eq!(to_tuples(bed.merge(0, MergeScore::Sum)), vec![
+            ("chr1".to_string(), 100, 350, 9.5),
+            ("chr1".to_string(), 400, 450, -0.9),
+            ("chr1".to_string(), 460, 460, 1.),
+            ("chr2".to_string(), 10, 10, 7.),
+            ("chr3".to_string(), 1000, 3000, 0.),
+        ]);
+        assert_eq!(to_tuples(bed.merge(10, MergeScore::Max)), vec![
+            ("chr1".to_string(), 100, 350, 4.),
+            ("chr1".to_string(), 400, 460, 1.),
+            ("chr2".to_string(), 10, 10, 7.),
+            ("chr3".to_string(), 1000, 3000, 0.3),
+        ]);
+
+        let empty_file = NamedTempFile::new().unwrap();
+        let empty_bed = Bed::new(empty_file.path().to_str().unwrap(), false);
+        assert!(empty_bed.merge::<f64, _>(0, MergeScore::Sum).is_empty());
+    }
+
+    #[test]
+    fn test_intersect() {
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer
+                .write_fmt(format_args!(
+                    "chr1 100 500 a_1 1\n\
+                    chr1 600 700 a_2 2\n\
+                    chr2 10 20 a_3 3\n\
+                    chr3 0 100 a_4 4\n"
+                ))
+                .unwrap();
+        }
+        let other_file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&other_file);
+            writer
+                .write_fmt(format_args!(
+                    "chr1 150 200 b_1 5\n\
+                    chr1 120 450 b_2 6\n\
+                    chr1 500 600 b_3 7\n\
+                    chr1 650 800 b_4 8\n\
+                    chr1 300 300 b_5 9\n\
+                    chr2 20 30 b_6 10\n\
+                    chr3 0 100 b_7 11\n"
+                ))
+                .unwrap();
+        }
+        let bed = Bed::new(file.path().to_str().unwrap(), false);
+        let other = Bed::new(other_file.path().to_str().unwrap(), false);
+        let to_tuples = |records: Vec<IntersectionRecord<f64>>| {
+            let mut tuples = records
+                .into_iter()
+                .map(|r| {
+                    (
+                        r.chrom,
+                        r.start,
+                        r.end,
+                        r.line.name.unwrap(),
+                        r.other_line.name.unwrap(),
+                    )
+                })
+                .collect::<Vec<(Chrom, Coordinate, Coordinate, String, String)>>(
+                );
+            tuples.sort();
+            tuples
+        };
+        let s = |x: &str| x.to_string();
+        // b_1 and b_2 are nested in a_1, b_1 is nested in b_2, b_3 is
+        // book-ended with both a_1 and a_2, b_5 is empty, and b_6 is
+        // book-ended with a_3
+        assert_eq!(to_tuples(bed.intersect(&other)), vec![
+            (s("chr1"), 120, 450, s("a_1"), s("b_2")),
+            (s("chr1"), 150, 200, s("a_1"), s("b_1")),
+            (s("chr1"), 650, 700, s("a_2"), s("b_4")),
+            (s("chr3"), 0, 100, s("a_4"), s("b_7")),
+        ]);
+        assert_eq!(
+            to_tuples(other.intersect(&bed))
+                .into_iter()
+                .map(|(c, start, end, a, b)| (c, start, end, b, a))
+                .collect::<Vec<(Chrom, Coordinate, Coordinate, String, String)>>(
+                ),
+            to_tuples(bed.intersect(&other))
+        );
+
+        let empty_file = NamedTempFile::new().unwrap();
+        let empty_bed = Bed::new(empty_file.path().to_str().unwrap(), false);
+        assert!(bed.intersect::<f64, _>(&empty_bed).is_empty());
+    }
+
+    #[test]
+    fn test_sort() {
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer
+                .write_fmt(format_args!(
+                    "track name=test\n\
+                    chr10 5 10 name_1 1\n\
+                    chrX 1 2 name_2 2\n\
+                    chr2 30 40 name_3 3\n\
+                    chrM 0 5 name_4 4\n\
+                    chr2 10 50 name_5 5\n\
+                    chr1 100 200 name_6 6\n\
+                    chrY 3 4 name_7 7\n\
+                    chr2 10 20 name_8 8\n\
+                    chrUn_1 0 1 name_9 9\n"
+                ))
+                .unwrap();
+        }
+        let mut bed = Bed::new(file.path().to_str().unwrap(), false);
+        bed.sort().unwrap();
+        let contents = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            contents,
+            "track name=test\n\
+            chr1 100 200 name_6 6\n\
+            chr2 10 20 name_8 8\n\
+            chr2 10 50 name_5 5\n\
+            chr2 30 40 name_3 3\n\
+            chr10 5 10 name_1 1\n\
+            chrX 1 2 name_2 2\n\
+            chrY 3 4 name_7 7\n\
+            chrM 0 5 name_4 4\n\
+            chrUn_1 0 1 name_9 9\n"
+        );
+        assert!(!std::path::Path::new(&format!(
+            "{}.sort.tmp",
+            file.path().to_str().unwrap()
+        ))
+        .exists());
+    }
+
+    #[test]
+    fn test_natural_chrom_cmp() {
+        let mut chroms = vec![
+            "chrM",
+            "chr10",
+            "chrX",
+            "chr2",
+            "chr1_random",
+            "chrY",
+            "chr1",
+            "chr22",
+        ];
+        chroms.sort_by(|a, b| natural_chrom_cmp(a, b));
+        assert_eq!(chroms, vec![
+            "chr1",
+            "chr2",
+            "chr10",
+            "chr22",
+            "chrX",
+            "chrY",
+            "chrM",
+            "chr1_random",
+        ]);
+    }
 }
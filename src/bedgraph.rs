@@ -4,16 +4,20 @@
 use math::traits::ToIterator;
 use num::Float;
 use std::{
+    cmp::{max, min},
+    collections::HashMap,
     fmt::Debug,
-    fs::File,
-    io::{BufRead, BufReader},
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
     marker::PhantomData,
     str::FromStr,
 };
 
 use crate::{
+    bed::Bed,
+    error::Error,
     iter::{ChromIntervalValue, ToChromIntervalValueIter},
-    util::get_buf,
+    util::get_file_buf,
 };
 use math::set::contiguous_integer_set::ContiguousIntegerSet;
 
@@ -37,6 +41,421 @@ impl BedGraph {
     pub fn get_filepath(&self) -> &str {
         &self.filepath
     }
+
+    /// Summarizes the values of the intervals, where the mean and the
+    /// standard deviation are weighted by the interval lengths. If there are
+    /// no intervals, all the statistics except `total_bases` are NaN.
+    pub fn summary(&self) -> BedGraphSummary {
+        let mut summary = BedGraphSummary {
+            total_bases: 0,
+            mean: f64::NAN,
+            min: f32::NAN,
+            max: f32::NAN,
+            std: f64::NAN,
+        };
+        let mut mean = 0f64;
+        // the length-weighted sum of squared deviations from the mean
+        let mut sum_squared_deviations = 0f64;
+        let iter: BedGraphDataLineIter<f32> = self.to_iter();
+        for line in iter {
+            let length = (line.end_exclusive - line.start) as u64;
+            if length == 0 {
+                continue;
+            }
+            summary.total_bases += length;
+            summary.min = summary.min.min(line.value);
+            summary.max = summary.max.max(line.value);
+            let value = line.value as f64;
+            let delta = value - mean;
+            mean += delta * length as f64 / summary.total_bases as f64;
+            sum_squared_deviations += length as f64 * delta * (value - mean);
+        }
+        if summary.total_bases > 0 {
+            summary.mean = mean;
+            summary.std =
+                (sum_squared_deviations / summary.total_bases as f64).sqrt();
+        }
+        summary
+    }
+
+    /// Counts the base pairs whose values fall in each of the `bins` equal
+    /// width bins partitioning [`lo`, `hi`], where the last bin includes `hi`.
+    /// The base pairs with values outside [`lo`, `hi`] are not counted.
+    /// Returns an error if `bins` is 0, or if `lo` and `hi` are not finite
+    /// with `lo` less than `hi`.
+    pub fn histogram(
+        &self,
+        bins: usize,
+        lo: f32,
+        hi: f32,
+    ) -> Result<Vec<u64>, Error> {
+        if bins == 0 {
+            return Err(Error::Generic("bins must be positive".to_string()));
+        }
+        if !(lo.is_finite() && hi.is_finite() && lo < hi) {
+            return Err(Error::Generic(format!(
+                "lo ({}) and hi ({}) must be finite with lo less than hi",
+                lo, hi
+            )));
+        }
+        let mut counts = vec![0u64; bins];
+        let bin_width = (hi - lo) / bins as f32;
+        let iter: BedGraphDataLineIter<f32> = self.to_iter();
+        for line in iter {
+            if !(lo..=hi).contains(&line.value) {
+                continue;
+            }
+            let bin = min(((line.value - lo) / bin_width) as usize, bins - 1);
+            counts[bin] += (line.end_exclusive - line.start) as u64;
+        }
+        Ok(counts)
+    }
+
+    /// Rebins the track into the fixed windows [0, `window`),
+    /// [`window`, 2 * `window`), ... on each chromosome, where the value of
+    /// each window combines the values of the overlapping intervals according
+    /// to `aggregate`. The windows span up to the end of the last interval on
+    /// each chromosome, and the windows without any covered base are either
+    /// given the value 0 or omitted if `omit_empty` is true.
+    ///
+    /// The binned track is written to `out_path`, and the chromosomes are in
+    /// the order of their first appearance in this track. Returns an error if
+    /// the `window` is 0.
+    pub fn bin_fixed(
+        &self,
+        window: u64,
+        aggregate: Aggregate,
+        omit_empty: bool,
+        out_path: &str,
+    ) -> Result<BedGraph, Error> {
+        if window == 0 {
+            return Err(Error::Generic("window must be positive".to_string()));
+        }
+        let mut chrom_order: Vec<Chrom> = Vec::new();
+        let mut chrom_to_windows: HashMap<Chrom, Vec<WindowAggregate>> =
+            HashMap::new();
+        let iter: BedGraphDataLineIter<f32> = self.to_iter();
+        for line in iter {
+            let windows = match chrom_to_windows.get_mut(&line.chrom) {
+                Some(windows) => windows,
+                None => {
+                    chrom_order.push(line.chrom.clone());
+                    chrom_to_windows.entry(line.chrom.clone()).or_default()
+                }
+            };
+            let start = line.start as u64;
+            let end = line.end_exclusive as u64;
+            if end <= start {
+                continue;
+            }
+            let last_window = ((end - 1) / window) as usize;
+            if windows.len() <= last_window {
+                windows.resize(last_window + 1, WindowAggregate::new());
+            }
+            let first_window = (start / window) as usize;
+            for (k, w) in windows
+                .iter_mut()
+                .enumerate()
+                .take(last_window + 1)
+                .skip(first_window)
+            {
+                let window_start = k as u64 * window;
+                let num_bases =
+                    min(end, window_start + window) - max(start, window_start);
+                w.add(line.value, num_bases);
+            }
+        }
+
+        let mut writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(out_path)?,
+        );
+        for chrom in chrom_order.iter() {
+            for (k, w) in chrom_to_windows[chrom].iter().enumerate() {
+                if omit_empty && w.num_covered_bases == 0 {
+                    continue;
+                }
+                let window_start = k as u64 * window;
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}",
+                    chrom,
+                    window_start,
+                    window_start + window,
+                    w.get(aggregate)
+                )?;
+            }
+        }
+        writer.flush()?;
+        Ok(BedGraph::new(out_path, false))
+    }
+
+    /// Aggregates the values of the track over each interval in `regions`
+    /// according to `aggregate`, returning one value per interval in the
+    /// order of the `regions` file. Only the bases of an interval covered by
+    /// the track contribute, so that e.g. the mean is over the covered bases.
+    /// An interval without any covered base has the value NaN for `Mean`,
+    /// `Max` and `Min`, which are undefined without any value, and 0 for
+    /// `Sum`. This differs from the empty windows of `bin_fixed`, which are
+    /// given the value 0 for every `aggregate`.
+    ///
+    /// On each chromosome, the intervals of the track and the `regions` are
+    /// sorted by their start coordinates and swept with a pointer into the
+    /// track that only moves forward.
+    pub fn aggregate_over(
+        &self,
+        regions: &Bed,
+        aggregate: Aggregate,
+    ) -> Vec<f32> {
+        let mut chrom_to_track: HashMap<
+            Chrom,
+            Vec<(Coordinate, Coordinate, f32)>,
+        > = HashMap::new();
+        let iter: BedGraphDataLineIter<f32> = self.to_iter();
+        for line in iter.filter(|l| l.start < l.end_exclusive) {
+            chrom_to_track.entry(line.chrom).or_default().push((
+                line.start,
+                line.end_exclusive,
+                line.value,
+            ));
+        }
+        for intervals in chrom_to_track.values_mut() {
+            intervals.sort_by_key(|&(start, ..)| start);
+        }
+
+        let regions: Vec<(Chrom, Coordinate, Coordinate)> =
+            regions.to_coord_iter().collect();
+        let mut chrom_to_region_indices: HashMap<&str, Vec<usize>> =
+            HashMap::new();
+        for (i, (chrom, ..)) in regions.iter().enumerate() {
+            chrom_to_region_indices.entry(chrom).or_default().push(i);
+        }
+        let empty_value = match aggregate {
+            Aggregate::Sum => 0.,
+            Aggregate::Mean | Aggregate::Max | Aggregate::Min => f32::NAN,
+        };
+        let mut values = vec![empty_value; regions.len()];
+        for (chrom, mut region_indices) in chrom_to_region_indices {
+            let intervals = match chrom_to_track.get(chrom) {
+                Some(intervals) => intervals,
+                None => continue,
+            };
+            region_indices.sort_by_key(|&i| regions[i].1);
+            // the first interval of the track that can overlap the current
+            // region, which only moves forward as the regions are sorted by
+            // start
+            let mut j = 0;
+            for i in region_indices {
+                let (_, start, end) = regions[i];
+                while j < intervals.len() && intervals[j].1 <= start {
+                    j += 1;
+                }
+                let mut window = WindowAggregate::new();
+                for &(track_start, track_end, value) in intervals[j..].iter() {
+                    if track_start >= end {
+                        break;
+                    }
+                    let num_bases =
+                        min(end, track_end) - max(start, track_start);
+                    if num_bases > 0 {
+                        window.add(value, num_bases as u64);
+                    }
+                }
+                if window.num_covered_bases > 0 {
+                    values[i] = window.get(aggregate);
+                }
+            }
+        }
+        values
+    }
+
+    /// Combines this track with the `other` track base by base according to
+    /// `op`, where the bases not covered by a track have the value 0 in that
+    /// track, and writes the combined track to `out_path`. The tracks are
+    /// split at the boundaries of the intervals of both tracks, and each
+    /// segment between two consecutive boundaries that is covered by either
+    /// track becomes an interval of the combined track. Overlapping intervals
+    /// within a track add up.
+    ///
+    /// The chromosomes are in the order of their first appearance in this
+    /// track followed by those only in the `other` track.
+    pub fn combine(
+        &self,
+        other: &BedGraph,
+        op: TrackOp,
+        out_path: &str,
+    ) -> Result<BedGraph, Error> {
+        let mut chrom_order: Vec<Chrom> = Vec::new();
+        // the (position, track index, change in value, change in the number
+        // of covering intervals) at the boundaries of the intervals
+        let mut chrom_to_boundaries: HashMap<
+            Chrom,
+            Vec<(Coordinate, usize, f64, i64)>,
+        > = HashMap::new();
+        for (track_index, track) in [self, other].iter().enumerate() {
+            let iter: BedGraphDataLineIter<f32> = track.to_iter();
+            for line in iter {
+                if line.end_exclusive <= line.start {
+                    continue;
+                }
+                let boundaries = match chrom_to_boundaries.get_mut(&line.chrom)
+                {
+                    Some(boundaries) => boundaries,
+                    None => {
+                        chrom_order.push(line.chrom.clone());
+                        chrom_to_boundaries
+                            .entry(line.chrom.clone())
+                            .or_default()
+                    }
+                };
+                let value = line.value as f64;
+                boundaries.push((line.start, track_index, value, 1));
+                boundaries.push((line.end_exclusive, track_index, -value, -1));
+            }
+        }
+
+        let mut writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(out_path)?,
+        );
+        for chrom in chrom_order.iter() {
+            let boundaries = chrom_to_boundaries.get_mut(chrom).unwrap();
+            boundaries.sort_by_key(|b| b.0);
+            let mut values = [0f64; 2];
+            let mut num_covering = [0i64; 2];
+            let mut i = 0;
+            while i < boundaries.len() {
+                let position = boundaries[i].0;
+                while i < boundaries.len() && boundaries[i].0 == position {
+                    let (_, track_index, value_change, num_covering_change) =
+                        boundaries[i];
+                    values[track_index] += value_change;
+                    num_covering[track_index] += num_covering_change;
+                    // discards the rounding errors of the uncovered track
+                    if num_covering[track_index] == 0 {
+                        values[track_index] = 0.;
+                    }
+                    i += 1;
+                }
+                if i < boundaries.len()
+                    && (num_covering[0] > 0 || num_covering[1] > 0)
+                {
+                    writeln!(
+                        writer,
+                        "{}\t{}\t{}\t{}",
+                        chrom,
+                        position,
+                        boundaries[i].0,
+                        op.apply(values[0] as f32, values[1] as f32)
+                    )?;
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(BedGraph::new(out_path, false))
+    }
+}
+
+/// The operation applied base by base to the values `a` and `b` of two tracks
+/// in `BedGraph::combine`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TrackOp {
+    Add,
+    /// `a - b`
+    Subtract,
+    Multiply,
+    /// `a / b`, which is NaN if `b` is 0.
+    Ratio,
+}
+
+impl TrackOp {
+    fn apply(self, a: f32, b: f32) -> f32 {
+        match self {
+            TrackOp::Add => a + b,
+            TrackOp::Subtract => a - b,
+            TrackOp::Multiply => a * b,
+            TrackOp::Ratio => {
+                if b == 0. {
+                    f32::NAN
+                } else {
+                    a / b
+                }
+            }
+        }
+    }
+}
+
+/// How the values of the intervals overlapping a window are combined into the
+/// value of the window.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Aggregate {
+    /// The mean of the values weighted by the number of overlapping bases,
+    /// i.e. the mean over the covered bases in the window.
+    Mean,
+    /// The sum of the values times the number of overlapping bases.
+    Sum,
+    Max,
+    Min,
+}
+
+/// The aggregated values of the intervals overlapping a window.
+#[derive(Copy, Clone)]
+struct WindowAggregate {
+    num_covered_bases: u64,
+    weighted_sum: f64,
+    max: f32,
+    min: f32,
+}
+
+impl WindowAggregate {
+    fn new() -> WindowAggregate {
+        WindowAggregate {
+            num_covered_bases: 0,
+            weighted_sum: 0.,
+            max: f32::NEG_INFINITY,
+            min: f32::INFINITY,
+        }
+    }
+
+    fn add(&mut self, value: f32, num_bases: u64) {
+        self.num_covered_bases += num_bases;
+        self.weighted_sum += value as f64 * num_bases as f64;
+        self.max = self.max.max(value);
+        self.min = self.min.min(value);
+    }
+
+    fn get(&self, aggregate: Aggregate) -> f32 {
+        if self.num_covered_bases == 0 {
+            return 0.;
+        }
+        match aggregate {
+            Aggregate::Mean => {
+                (self.weighted_sum / self.num_covered_bases as f64) as f32
+            }
+            Aggregate::Sum => self.weighted_sum as f32,
+            Aggregate::Max => self.max,
+            Aggregate::Min => self.min,
+        }
+    }
+}
+
+/// The statistics of the values in a bedgraph, where each interval is
+/// weighted by its length.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BedGraphSummary {
+    /// The total length of the intervals.
+    pub total_bases: u64,
+    pub mean: f64,
+    pub min: f32,
+    pub max: f32,
+    /// The population standard deviation.
+    pub std: f64,
 }
 
 impl<D, E>
@@ -50,7 +469,7 @@ where
     E: Debug,
 {
     fn to_iter(&self) -> BedGraphDataLineIter<D> {
-        let buf = get_buf(&self.filepath).unwrap();
+        let buf = get_file_buf(&self.filepath).unwrap();
         BedGraphDataLineIter {
             buf,
             filename: self.filepath.clone(),
@@ -165,12 +584,13 @@ impl<D: Float + FromStr<Err = E>, E: Debug> Iterator
 #[cfg(test)]
 mod tests {
     use crate::{
-        bedgraph::{BedGraph, BedGraphDataLineIter},
+        bed::Bed,
+        bedgraph::{Aggregate, BedGraph, BedGraphDataLineIter, TrackOp},
         iter::{ChromIntervalValue, ToChromIntervalValueIter},
     };
     use math::{
         partition::integer_interval_map::IntegerIntervalMap,
-        set::contiguous_integer_set::ContiguousIntegerSet,
+        set::contiguous_integer_set::ContiguousIntegerSet, traits::ToIterator,
     };
     use std::io::{BufWriter, Write};
     use tempfile::NamedTempFile;
@@ -271,5 +691,235 @@ mod tests {
         assert_eq!(iter.next(), None);
     }
 
+    #[test]
+    fn test_summary_and_histogram() {
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer
+                .write_fmt(format_args!(
+                    "track type=bedGraph\n\
+                    chr1 100 200 1.5\n\
+                    chr1 200 250 4\n\
+                    chr2 0 50 -1\n\
+                    chr3 10 10 9\n"
+                ))
+                .unwrap();
+        }
+        let bedgraph = BedGraph::new(file.path().to_str().unwrap(), false);
+        let summary = bedgraph.summary();
+        assert_eq!(summary.total_bases, 200);
+        assert_eq!(summary.min, -1.);
+        assert_eq!(summary.max, 4.);
+        // (100 * 1.5 + 50 * 4 - 50) / 200
+        assert!((summary.mean - 1.5).abs() < 1e-9);
+        // (50 * 2.5^2 + 50 * 2.5^2) / 200
+        assert!((summary.std - 3.125f64.sqrt()).abs() < 1e-9);
+
+        assert_eq!(bedgraph.histogram(5, -1., 4.).unwrap(), vec![
+            50, 0, 100, 0, 50
+        ]);
+        assert_eq!(bedgraph.histogram(2, 0., 2.).unwrap(), vec![0, 100]);
+        assert!(bedgraph.histogram(0, 0., 2.).is_err());
+        assert!(bedgraph.histogram(2, 2., 2.).is_err());
+        assert!(bedgraph.histogram(2, 2., 0.).is_err());
+        assert!(bedgraph.histogram(2, f32::NAN, 2.).is_err());
+        assert!(bedgraph.histogram(2, 0., f32::INFINITY).is_err());
+
+        let empty_file = NamedTempFile::new().unwrap();
+        let empty = BedGraph::new(empty_file.path().to_str().unwrap(), false);
+        let summary = empty.summary();
+        assert_eq!(summary.total_bases, 0);
+        assert!(summary.mean.is_nan() && summary.std.is_nan());
+        assert_eq!(empty.histogram(3, 0., 1.).unwrap(), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_bin_fixed() {
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer
+                .write_fmt(format_args!(
+                    "chr2 0 50 2\n\
+                    chr1 100 150 1\n\
+                    chr1 150 250 3\n\
+                    chr1 350 360 -2\n"
+                ))
+                .unwrap();
+        }
+        let bedgraph = BedGraph::new(file.path().to_str().unwrap(), false);
+        let out_path = NamedTempFile::new().unwrap().into_temp_path();
+        let out_path_str = out_path.to_str().unwrap();
+        let binned_lines = |aggregate: Aggregate, omit_empty: bool| {
+            let binned = bedgraph
+                .bin_fixed(100, aggregate, omit_empty, out_path_str)
+                .unwrap();
+            let iter: BedGraphDataLineIter<f32> = binned.to_iter();
+            iter.map(|l| (l.chrom, l.start, l.end_exclusive, l.value))
+                .collect::<Vec<(String, i64, i64, f32)>>()
+        };
+        let line = |chrom: &str, start: i64, value: f32| {
+            (chrom.to_string(), start, start + 100, value)
+        };
+        assert_eq!(binned_lines(Aggregate::Mean, false), vec![
+            line("chr2", 0, 2.),
+            line("chr1", 0, 0.),
+            line("chr1", 100, 2.),
+            line("chr1", 200, 3.),
+            line("chr1", 300, -2.),
+        ]);
+        assert_eq!(binned_lines(Aggregate::Sum, true), vec![
+            line("chr2", 0, 100.),
+            line("chr1", 100, 200.),
+            line("chr1", 200, 150.),
+            line("chr1", 300, -20.),
+        ]);
+        assert_eq!(
+            binned_lines(Aggregate::Max, true)[1],
+            line("chr1", 100, 3.)
+        );
+        assert_eq!(
+            binned_lines(Aggregate::Min, true)[1],
+            line("chr1", 100, 1.)
+        );
+        assert!(bedgraph
+            .bin_fixed(0, Aggregate::Mean, false, out_path_str)
+            .is_err());
+    }
+
+    #[test]
+    fn test_aggregate_over() {
+        let write_file = |content: &str| {
+            let file = NamedTempFile::new().unwrap();
+            {
+                let mut writer = BufWriter::new(&file);
+                writer.write_all(content.as_bytes()).unwrap();
+            }
+            file
+        };
+        let track_file = write_file(
+            "chr1 150 200 3\n\
+            chr1 100 150 1\n\
+            chr1 300 400 -2\n\
+            chr2 0 100 5\n",
+        );
+        let regions_file = write_file(
+            "chr1 120 180\n\
+            chr2 50 150\n\
+            chr1 0 100\n\
+            chr1 190 350\n\
+            chr3 0 10\n\
+            chr1 100 200\n",
+        );
+        let bedgraph =
+            BedGraph::new(track_file.path().to_str().unwrap(), false);
+        let regions = Bed::new(regions_file.path().to_str().unwrap(), false);
+        // the uncovered regions are NaN, which is compared through its bits
+        let bits = |aggregate: Aggregate| -> Vec<u32> {
+            bedgraph
+                .aggregate_over(&regions, aggregate)
+                .iter()
+                .map(|x| x.to_bits())
+                .collect()
+        };
+        let expected_bits = |values: Vec<f32>| -> Vec<u32> {
+            values.iter().map(|x| x.to_bits()).collect()
+        };
+        let nan = f32::NAN;
+        // (30 * 1 + 30 * 3) / 60, 5, none, (10 * 3 - 50 * 2) / 60, none,
+        // (50 * 1 + 50 * 3) / 100
+        assert_eq!(
+            bits(Aggregate::Mean),
+            expected_bits(vec![2., 5., nan, -70. / 60., nan, 2.])
+        );
+        assert_eq!(bedgraph.aggregate_over(&regions, Aggregate::Sum), vec![
+            120., 250., 0., -70., 0., 200.
+        ]);
+        assert_eq!(
+            bits(Aggregate::Max),
+            expected_bits(vec![3., 5., nan, 3., nan, 3.])
+        );
+        assert_eq!(
+            bits(Aggregate::Min),
+            expected_bits(vec![1., 5., nan, -2., nan, 1.])
+        );
+    }
+
+    #[test]
+    fn test_combine() {
+        let write_track = |content: &str| {
+            let file = NamedTempFile::new().unwrap();
+            {
+                let mut writer = BufWriter::new(&file);
+                writer.write_all(content.as_bytes()).unwrap();
+            }
+            file
+        };
+        let a_file = write_track(
+            "chr1 0 100 2\n\
+            chr1 150 200 1\n\
+            chr2 0 10 5\n",
+        );
+        let b_file = write_track(
+            "chr3 0 10 1\n\
+            chr1 50 150 4\n\
+            chr1 300 300 7\n",
+        );
+        let a = BedGraph::new(a_file.path().to_str().unwrap(), false);
+        let b = BedGraph::new(b_file.path().to_str().unwrap(), false);
+        let out_path = NamedTempFile::new().unwrap().into_temp_path();
+        let out_path_str = out_path.to_str().unwrap();
+        let combined_lines = |op: TrackOp| {
+            let combined = a.combine(&b, op, out_path_str).unwrap();
+            let iter: BedGraphDataLineIter<f32> = combined.to_iter();
+            iter.map(|l| (l.chrom, l.start, l.end_exclusive, l.value))
+                .collect::<Vec<(String, i64, i64, f32)>>()
+        };
+        let intervals = [
+            ("chr1", 0, 50),
+            ("chr1", 50, 100),
+            ("chr1", 100, 150),
+            ("chr1", 150, 200),
+            ("chr2", 0, 10),
+            ("chr3", 0, 10),
+        ];
+        let expected_lines = |values: &[f32]| {
+            intervals
+                .iter()
+                .zip(values.iter())
+                .map(|(&(chrom, start, end), &value)| {
+                    (chrom.to_string(), start, end, value)
+                })
+                .collect::<Vec<(String, i64, i64, f32)>>()
+        };
+        assert_eq!(
+            combined_lines(TrackOp::Add),
+            expected_lines(&[2., 6., 4., 1., 5., 1.])
+        );
+        assert_eq!(
+            combined_lines(TrackOp::Subtract),
+            expected_lines(&[2., -2., -4., 1., 5., -1.])
+        );
+        assert_eq!(
+            combined_lines(TrackOp::Multiply),
+            expected_lines(&[0., 8., 0., 0., 0., 0.])
+        );
+        let ratio = combined_lines(TrackOp::Ratio);
+        assert_eq!(ratio.len(), 6);
+        for (line, expected) in ratio.iter().zip(
+            expected_lines(&[f32::NAN, 0.5, 0., f32::NAN, f32::NAN, 0.]).iter(),
+        ) {
+            assert_eq!(
+                (&line.0, line.1, line.2),
+                (&expected.0, expected.1, expected.2)
+            );
+            assert!(
+                line.3 == expected.3
+                    || (line.3.is_nan() && expected.3.is_nan())
+            );
+        }
+    }
+
     // TODO: test binarize_score
 }
@@ -1,5 +1,6 @@
+use crate::plink_bed::usize_div_ceil;
 use std::{
-    cmp::min,
+    cmp::{max, min},
     io,
     io::{BufReader, Read, Seek, SeekFrom},
 };
@@ -8,6 +9,8 @@ pub struct ByteChunkIter<R> {
     pub start_byte_index: usize,
     end_byte_index_exclusive: usize,
     current_byte_index: usize,
+    /// The exclusive end of the bytes not yet yielded by `next_back`.
+    back_byte_index_exclusive: usize,
     pub chunk_size: usize,
     buf: BufReader<R>,
 }
@@ -26,10 +29,20 @@ impl<R: Seek> ByteChunkIter<R> {
             start_byte_index,
             end_byte_index_exclusive,
             current_byte_index: start_byte_index,
+            back_byte_index_exclusive: end_byte_index_exclusive,
             chunk_size,
             buf,
         }
     }
+
+    /// Repositions the iterator so that it yields the chunks from the
+    /// `chunk_index`-th chunk up to `end_byte_index_exclusive`, where the
+    /// chunks are delimited every `chunk_size` bytes from `start_byte_index`.
+    pub fn seek_to_chunk(&mut self, chunk_index: usize) -> io::Result<u64> {
+        let byte_index = self.start_byte_index + chunk_index * self.chunk_size;
+        self.back_byte_index_exclusive = self.end_byte_index_exclusive;
+        self.seek(SeekFrom::Start(byte_index as u64))
+    }
 }
 
 impl<R: Seek> Seek for ByteChunkIter<R> {
@@ -44,11 +57,11 @@ impl<R: Read> Iterator for ByteChunkIter<R> {
     type Item = Vec<u8>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current_byte_index >= self.end_byte_index_exclusive {
+        if self.current_byte_index >= self.back_byte_index_exclusive {
             None
         } else {
             let len = min(
-                self.end_byte_index_exclusive - self.current_byte_index,
+                self.back_byte_index_exclusive - self.current_byte_index,
                 self.chunk_size,
             );
             let mut bytes = vec![0u8; len];
@@ -57,4 +70,117 @@ impl<R: Read> Iterator for ByteChunkIter<R> {
             Some(bytes)
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let num_bytes = self
+            .back_byte_index_exclusive
+            .saturating_sub(self.current_byte_index);
+        let num_chunks = usize_div_ceil(num_bytes, self.chunk_size);
+        (num_chunks, Some(num_chunks))
+    }
+}
+
+impl<R: Read> ExactSizeIterator for ByteChunkIter<R> {}
+
+impl<R: Read + Seek> DoubleEndedIterator for ByteChunkIter<R> {
+    /// Yields the chunks from the back, where the chunk boundaries are the
+    /// same as in the forward direction, so the first chunk yielded is the
+    /// final partial chunk if `(end - start) % chunk_size != 0`.
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.current_byte_index >= self.back_byte_index_exclusive {
+            None
+        } else {
+            let chunk_start = self.start_byte_index
+                + (self.back_byte_index_exclusive - 1 - self.start_byte_index)
+                    / self.chunk_size
+                    * self.chunk_size;
+            let chunk_start = max(chunk_start, self.current_byte_index);
+            let mut bytes =
+                vec![0u8; self.back_byte_index_exclusive - chunk_start];
+            self.buf.seek(SeekFrom::Start(chunk_start as u64)).unwrap();
+            self.buf.read_exact(bytes.as_mut_slice()).unwrap();
+            self.buf
+                .seek(SeekFrom::Start(self.current_byte_index as u64))
+                .unwrap();
+            self.back_byte_index_exclusive = chunk_start;
+            Some(bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Cursor};
+
+    use super::ByteChunkIter;
+
+    fn get_iter(
+        start: usize,
+        end: usize,
+        chunk_size: usize,
+    ) -> ByteChunkIter<Cursor<Vec<u8>>> {
+        let bytes: Vec<u8> = (0..20).collect();
+        ByteChunkIter::new(
+            BufReader::new(Cursor::new(bytes)),
+            start,
+            end,
+            chunk_size,
+        )
+    }
+
+    #[test]
+    fn test_next_back() {
+        let mut iter = get_iter(2, 12, 4);
+        assert_eq!(iter.next_back(), Some(vec![10, 11]));
+        assert_eq!(iter.next_back(), Some(vec![6, 7, 8, 9]));
+        assert_eq!(iter.next_back(), Some(vec![2, 3, 4, 5]));
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(iter.next(), None);
+
+        let mut iter = get_iter(2, 14, 4);
+        assert_eq!(iter.next_back(), Some(vec![10, 11, 12, 13]));
+        assert_eq!(iter.next(), Some(vec![2, 3, 4, 5]));
+        assert_eq!(iter.next_back(), Some(vec![6, 7, 8, 9]));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        let mut iter = get_iter(2, 12, 4);
+        assert_eq!(iter.next(), Some(vec![2, 3, 4, 5]));
+        assert_eq!(iter.next_back(), Some(vec![10, 11]));
+        assert_eq!(iter.next(), Some(vec![6, 7, 8, 9]));
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn test_seek_to_chunk() {
+        let mut iter = get_iter(2, 12, 4);
+        iter.seek_to_chunk(2).unwrap();
+        assert_eq!(iter.next(), Some(vec![10, 11]));
+        assert_eq!(iter.next(), None);
+
+        iter.seek_to_chunk(0).unwrap();
+        assert_eq!(iter.next(), Some(vec![2, 3, 4, 5]));
+        assert_eq!(iter.next_back(), Some(vec![10, 11]));
+        iter.seek_to_chunk(1).unwrap();
+        assert_eq!(iter.next(), Some(vec![6, 7, 8, 9]));
+        assert_eq!(iter.next(), Some(vec![10, 11]));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_len() {
+        let mut iter = get_iter(2, 12, 4);
+        assert_eq!(iter.len(), 3);
+        iter.next();
+        assert_eq!(iter.len(), 2);
+        iter.next_back();
+        assert_eq!(iter.len(), 1);
+        iter.next();
+        assert_eq!(iter.len(), 0);
+        assert_eq!(iter.next(), None);
+
+        assert_eq!(get_iter(2, 14, 4).len(), 3);
+        assert_eq!(get_iter(2, 2, 4).len(), 0);
+        assert_eq!(get_iter(0, 20, 4).count(), 5);
+    }
 }
@@ -1,9 +1,25 @@
 use std::{fmt, io};
 
 pub enum Error {
-    IO { why: String, io_error: io::Error },
+    IO {
+        why: String,
+        io_error: io::Error,
+    },
     BadFormat(String),
     Generic(String),
+    SnpIndexOutOfRange {
+        index: usize,
+        total: usize,
+    },
+    FileIndexOutOfRange {
+        index: usize,
+        num_files: usize,
+    },
+    /// The number of samples differs across the files, where `counts[i]` is
+    /// the number of samples in the i-th file.
+    InconsistentSampleCount {
+        counts: Vec<usize>,
+    },
 }
 
 impl fmt::Display for Error {
@@ -14,18 +30,40 @@ impl fmt::Display for Error {
                 why, ..
             } => write!(f, "IO error: {}", why),
             Error::Generic(why) => write!(f, "Generic error: {}", why),
+            Error::SnpIndexOutOfRange {
+                index,
+                total,
+            } => write!(f, "SNP index out of range: {} >= {}", index, total),
+            Error::FileIndexOutOfRange {
+                index,
+                num_files,
+            } => {
+                write!(f, "file index out of range: {} >= {}", index, num_files)
+            }
+            Error::InconsistentSampleCount {
+                counts,
+            } => write!(
+                f,
+                "inconsistent number of samples across the files: {:?}",
+                counts
+            ),
         }
     }
 }
 
 impl fmt::Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Error::BadFormat(why) => write!(f, "Bad format: {}", why),
             Error::IO {
-                why, ..
-            } => write!(f, "IO error: {}", why),
-            Error::Generic(why) => write!(f, "Generic error: {}", why),
+                io_error, ..
+            } => Some(io_error),
+            _ => None,
         }
     }
 }
@@ -44,3 +82,31 @@ impl From<String> for Error {
         Error::Generic(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error as StdError, io};
+
+    use super::Error;
+
+    #[test]
+    fn test_source() {
+        let error: Error =
+            io::Error::new(io::ErrorKind::NotFound, "no such file").into();
+        assert_eq!(error.to_string(), "IO error: no such file");
+        let source = error.source().unwrap();
+        assert_eq!(source.to_string(), "no such file");
+        assert_eq!(
+            source.downcast_ref::<io::Error>().unwrap().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert!(Error::BadFormat("bad".to_string()).source().is_none());
+
+        // converts into a boxed std error with `?`
+        fn open() -> Result<(), Box<dyn StdError>> {
+            Err(Error::Generic("failed".to_string()))?;
+            Ok(())
+        }
+        assert_eq!(open().unwrap_err().to_string(), "Generic error: failed");
+    }
+}
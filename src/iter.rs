@@ -7,7 +7,10 @@ use math::{
     },
 };
 use num::{Integer, Num};
-use std::collections::HashMap;
+use std::{
+    cmp::min,
+    collections::{HashMap, VecDeque},
+};
 
 pub trait ChromIntervalValue<T, V>
 where
@@ -63,3 +66,175 @@ where
         Ok(chrom_to_interval_map)
     }
 }
+
+/// Groups the consecutive items of `iter` sharing the same key into a
+/// `(key, items)` pair, e.g. the sorted bed intervals on each chromosome.
+/// Only one group is held in memory at a time, and the same key can appear in
+/// more than one group if the items are not sorted by the key.
+pub fn chunk_by<I, F, K>(iter: I, key: F) -> ChunkBy<I::IntoIter, F, K>
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item) -> K,
+    K: PartialEq, {
+    ChunkBy {
+        iter: iter.into_iter(),
+        key,
+        next_item: None,
+    }
+}
+
+/// The iterator returned by `chunk_by`.
+pub struct ChunkBy<I: Iterator, F, K> {
+    iter: I,
+    key: F,
+    /// The first item of the next group along with its key.
+    next_item: Option<(K, I::Item)>,
+}
+
+impl<I, F, K> Iterator for ChunkBy<I, F, K>
+where
+    I: Iterator,
+    F: FnMut(&I::Item) -> K,
+    K: PartialEq,
+{
+    type Item = (K, Vec<I::Item>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (group_key, first_item) = match self.next_item.take() {
+            Some(next_item) => next_item,
+            None => {
+                let item = self.iter.next()?;
+                ((self.key)(&item), item)
+            }
+        };
+        let mut group = vec![first_item];
+        for item in &mut self.iter {
+            let k = (self.key)(&item);
+            if k == group_key {
+                group.push(item);
+            } else {
+                self.next_item = Some((k, item));
+                break;
+            }
+        }
+        Some((group_key, group))
+    }
+}
+
+/// Wraps an iterator to look ahead at any number of its next items, which are
+/// buffered until they are consumed.
+pub struct LookAhead<I: Iterator> {
+    iter: I,
+    buffer: VecDeque<I::Item>,
+}
+
+impl<I: Iterator> LookAhead<I> {
+    pub fn new(iter: I) -> LookAhead<I> {
+        LookAhead {
+            iter,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// Returns the next `n` items without consuming them, or all the remaining
+    /// items if there are fewer than `n`.
+    pub fn peek_n(&mut self, n: usize) -> &[I::Item] {
+        while self.buffer.len() < n {
+            match self.iter.next() {
+                Some(item) => self.buffer.push_back(item),
+                None => break,
+            }
+        }
+        let num_items = min(n, self.buffer.len());
+        &self.buffer.make_contiguous()[..num_items]
+    }
+
+    /// Returns the next item without consuming it.
+    #[inline]
+    pub fn peek(&mut self) -> Option<&I::Item> {
+        self.peek_n(1).first()
+    }
+}
+
+impl<I: Iterator> Iterator for LookAhead<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.buffer.pop_front() {
+            Some(item) => Some(item),
+            None => self.iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.iter.size_hint();
+        let num_buffered = self.buffer.len();
+        (
+            lower.saturating_add(num_buffered),
+            upper.and_then(|upper| upper.checked_add(num_buffered)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk_by, LookAhead};
+
+    #[test]
+    fn test_chunk_by() {
+        let intervals = vec![
+            ("chr1", 1),
+            ("chr1", 5),
+            ("chr2", 3),
+            ("chr3", 2),
+            ("chr3", 8),
+            ("chr3", 9),
+            ("chr1", 20),
+        ];
+        let groups: Vec<(&str, Vec<i32>)> =
+            chunk_by(intervals, |(chrom, _)| *chrom)
+                .map(|(chrom, group)| {
+                    (chrom, group.into_iter().map(|(_, x)| x).collect())
+                })
+                .collect();
+        assert_eq!(groups, vec![
+            ("chr1", vec![1, 5]),
+            ("chr2", vec![3]),
+            ("chr3", vec![2, 8, 9]),
+            ("chr1", vec![20]),
+        ]);
+    }
+
+    #[test]
+    fn test_chunk_by_empty() {
+        let mut iter = chunk_by(Vec::<i32>::new(), |x| x % 2);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_chunk_by_single_group() {
+        let groups: Vec<(bool, Vec<i32>)> =
+            chunk_by(vec![2, 4, 6, 8], |x| x % 2 == 0).collect();
+        assert_eq!(groups, vec![(true, vec![2, 4, 6, 8])]);
+    }
+
+    #[test]
+    fn test_look_ahead() {
+        let mut iter = LookAhead::new(vec![1, 3, 5, 7, 9].into_iter());
+        assert_eq!(iter.peek_n(0), &[] as &[i32]);
+        assert_eq!(iter.peek_n(2), &[1, 3]);
+        assert_eq!(iter.size_hint(), (5, Some(5)));
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.peek(), Some(&3));
+        assert_eq!(iter.peek_n(3), &[3, 5, 7]);
+        assert_eq!(iter.next(), Some(3));
+        assert_eq!(iter.peek_n(10), &[5, 7, 9]);
+        assert_eq!(iter.collect::<Vec<i32>>(), vec![5, 7, 9]);
+
+        let mut empty = LookAhead::new(Vec::<i32>::new().into_iter());
+        assert_eq!(empty.peek_n(3), &[] as &[i32]);
+        assert_eq!(empty.peek(), None);
+        assert_eq!(empty.next(), None);
+    }
+}
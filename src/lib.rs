@@ -1,5 +1,6 @@
 #![feature(bufreader_seek_relative)]
 #![feature(type_ascription)]
+#![cfg_attr(feature = "simd", feature(portable_simd))]
 
 pub mod bed;
 pub mod bedgraph;
@@ -9,5 +10,6 @@ pub mod iter;
 pub mod peak_file;
 pub mod plink_bed;
 pub mod plink_bim;
+pub mod plink_fam;
 pub mod traits;
 pub mod util;
@@ -1,7 +1,8 @@
 use std::{
+    cmp::min,
     collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
 };
 
 use math::{
@@ -13,8 +14,10 @@ use math::{
 };
 
 use crate::{
+    bed::Bed,
     error::Error,
-    util::{get_buf, Strand},
+    traits::{Chrom, Coordinate, Peak},
+    util::{get_file_buf, natural_chrom_cmp, Strand},
 };
 
 pub struct PeakFile {
@@ -29,7 +32,7 @@ impl PeakFile {
     }
 
     pub fn iter(&self) -> Result<PeakFileIter, Error> {
-        Ok(PeakFileIter::new(get_buf(&self.filepath)?))
+        Ok(PeakFileIter::new(get_file_buf(&self.filepath)?))
     }
 
     pub fn get_chrom_to_peak_locations(
@@ -74,6 +77,489 @@ impl PeakFile {
     pub fn get_filepath(&self) -> &str {
         &self.filepath
     }
+
+    /// Parses all the records in the ENCODE narrowPeak file at `path`, as
+    /// specified in https://genome.ucsc.edu/FAQ/FAQformat.html#format12
+    ///
+    /// A `-1` in the p-value or q-value column indicates that the value is
+    /// not set, in which case the corresponding field will be `None`.
+    pub fn read_narrow_peak(path: &str) -> Result<Vec<NarrowPeak>, Error> {
+        read_peak_records(path, |toks, line_num| {
+            if toks.len() < 10 {
+                return Err(Error::BadFormat(format!(
+                    "expected 10 columns on line {} of {} but found {}",
+                    line_num,
+                    path,
+                    toks.len()
+                )));
+            }
+            let p = parse_broad_peak_columns(toks, line_num, path)?;
+            Ok(NarrowPeak {
+                chrom: p.chrom,
+                start: p.start,
+                end: p.end,
+                name: p.name,
+                score: p.score,
+                strand: p.strand,
+                signal_value: p.signal_value,
+                p_value: p.p_value,
+                q_value: p.q_value,
+                peak: toks[9]
+                    .parse()
+                    .map_err(|_| bad_column(path, line_num, "peak"))?,
+            })
+        })
+    }
+
+    /// Parses all the records in the ENCODE broadPeak file at `path`, as
+    /// specified in https://genome.ucsc.edu/FAQ/FAQformat.html#format13
+    ///
+    /// The broadPeak format is the narrowPeak format without the summit
+    /// column, so every record must have exactly 9 columns. As with
+    /// narrowPeak, a `-1` in the p-value or q-value column is read as `None`.
+    pub fn read_broad_peak(path: &str) -> Result<Vec<BroadPeak>, Error> {
+        read_peak_records(path, |toks, line_num| {
+            if toks.len() != 9 {
+                return Err(Error::BadFormat(format!(
+                    "expected 9 columns on line {} of {} but found {}",
+                    line_num,
+                    path,
+                    toks.len()
+                )));
+            }
+            parse_broad_peak_columns(toks, line_num, path)
+        })
+    }
+
+    /// Retains the `peaks` whose q-value is at most `max_q`, where `max_q` is
+    /// a linear q-value such as 0.05. The q-value column holds `-log10(q)` as
+    /// in the ENCODE formats, so a peak is retained if its column is at least
+    /// `-log10(max_q)`. Peaks without a q-value are dropped unless
+    /// `keep_missing` is true. The retained peaks are in their original order.
+    pub fn filter_by_qvalue<P: Peak>(
+        peaks: Vec<P>,
+        max_q: f64,
+        keep_missing: bool,
+    ) -> Vec<P> {
+        let min_neg_log10_q = -max_q.log10();
+        peaks
+            .into_iter()
+            .filter(|p| match p.q_value() {
+                Some(neg_log10_q) => neg_log10_q >= min_neg_log10_q,
+                None => keep_missing,
+            })
+            .collect()
+    }
+
+    /// Retains the `peaks` whose signal value is at least `min_signal`, in
+    /// their original order.
+    pub fn filter_by_signal<P: Peak>(peaks: Vec<P>, min_signal: f64) -> Vec<P> {
+        peaks
+            .into_iter()
+            .filter(|p| p.signal_value() >= min_signal)
+            .collect()
+    }
+
+    /// Returns the `(chrom, summit)` of each narrowPeak record in the file,
+    /// where `summit = start + peak`. Records without a called summit, i.e.
+    /// with a peak offset of -1, are skipped.
+    pub fn summits(&self) -> Result<Vec<(String, u64)>, Error> {
+        Ok(PeakFile::read_narrow_peak(&self.filepath)?
+            .into_iter()
+            .filter(|p| p.peak >= 0)
+            .map(|p| (p.chrom, p.start as u64 + p.peak as u64))
+            .collect())
+    }
+
+    /// Returns the `(chrom, start, end)` window [summit - flank, summit +
+    /// flank) around each of the `summits`, where the start is clamped at
+    /// zero.
+    pub fn summit_intervals(
+        &self,
+        flank: u64,
+    ) -> Result<Vec<(String, u64, u64)>, Error> {
+        Ok(self
+            .summits()?
+            .into_iter()
+            .map(|(chrom, summit)| {
+                (chrom, summit.saturating_sub(flank), summit + flank)
+            })
+            .collect())
+    }
+
+    /// Returns `(peak_index, feature_index, distance)` for each peak in the
+    /// file, where `feature_index` is the index of the data line in
+    /// `features` closest to the peak, or `None` if there are no features on
+    /// the chromosome of the peak, in which case `distance` is 0.
+    ///
+    /// As in `bedtools closest -D ref`, the `distance` is 0 if the feature
+    /// overlaps the peak, and is otherwise one more than the number of base
+    /// pairs between them, negated if the feature is upstream of the peak,
+    /// i.e. has smaller coordinates. Ties are broken in favor of the upstream
+    /// feature.
+    ///
+    /// The features on each chromosome are sorted once, so that each peak is
+    /// resolved with a binary search.
+    pub fn nearest_feature(
+        &self,
+        features: &Bed,
+    ) -> Result<Vec<(usize, Option<usize>, i64)>, Error> {
+        type Features = Vec<(Coordinate, Coordinate, usize)>;
+        type PrefixMaxEnds = Vec<(Coordinate, usize)>;
+
+        // for each chromosome, the (start, end, index) of the features sorted
+        // by start, and the (end, index) of the feature with the largest end
+        // among each prefix of the sorted features
+        let mut chrom_to_features: HashMap<Chrom, Features> = HashMap::new();
+        for (i, (chrom, start, end)) in features.to_coord_iter().enumerate() {
+            chrom_to_features
+                .entry(chrom)
+                .or_default()
+                .push((start, end, i));
+        }
+        let chrom_to_sorted_features: HashMap<
+            Chrom,
+            (Features, PrefixMaxEnds),
+        > = chrom_to_features
+            .into_iter()
+            .map(|(chrom, mut sorted)| {
+                sorted.sort_by_key(|&(start, _, i)| (start, i));
+                let mut prefix_max_ends: PrefixMaxEnds =
+                    Vec::with_capacity(sorted.len());
+                for &(_, end, i) in sorted.iter() {
+                    let max_end = match prefix_max_ends.last() {
+                        Some(&(max_end, j)) if max_end >= end => (max_end, j),
+                        _ => (end, i),
+                    };
+                    prefix_max_ends.push(max_end);
+                }
+                (chrom, (sorted, prefix_max_ends))
+            })
+            .collect();
+
+        let peaks = read_peak_records(&self.filepath, |toks, line_num| {
+            if toks.len() < 3 {
+                return Err(Error::BadFormat(format!(
+                    "expected at least 3 columns on line {} of {} but found {}",
+                    line_num,
+                    self.filepath,
+                    toks.len()
+                )));
+            }
+            let bad_format =
+                |column: &str| bad_column(&self.filepath, line_num, column);
+            let start: Coordinate =
+                toks[1].parse().map_err(|_| bad_format("start"))?;
+            let end: Coordinate =
+                toks[2].parse().map_err(|_| bad_format("end"))?;
+            Ok((toks[0].to_string(), start, end))
+        })?;
+
+        Ok(peaks
+            .into_iter()
+            .enumerate()
+            .map(|(peak_index, (chrom, start, end))| {
+                let (sorted, prefix_max_ends) =
+                    match chrom_to_sorted_features.get(&chrom) {
+                        Some(features) => features,
+                        None => return (peak_index, None, 0),
+                    };
+                // the features in sorted[..k] start before the peak ends
+                let k = sorted.partition_point(|&(s, _, _)| s < end);
+                let upstream = if k > 0 {
+                    let (max_end, i) = prefix_max_ends[k - 1];
+                    if max_end > start {
+                        return (peak_index, Some(i), 0);
+                    }
+                    Some((start - max_end + 1, i))
+                } else {
+                    None
+                };
+                let downstream =
+                    sorted.get(k).map(|&(s, _, i)| (s - end + 1, i));
+                match (upstream, downstream) {
+                    (Some((u, i)), Some((d, _))) if u <= d => {
+                        (peak_index, Some(i), -u)
+                    }
+                    (_, Some((d, j))) => (peak_index, Some(j), d),
+                    (Some((u, i)), None) => (peak_index, Some(i), -u),
+                    (None, None) => (peak_index, None, 0),
+                }
+            })
+            .collect())
+    }
+
+    /// Builds the consensus peaks of the replicate `peaksets`, each of which
+    /// is a narrowPeak or broadPeak file, and writes them to `out_path` as a
+    /// broadPeak file.
+    ///
+    /// Two peaks are linked if they overlap by at least `min_overlap_frac` of
+    /// the length of the shorter peak, and the peaks connected by the links
+    /// form a group. A group yields a consensus peak if its peaks come from
+    /// at least `min_replicates` of the `peaksets`, where the consensus
+    /// interval is the union of the peaks in the group if `use_union` is
+    /// true, and their intersection otherwise. A group whose peaks do not
+    /// share a common base has an empty intersection and yields no consensus
+    /// peak in the latter case.
+    ///
+    /// Each consensus peak is named `consensus_peak_{k}`, its score is the
+    /// number of supporting replicates, and its signal value is the mean of
+    /// the signal values of the peaks in the group, while its p-value and
+    /// q-value are not set. The chromosomes are sorted by
+    /// `natural_chrom_cmp`, and the peaks on each chromosome by their start.
+    ///
+    /// The groups are found with a sweep line over the peaks sorted by start
+    /// on each chromosome. Since the links are chained, i.e. single-linkage,
+    /// a group can have peaks that do not overlap each other at all, e.g.
+    /// the peaks `[0, 10)` and `[20, 30)` are in the same group if both of
+    /// them are linked to a peak `[5, 25)` with a `min_overlap_frac` of 0.5,
+    /// and a long run of such peaks can make up a single wide consensus peak
+    /// if `use_union` is true.
+    pub fn merge_replicates(
+        peaksets: &[PeakFile],
+        min_overlap_frac: f32,
+        min_replicates: usize,
+        use_union: bool,
+        out_path: &str,
+    ) -> Result<PeakFile, Error> {
+        if !(0. ..=1.).contains(&min_overlap_frac) {
+            return Err(Error::Generic(format!(
+                "min_overlap_frac ({}) must be in [0, 1]",
+                min_overlap_frac
+            )));
+        }
+        if min_replicates == 0 {
+            return Err(Error::Generic(
+                "min_replicates must be positive".to_string(),
+            ));
+        }
+        // the (start, end, replicate index, signal value) of the peaks on
+        // each chromosome
+        let mut chrom_to_peaks: HashMap<
+            Chrom,
+            Vec<(usize, usize, usize, f64)>,
+        > = HashMap::new();
+        for (replicate, peakset) in peaksets.iter().enumerate() {
+            let path = peakset.get_filepath();
+            let peaks = read_peak_records(path, |toks, line_num| {
+                if toks.len() < 9 {
+                    return Err(Error::BadFormat(format!(
+                        "expected at least 9 columns on line {} of {} but \
+                        found {}",
+                        line_num,
+                        path,
+                        toks.len()
+                    )));
+                }
+                parse_broad_peak_columns(toks, line_num, path)
+            })?;
+            for p in peaks.into_iter().filter(|p| p.start < p.end) {
+                chrom_to_peaks.entry(p.chrom).or_default().push((
+                    p.start,
+                    p.end,
+                    replicate,
+                    p.signal_value,
+                ));
+            }
+        }
+        let mut chroms: Vec<Chrom> = chrom_to_peaks.keys().cloned().collect();
+        chroms.sort_by(|a, b| natural_chrom_cmp(a, b));
+
+        let mut writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(out_path)?,
+        );
+        let mut num_consensus_peaks = 0;
+        for chrom in chroms.iter() {
+            let peaks = chrom_to_peaks.get_mut(chrom).unwrap();
+            peaks.sort_by_key(|&(start, end, replicate, _)| {
+                (start, end, replicate)
+            });
+            let mut parents: Vec<usize> = (0..peaks.len()).collect();
+            // the peaks whose end has not yet been passed by the sweep line
+            let mut active: Vec<usize> = Vec::new();
+            for i in 0..peaks.len() {
+                let (start, end, ..) = peaks[i];
+                active.retain(|&j| peaks[j].1 > start);
+                for &j in active.iter() {
+                    let (other_start, other_end, ..) = peaks[j];
+                    let overlap = min(end, other_end) - start;
+                    let shorter = min(end - start, other_end - other_start);
+                    if overlap as f32 >= min_overlap_frac * shorter as f32 {
+                        let root = find_root(&mut parents, i);
+                        let other_root = find_root(&mut parents, j);
+                        parents[root] = other_root;
+                    }
+                }
+                active.push(i);
+            }
+            let mut root_to_group: HashMap<usize, usize> = HashMap::new();
+            let mut groups: Vec<Vec<usize>> = Vec::new();
+            for i in 0..peaks.len() {
+                let root = find_root(&mut parents, i);
+                let k = *root_to_group.entry(root).or_insert_with(|| {
+                    groups.push(Vec::new());
+                    groups.len() - 1
+                });
+                groups[k].push(i);
+            }
+
+            let mut consensus_peaks: Vec<(usize, usize, usize, f64)> =
+                Vec::new();
+            for group in groups.iter() {
+                let mut replicates: Vec<usize> =
+                    group.iter().map(|&i| peaks[i].2).collect();
+                replicates.sort_unstable();
+                replicates.dedup();
+                if replicates.len() < min_replicates {
+                    continue;
+                }
+                let starts = group.iter().map(|&i| peaks[i].0);
+                let ends = group.iter().map(|&i| peaks[i].1);
+                let (start, end) = if use_union {
+                    (starts.min().unwrap(), ends.max().unwrap())
+                } else {
+                    (starts.max().unwrap(), ends.min().unwrap())
+                };
+                if start >= end {
+                    continue;
+                }
+                let mean_signal =
+                    group.iter().map(|&i| peaks[i].3).sum::<f64>()
+                        / group.len() as f64;
+                consensus_peaks.push((
+                    start,
+                    end,
+                    replicates.len(),
+                    mean_signal,
+                ));
+            }
+            consensus_peaks.sort_by_key(|&(start, end, ..)| (start, end));
+            for (start, end, num_replicates, mean_signal) in consensus_peaks {
+                num_consensus_peaks += 1;
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\tconsensus_peak_{}\t{}\t.\t{}\t-1\t-1",
+                    chrom,
+                    start,
+                    end,
+                    num_consensus_peaks,
+                    num_replicates,
+                    mean_signal
+                )?;
+            }
+        }
+        writer.flush()?;
+        Ok(PeakFile::new(out_path.to_string()))
+    }
+}
+
+/// Returns the root of the tree containing `i` in the union-find forest
+/// `parents`, halving the path along the way.
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// Applies `parse` to the whitespace separated columns of each record line in
+/// the peak file at `path`, skipping blank, comment, track and browser lines.
+/// `parse` also receives the one-based line number for error reporting.
+fn read_peak_records<T, F>(path: &str, mut parse: F) -> Result<Vec<T>, Error>
+where
+    F: FnMut(&[&str], usize) -> Result<T, Error>, {
+    let mut records = Vec::new();
+    for (i, line) in get_file_buf(path)?.lines().enumerate() {
+        let line = line?;
+        let toks: Vec<&str> = line.split_whitespace().collect();
+        if toks.is_empty()
+            || toks[0].starts_with('#')
+            || toks[0] == "track"
+            || toks[0] == "browser"
+        {
+            continue;
+        }
+        records.push(parse(&toks, i + 1)?);
+    }
+    Ok(records)
+}
+
+fn bad_column(path: &str, line_num: usize, column: &str) -> Error {
+    Error::BadFormat(format!(
+        "failed to parse the {} on line {} of {}",
+        column, line_num, path
+    ))
+}
+
+/// Parses the first 9 columns shared by the narrowPeak and broadPeak formats.
+/// The caller is responsible for checking that `toks` has enough columns.
+fn parse_broad_peak_columns(
+    toks: &[&str],
+    line_num: usize,
+    path: &str,
+) -> Result<BroadPeak, Error> {
+    let bad_format = |column: &str| bad_column(path, line_num, column);
+    let optional_value = |v: f64| {
+        if v == -1. {
+            None
+        } else {
+            Some(v)
+        }
+    };
+    Ok(BroadPeak {
+        chrom: toks[0].to_string(),
+        start: toks[1].parse().map_err(|_| bad_format("start"))?,
+        end: toks[2].parse().map_err(|_| bad_format("end"))?,
+        name: toks[3].to_string(),
+        score: toks[4].parse().map_err(|_| bad_format("score"))?,
+        strand: Strand::new(toks[5])?,
+        signal_value: toks[6]
+            .parse()
+            .map_err(|_| bad_format("signal value"))?,
+        p_value: optional_value(
+            toks[7].parse().map_err(|_| bad_format("p-value"))?,
+        ),
+        q_value: optional_value(
+            toks[8].parse().map_err(|_| bad_format("q-value"))?,
+        ),
+    })
+}
+
+/// A record in an ENCODE narrowPeak file, where [start, end) is a zero-based
+/// left-closed right-open coordinate range, and `peak` is the zero-based
+/// offset of the peak summit from `start`, or -1 if no summit was called.
+#[derive(PartialEq, Clone, Debug)]
+pub struct NarrowPeak {
+    pub chrom: String,
+    pub start: usize,
+    pub end: usize,
+    pub name: String,
+    pub score: f64,
+    pub strand: Option<Strand>,
+    pub signal_value: f64,
+    pub p_value: Option<f64>,
+    pub q_value: Option<f64>,
+    pub peak: i64,
+}
+
+/// A record in an ENCODE broadPeak file, where [start, end) is a zero-based
+/// left-closed right-open coordinate range.
+#[derive(PartialEq, Clone, Debug)]
+pub struct BroadPeak {
+    pub chrom: String,
+    pub start: usize,
+    pub end: usize,
+    pub name: String,
+    pub score: f64,
+    pub strand: Option<Strand>,
+    pub signal_value: f64,
+    pub p_value: Option<f64>,
+    pub q_value: Option<f64>,
 }
 
 /// The [start, end) is a zero-based left-closed right-open coordinate range
@@ -169,7 +655,12 @@ mod tests {
     };
     use tempfile::NamedTempFile;
 
-    use crate::peak_file::{PeakFile, PeakFileDataLine};
+    use crate::{
+        bed::Bed,
+        error::Error,
+        peak_file::{BroadPeak, NarrowPeak, PeakFile, PeakFileDataLine},
+        util::Strand,
+    };
 
     #[test]
     fn test_get_chrom_to_interval_to_val() {
@@ -297,4 +788,367 @@ mod tests {
             peak_file.get_chrom_to_peak_locations(None, None).unwrap()
         );
     }
+
+    #[test]
+    fn test_read_narrow_peak() {
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer
+                .write_fmt(format_args!(
+                    "track type=narrowPeak name=\"test\"\n\
+                    chr1 100 500 peak_1 1000 + 3.5 12.25 -1 150\n\
+                    chr2 2000 2400 peak_2 50 . 1.5 -1 0.5 -1\n"
+                ))
+                .unwrap();
+        }
+        let peaks =
+            PeakFile::read_narrow_peak(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(peaks, vec![
+            NarrowPeak {
+                chrom: "chr1".to_string(),
+                start: 100,
+                end: 500,
+                name: "peak_1".to_string(),
+                score: 1000.,
+                strand: Some(Strand::Positive),
+                signal_value: 3.5,
+                p_value: Some(12.25),
+                q_value: None,
+                peak: 150,
+            },
+            NarrowPeak {
+                chrom: "chr2".to_string(),
+                start: 2000,
+                end: 2400,
+                name: "peak_2".to_string(),
+                score: 50.,
+                strand: None,
+                signal_value: 1.5,
+                p_value: None,
+                q_value: Some(0.5),
+                peak: -1,
+            },
+        ]);
+
+        let bad_file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&bad_file);
+            writer
+                .write_fmt(format_args!(
+                    "chr1 100 500 peak_1 1000 + 3.5 12.25 -1 150\n\
+                    chr1 600 700 peak_2 1000 + 3.5 12.25 -1\n"
+                ))
+                .unwrap();
+        }
+        match PeakFile::read_narrow_peak(bad_file.path().to_str().unwrap()) {
+            Err(Error::BadFormat(why)) => assert!(why.contains("line 2")),
+            _ => panic!("expected a BadFormat error"),
+        }
+    }
+
+    #[test]
+    fn test_read_broad_peak() {
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer
+                .write_fmt(format_args!(
+                    "track type=broadPeak name=\"test\"\n\
+                    # comment\n\
+                    chr1 100 500 peak_1 1000 + 3.5 -1 2.25\n\
+                    chr3 0 4000 peak_2 0 - 0.75 8 -1\n"
+                ))
+                .unwrap();
+        }
+        let peaks =
+            PeakFile::read_broad_peak(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(peaks, vec![
+            BroadPeak {
+                chrom: "chr1".to_string(),
+                start: 100,
+                end: 500,
+                name: "peak_1".to_string(),
+                score: 1000.,
+                strand: Some(Strand::Positive),
+                signal_value: 3.5,
+                p_value: None,
+                q_value: Some(2.25),
+            },
+            BroadPeak {
+                chrom: "chr3".to_string(),
+                start: 0,
+                end: 4000,
+                name: "peak_2".to_string(),
+                score: 0.,
+                strand: Some(Strand::Negative),
+                signal_value: 0.75,
+                p_value: Some(8.),
+                q_value: None,
+            },
+        ]);
+
+        for bad_line in &[
+            "chr1 600 700 peak_2 1000 + 3.5 12.25 -1 150\n",
+            "chr1 600 700 peak_2 1000 + 3.5 12.25\n",
+        ] {
+            let bad_file = NamedTempFile::new().unwrap();
+            {
+                let mut writer = BufWriter::new(&bad_file);
+                writer
+                    .write_fmt(format_args!(
+                        "chr1 100 500 peak_1 1000 + 3.5 12.25 -1\n{}",
+                        bad_line
+                    ))
+                    .unwrap();
+            }
+            match PeakFile::read_broad_peak(bad_file.path().to_str().unwrap()) {
+                Err(Error::BadFormat(why)) => assert!(why.contains("line 2")),
+                _ => panic!("expected a BadFormat error"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_filter_peaks() {
+        let broad_peak =
+            |name: &str, signal_value: f64, q_value: Option<f64>| BroadPeak {
+                chrom: "chr1".to_string(),
+                start: 0,
+                end: 100,
+                name: name.to_string(),
+                score: 0.,
+                strand: None,
+                signal_value,
+                p_value: None,
+                q_value,
+            };
+        // the q-value columns are -log10(q) as in the ENCODE files, i.e.
+        // q = 0.0355, 1e-12 and 0.2 for a, c and d
+        let peaks = vec![
+            broad_peak("a", 1., Some(1.44977)),
+            broad_peak("b", 4., None),
+            broad_peak("c", 2., Some(12.)),
+            broad_peak("d", 3., Some(0.69897)),
+        ];
+        let names = |peaks: Vec<BroadPeak>| -> Vec<String> {
+            peaks.into_iter().map(|p| p.name).collect()
+        };
+        assert_eq!(
+            names(PeakFile::filter_by_qvalue(peaks.clone(), 0.05, false)),
+            vec!["a", "c"]
+        );
+        assert_eq!(
+            names(PeakFile::filter_by_qvalue(peaks.clone(), 0.05, true)),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(
+            names(PeakFile::filter_by_qvalue(peaks.clone(), 0.01, false)),
+            vec!["c"]
+        );
+        assert_eq!(
+            names(PeakFile::filter_by_qvalue(peaks.clone(), 1., false)),
+            vec!["a", "c", "d"]
+        );
+        assert_eq!(names(PeakFile::filter_by_signal(peaks.clone(), 2.)), vec![
+            "b", "c", "d"
+        ]);
+        assert!(PeakFile::filter_by_signal(peaks, 5.).is_empty());
+
+        let narrow_peaks = vec![
+            NarrowPeak {
+                chrom: "chr2".to_string(),
+                start: 10,
+                end: 20,
+                name: "e".to_string(),
+                score: 0.,
+                strand: None,
+                signal_value: 1.,
+                p_value: None,
+                q_value: Some(2.),
+                peak: -1,
+            },
+            NarrowPeak {
+                chrom: "chr2".to_string(),
+                start: 30,
+                end: 40,
+                name: "f".to_string(),
+                score: 0.,
+                strand: None,
+                signal_value: 1.,
+                p_value: None,
+                q_value: None,
+                peak: 5,
+            },
+        ];
+        let filtered = PeakFile::filter_by_qvalue(narrow_peaks, 0.05, false);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].name, "e");
+    }
+
+    #[test]
+    fn test_nearest_feature() {
+        let features_file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&features_file);
+            writer
+                .write_fmt(format_args!(
+                    "track name=genes\n\
+                    chr1 1000 2000 gene_0\n\
+                    chr1 100 300 gene_1\n\
+                    chr2 50 60 gene_2\n\
+                    chr1 150 900 gene_3\n\
+                    chr1 5000 6000 gene_4\n"
+                ))
+                .unwrap();
+        }
+        let features = Bed::new(features_file.path().to_str().unwrap(), false);
+
+        let peaks_file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&peaks_file);
+            writer
+                .write_fmt(format_args!(
+                    "chr1 200 400 peak_0 0 . 1 -1 -1 -1\n\
+                    chr1 900 950 peak_1 0 . 1 -1 -1 -1\n\
+                    chr1 2100 2200 peak_2 0 . 1 -1 -1 -1\n\
+                    chr1 4000 4900 peak_3 0 . 1 -1 -1 -1\n\
+                    chr1 0 50 peak_4 0 . 1 -1 -1 -1\n\
+                    chr3 0 50 peak_5 0 . 1 -1 -1 -1\n\
+                    chr2 100 200 peak_6 0 . 1 -1 -1 -1\n\
+                    chr1 7000 7100 peak_7 0 . 1 -1 -1 -1\n"
+                ))
+                .unwrap();
+        }
+        let peak_file =
+            PeakFile::new(peaks_file.path().to_str().unwrap().to_string());
+        assert_eq!(peak_file.nearest_feature(&features).unwrap(), vec![
+            (0, Some(3), 0),
+            (1, Some(3), -1),
+            (2, Some(0), -101),
+            (3, Some(4), 101),
+            (4, Some(1), 51),
+            (5, None, 0),
+            (6, Some(2), -41),
+            (7, Some(4), -1001),
+        ]);
+    }
+
+    #[test]
+    fn test_summits() {
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&file);
+            writer
+                .write_fmt(format_args!(
+                    "chr1 100 500 peak_1 1000 + 3.5 12.25 -1 150\n\
+                    chr1 600 700 peak_2 50 . 1.5 -1 0.5 -1\n\
+                    chr2 10 400 peak_3 50 . 1.5 -1 0.5 20\n"
+                ))
+                .unwrap();
+        }
+        let peak_file =
+            PeakFile::new(file.path().to_str().unwrap().to_string());
+        assert_eq!(peak_file.summits().unwrap(), vec![
+            ("chr1".to_string(), 250),
+            ("chr2".to_string(), 30),
+        ]);
+        assert_eq!(peak_file.summit_intervals(50).unwrap(), vec![
+            ("chr1".to_string(), 200, 300),
+            ("chr2".to_string(), 0, 80),
+        ]);
+    }
+
+    #[test]
+    fn test_merge_replicates() {
+        let write_peaks = |lines: &[(&str, usize, usize, f64)]| {
+            let file = NamedTempFile::new().unwrap();
+            {
+                let mut writer = BufWriter::new(&file);
+                for &(chrom, start, end, signal) in lines.iter() {
+                    writeln!(
+                        writer,
+                        "{} {} {} peak 0 . {} -1 -1",
+                        chrom, start, end, signal
+                    )
+                    .unwrap();
+                }
+            }
+            file
+        };
+        let files = [
+            write_peaks(&[
+                ("chr1", 100, 200, 2.),
+                ("chr1", 500, 600, 1.),
+                ("chr2", 0, 100, 4.),
+            ]),
+            write_peaks(&[
+                ("chr2", 10, 90, 2.),
+                ("chr1", 150, 260, 4.),
+                ("chr1", 590, 700, 1.),
+            ]),
+            write_peaks(&[("chr10", 0, 50, 1.), ("chr1", 180, 240, 6.)]),
+        ];
+        let peaksets: Vec<PeakFile> = files
+            .iter()
+            .map(|f| PeakFile::new(f.path().to_str().unwrap().to_string()))
+            .collect();
+        let out_path = NamedTempFile::new().unwrap().into_temp_path();
+        let out_path_str = out_path.to_str().unwrap();
+        let consensus =
+            |min_overlap_frac: f32, min_replicates: usize, use_union: bool| {
+                let merged = PeakFile::merge_replicates(
+                    &peaksets,
+                    min_overlap_frac,
+                    min_replicates,
+                    use_union,
+                    out_path_str,
+                )
+                .unwrap();
+                PeakFile::read_broad_peak(merged.get_filepath())
+                    .unwrap()
+                    .into_iter()
+                    .map(|p| (p.chrom, p.start, p.end, p.score, p.signal_value))
+                    .collect::<Vec<(String, usize, usize, f64, f64)>>()
+            };
+        // the first and the third peaks on chr1 overlap by less than half of
+        // the third peak, but both are linked to the second peak, while the
+        // peaks at 500 and 590 overlap by only 10 bases
+        let peak = |chrom: &str, start, end, score, signal| {
+            (chrom.to_string(), start, end, score, signal)
+        };
+        assert_eq!(consensus(0.5, 2, false), vec![
+            peak("chr1", 180, 200, 3., 4.),
+            peak("chr2", 10, 90, 2., 3.),
+        ]);
+        assert_eq!(consensus(0.5, 2, true), vec![
+            peak("chr1", 100, 260, 3., 4.),
+            peak("chr2", 0, 100, 2., 3.),
+        ]);
+        assert_eq!(consensus(0.5, 3, true), vec![peak(
+            "chr1", 100, 260, 3., 4.
+        )]);
+        assert_eq!(consensus(0., 2, false), vec![
+            peak("chr1", 180, 200, 3., 4.),
+            peak("chr1", 590, 600, 2., 1.),
+            peak("chr2", 10, 90, 2., 3.),
+        ]);
+        assert_eq!(consensus(0., 1, true)[3], peak("chr10", 0, 50, 1., 1.));
+        assert!(PeakFile::merge_replicates(
+            &peaksets,
+            1.5,
+            2,
+            true,
+            out_path_str
+        )
+        .is_err());
+        assert!(PeakFile::merge_replicates(
+            &peaksets,
+            0.5,
+            0,
+            true,
+            out_path_str
+        )
+        .is_err());
+    }
 }
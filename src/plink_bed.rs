@@ -1,46 +1,236 @@
+use log::{info, warn};
 use math::{
     set::{
-        ordered_integer_set::OrderedIntegerSet,
+        ordered_integer_set::{IntegerSetIter, OrderedIntegerSet},
         traits::{Finite, Set},
     },
-    stats::sum_f32,
-    traits::ToIterator,
+    stats::{kahan_sigma, sum_f32},
+    traits::{Collecting, ToIterator},
 };
-use ndarray::{Array, Axis, Ix2, ShapeBuilder};
-use rayon::iter::{
-    plumbing::{
-        bridge, Consumer, Producer, ProducerCallback, UnindexedConsumer,
+use ndarray::{
+    linalg::general_mat_mul, s, Array, ArrayView, ArrayViewMut, Axis, Ix1, Ix2,
+    ShapeBuilder,
+};
+use num::Float;
+use rayon::{
+    iter::{
+        plumbing::{
+            bridge, Consumer, Producer, ProducerCallback, UnindexedConsumer,
+        },
+        IndexedParallelIterator, IntoParallelIterator,
+        IntoParallelRefMutIterator, ParallelIterator,
     },
-    IndexedParallelIterator, IntoParallelIterator, ParallelIterator,
+    slice::ParallelSliceMut,
 };
 use std::{
-    cmp::min,
-    collections::HashSet,
+    borrow::Cow,
+    cmp::{max, min},
+    collections::{HashSet, VecDeque},
+    convert::TryFrom,
     fs::{File, OpenOptions},
     io,
     io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use plink_snps::PlinkSnps;
+use shared_file::SharedFile;
 
-use crate::{byte_chunk_iter::ByteChunkIter, error::Error, util::get_buf};
+use crate::{
+    byte_chunk_iter::ByteChunkIter,
+    error::Error,
+    plink_bim::{Bim, BimRecord},
+    plink_fam::FamRecord,
+    util::{bfile_paths, get_buf, get_buf_with_len, get_file_buf},
+};
 
 pub const MAGIC_BYTES: [u8; 3] = [0x6c_u8, 0x1b_u8, 0x01_u8];
 pub const NUM_MAGIC_BYTES: usize = 3;
 const NUM_PEOPLE_PER_BYTE: usize = 4;
+/// The two bits encoding a missing genotype.
+const MISSING_GENOTYPE_BITS: u8 = 0b01;
+/// The two bits encoding a heterozygous genotype.
+const HETEROZYGOUS_GENOTYPE_BITS: u8 = 0b10;
+
+/// The two bits encoding the genotype homozygous for the first allele.
+const HOMOZYGOUS_FIRST_GENOTYPE_BITS: u8 = 0b00;
+/// The two bits encoding the genotype homozygous for the second allele.
+const HOMOZYGOUS_SECOND_GENOTYPE_BITS: u8 = 0b11;
+
+/// The number of SNPs in each block of columns whose pairwise r^2 are computed
+/// together in `ld_matrix`.
+const LD_MATRIX_BLOCK_NUM_SNPS: usize = 256;
+
+/// The number of people in each block over which `snp_covariance` accumulates
+/// the SNP-by-SNP products.
+const SNP_COVARIANCE_BLOCK_NUM_PEOPLE: usize = 1024;
+
+/// The maximum number of SNPs of the `snp_covariance`, whose result alone
+/// takes `4 * num_snps^2` bytes, i.e. 1.6 GB at this limit.
+pub const MAX_SNP_COVARIANCE_NUM_SNPS: usize = 20_000;
+
+/// The maximum number of times the progress callback of a long-running
+/// operation is invoked, regardless of the size of the data.
+const MAX_PROGRESS_REPORTS: usize = 1000;
 
+/// The number of bytes read at a time when computing the `content_digest`.
+const DIGEST_CHUNK_NUM_BYTES: usize = 1 << 20;
+
+/// The memory budget for a chunk of `f32` genotypes when the chunk size is
+/// not specified.
+pub const DEFAULT_CHUNK_MEMORY_BUDGET_BYTES: usize = 64 * 1024 * 1024;
+
+pub mod annotated_chunk_iter;
+pub mod bed_writer;
+pub mod builder;
+pub mod genotype_counts;
 pub mod plink_snps;
+mod shared_file;
+#[cfg(feature = "simd")]
+mod simd_decode;
+pub mod snp_byte_iter;
+pub mod transposed_chunk_iter;
+
+pub use annotated_chunk_iter::AnnotatedColChunkIter;
+pub use bed_writer::BedWriter;
+pub use builder::PlinkBedBuilder;
+pub use genotype_counts::{DatasetSummary, GenotypeCounts, SnpFlag};
+pub use snp_byte_iter::SnpByteIter;
+pub use transposed_chunk_iter::PlinkTransposedChunkIter;
+use transposed_chunk_iter::SnpSelection;
 
 pub struct PlinkBed {
     bed_path_list: Vec<String>,
+    fam_path_list: Vec<String>,
     file_num_snps: Vec<(usize, PlinkSnpType)>,
+    /// The sum of the number of SNPs in `file_num_snps`.
+    pub(crate) total_num_snps: usize,
     pub num_people: usize,
+    missing_strategy: MissingStrategy,
+    dominance_orientation: DominanceOrientation,
+    /// The genotype counts of all the SNPs cached by `precompute_snp_stats`.
+    snp_counts: Option<Vec<GenotypeCounts>>,
+    /// The types of all the SNPs read by `new_with_snp_type_file`, which
+    /// override the types in `file_num_snps`.
+    snp_types: Option<Vec<PlinkSnpType>>,
 }
 
 impl PlinkBed {
     /// `bfile_path_list` contains Vec<(bed, bim, fam)>
+    ///
+    /// The number of SNPs in each file and the number of people are logged
+    /// at the info level. Use `new_quiet` to skip the logging.
     pub fn new(
         bfile_path_list: &[(String, String, String, PlinkSnpType)],
+    ) -> Result<PlinkBed, Error> {
+        let bed = PlinkBed::new_quiet(bfile_path_list)?;
+        bed.bed_path_list
+            .iter()
+            .zip(bed.file_num_snps.iter())
+            .for_each(|(p, n)| {
+                info!("{} num_snps: {}", p, n.0);
+            });
+        info!("num_people: {}", bed.num_people);
+        Ok(bed)
+    }
+
+    /// Opens the bfile whose bed, bim and fam files are `{prefix}.bed`,
+    /// `{prefix}.bim` and `{prefix}.fam` respectively.
+    pub fn from_prefix(
+        prefix: &str,
+        snp_type: PlinkSnpType,
+    ) -> Result<PlinkBed, Error> {
+        PlinkBed::from_prefixes(&[(prefix, snp_type)])
+    }
+
+    /// Same as `from_prefix`, but the `PlinkSnpType` is read from the
+    /// `{prefix}.encoding` file, whose first line is either `additive` or
+    /// `dominance`. The type defaults to `Additive` if the file is absent.
+    pub fn from_prefix_auto(prefix: &str) -> Result<PlinkBed, Error> {
+        let encoding_path = format!("{}.encoding", prefix);
+        let snp_type = if Path::new(&encoding_path).exists() {
+            match get_buf(&encoding_path)?.lines().next() {
+                Some(line) => line?.parse::<PlinkSnpType>()?,
+                None => {
+                    return Err(Error::BadFormat(format!(
+                        "the encoding file {} is empty",
+                        encoding_path
+                    )))
+                }
+            }
+        } else {
+            PlinkSnpType::Additive
+        };
+        PlinkBed::from_prefix(prefix, snp_type)
+    }
+
+    /// Same as `from_prefix` but for multiple bfiles.
+    pub fn from_prefixes(
+        prefix_list: &[(&str, PlinkSnpType)],
+    ) -> Result<PlinkBed, Error> {
+        let mut bfile_path_list = Vec::with_capacity(prefix_list.len());
+        for (prefix, snp_type) in prefix_list.iter() {
+            let (bed, bim, fam) = bfile_paths(prefix)?;
+            for (path, ext) in
+                [(&bed, "bed"), (&bim, "bim"), (&fam, "fam")].iter()
+            {
+                if !Path::new(path).exists() {
+                    let why = format!(
+                        "the .{} file for the bfile prefix {} does not exist: {}",
+                        ext, prefix, path
+                    );
+                    return Err(Error::IO {
+                        io_error: io::Error::new(
+                            io::ErrorKind::NotFound,
+                            why.clone(),
+                        ),
+                        why,
+                    });
+                }
+            }
+            bfile_path_list.push((bed, bim, fam, *snp_type));
+        }
+        PlinkBed::new(&bfile_path_list)
+    }
+
+    /// Same as `new` but without logging.
+    pub fn new_quiet(
+        bfile_path_list: &[(String, String, String, PlinkSnpType)],
+    ) -> Result<PlinkBed, Error> {
+        PlinkBed::new_with_optional_num_snps(bfile_path_list, None)
+    }
+
+    /// Same as `new_quiet`, but the number of SNPs in the i-th bed file is
+    /// `num_snps_override[i]` instead of the number of lines in its bim file,
+    /// which is not read. This allows a bim file that is out of sync with its
+    /// bed file. The size of each bed file is still checked against the
+    /// number of SNPs.
+    pub fn new_with_num_snps(
+        bfile_path_list: &[(String, String, String, PlinkSnpType)],
+        num_snps_override: &[usize],
+    ) -> Result<PlinkBed, Error> {
+        if bfile_path_list.len() != num_snps_override.len() {
+            return Err(Error::Generic(format!(
+                "the number of SNP counts {} does not match the number of \
+                bfiles {}",
+                num_snps_override.len(),
+                bfile_path_list.len()
+            )));
+        }
+        PlinkBed::new_with_optional_num_snps(
+            bfile_path_list,
+            Some(num_snps_override),
+        )
+    }
+
+    fn new_with_optional_num_snps(
+        bfile_path_list: &[(String, String, String, PlinkSnpType)],
+        num_snps_override: Option<&[usize]>,
     ) -> Result<PlinkBed, Error> {
         if bfile_path_list.is_empty() {
             return Err(Error::Generic(
@@ -50,15 +240,22 @@ impl PlinkBed {
         }
         let bed_path_list: Vec<String> =
             bfile_path_list.iter().map(|t| t.0.to_string()).collect();
+        let fam_path_list: Vec<String> =
+            bfile_path_list.iter().map(|t| t.2.to_string()).collect();
 
-        for p in bed_path_list.iter() {
-            PlinkBed::verify_magic_bytes(&p)?;
-        }
+        let bed_file_sizes = bed_path_list
+            .iter()
+            .map(|p| PlinkBed::verify_magic_bytes(p))
+            .collect::<Result<Vec<u64>, Error>>()?;
 
         let file_num_snps: Vec<(usize, PlinkSnpType)> = bfile_path_list
             .iter()
-            .map(|t| {
-                let num_snps = get_line_count(&t.1)?;
+            .enumerate()
+            .map(|(i, t)| {
+                let num_snps = match num_snps_override {
+                    Some(counts) => counts[i],
+                    None => get_line_count(&t.1)?,
+                };
                 if num_snps == 0 {
                     Err(Error::Generic(
                         "cannot create PlinkBed with 0 SNPs".to_string(),
@@ -70,18 +267,16 @@ impl PlinkBed {
             .collect::<Result<Vec<(usize, PlinkSnpType)>, Error>>()?;
 
         let num_people: usize = {
-            let num_people_set: HashSet<usize> = bfile_path_list
+            let counts: Vec<usize> = bfile_path_list
                 .iter()
                 .map(|t| Ok(get_line_count(&t.2)?))
-                .collect::<Result<HashSet<usize>, Error>>()?;
-            if num_people_set.len() > 1 {
-                return Err(Error::Generic(
-                    "inconsistent number of people across the bed files"
-                        .to_string(),
-                ));
+                .collect::<Result<Vec<usize>, Error>>()?;
+            if counts.iter().collect::<HashSet<&usize>>().len() > 1 {
+                return Err(Error::InconsistentSampleCount {
+                    counts,
+                });
             }
-            let num_people =
-                num_people_set.into_iter().collect::<Vec<usize>>()[0];
+            let num_people = counts[0];
             if num_people == 0 {
                 return Err(Error::Generic(
                     "cannot create PlinkBed with 0 people".to_string(),
@@ -90,28 +285,146 @@ impl PlinkBed {
             num_people
         };
 
-        println!("----------");
-        bed_path_list
+        for ((p, file_size), (num_snps, _)) in bed_path_list
             .iter()
+            .zip(bed_file_sizes)
             .zip(file_num_snps.iter())
-            .for_each(|(p, n)| {
-                println!("{} num_snps: {}", p, n.0);
-            });
-        println!("num_people: {}\n----------", num_people,);
+        {
+            PlinkBed::verify_bed_size(p, file_size, num_people, *num_snps)?;
+        }
 
+        let total_num_snps =
+            file_num_snps.iter().map(|pair| pair.0).sum::<usize>();
         Ok(PlinkBed {
             bed_path_list,
+            fam_path_list,
             file_num_snps,
+            total_num_snps,
             num_people,
+            missing_strategy: MissingStrategy::default(),
+            dominance_orientation: DominanceOrientation::default(),
+            snp_counts: None,
+            snp_types: None,
         })
     }
 
+    /// Same as `new`, but the type of each SNP is read from the sidecar file
+    /// in `snp_type_paths` corresponding to its bfile, which has a line of
+    /// either `additive` or `dominance` for each SNP in the bfile. This allows
+    /// a single bfile to contain SNPs of both types.
+    pub fn new_with_snp_type_file(
+        bfile_path_list: &[(String, String, String)],
+        snp_type_paths: &[String],
+    ) -> Result<PlinkBed, Error> {
+        if bfile_path_list.len() != snp_type_paths.len() {
+            return Err(Error::Generic(format!(
+                "the number of SNP type files {} does not match the number of \
+                bfiles {}",
+                snp_type_paths.len(),
+                bfile_path_list.len()
+            )));
+        }
+        let mut bed = PlinkBed::new(
+            &bfile_path_list
+                .iter()
+                .map(|(bed, bim, fam)| {
+                    (
+                        bed.to_string(),
+                        bim.to_string(),
+                        fam.to_string(),
+                        PlinkSnpType::Additive,
+                    )
+                })
+                .collect::<Vec<(String, String, String, PlinkSnpType)>>(),
+        )?;
+        let mut snp_types = Vec::with_capacity(bed.total_num_snps());
+        for (path, (num_snps, _)) in
+            snp_type_paths.iter().zip(bed.file_num_snps.iter())
+        {
+            let mut file_snp_types = Vec::with_capacity(*num_snps);
+            for line in get_buf(path)?.lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    file_snp_types.push(line.parse::<PlinkSnpType>()?);
+                }
+            }
+            if file_snp_types.len() != *num_snps {
+                return Err(Error::BadFormat(format!(
+                    "the SNP type file {} has {} types but the bfile has {} \
+                    SNPs",
+                    path,
+                    file_snp_types.len(),
+                    num_snps
+                )));
+            }
+            snp_types.append(&mut file_snp_types);
+        }
+        bed.snp_types = Some(snp_types);
+        Ok(bed)
+    }
+
+    /// Returns whether every SNP is decoded with the additive encoding.
+    fn all_snps_additive(&self) -> bool {
+        match &self.snp_types {
+            Some(types) => types.iter().all(|t| *t == PlinkSnpType::Additive),
+            None => self
+                .file_num_snps
+                .iter()
+                .all(|(_, snp_type)| *snp_type == PlinkSnpType::Additive),
+        }
+    }
+
+    /// Returns the type of each of the `total_num_snps` SNPs, i.e. the type of
+    /// its file, or the type read by `new_with_snp_type_file`.
+    pub fn snp_types(&self) -> Vec<PlinkSnpType> {
+        match &self.snp_types {
+            Some(types) => types.clone(),
+            None => self
+                .file_num_snps
+                .iter()
+                .flat_map(|&(n, snp_type)| std::iter::repeat_n(snp_type, n))
+                .collect(),
+        }
+    }
+
+    /// Returns whether the SNPs are not all of the same type, in which case
+    /// the genotypes of different SNPs are on different scales, which the
+    /// computations combining SNPs, e.g. `compute_grm`, do not account for.
+    pub fn has_mixed_snp_types(&self) -> bool {
+        self.snp_types().windows(2).any(|w| w[0] != w[1])
+    }
+
+    #[inline]
+    pub fn get_missing_strategy(&self) -> MissingStrategy {
+        self.missing_strategy
+    }
+
+    /// Determines how the missing genotypes are decoded by the chunk
+    /// iterators created afterwards.
+    pub fn set_missing_strategy(&mut self, missing_strategy: MissingStrategy) {
+        self.missing_strategy = missing_strategy;
+    }
+
+    #[inline]
+    pub fn get_dominance_orientation(&self) -> DominanceOrientation {
+        self.dominance_orientation
+    }
+
+    /// Determines the allele taken as the minor allele when the SNPs of the
+    /// dominance type are decoded by the chunk iterators created afterwards.
+    pub fn set_dominance_orientation(
+        &mut self,
+        dominance_orientation: DominanceOrientation,
+    ) {
+        self.dominance_orientation = dominance_orientation;
+    }
+
     pub fn col_chunk_iter(
         &self,
         num_snps_per_iter: usize,
         range: Option<OrderedIntegerSet<usize>>,
     ) -> PlinkColChunkIter {
-        match range {
+        let mut iter = match range {
             Some(range) => PlinkColChunkIter::new(
                 self.file_num_snps.clone(),
                 range,
@@ -129,9 +442,203 @@ impl PlinkBed {
                 self.num_people,
                 self.bed_path_list.clone(),
             ),
+        };
+        iter.missing_strategy = self.missing_strategy;
+        iter.dominance_orientation = self.dominance_orientation;
+        if let Some(types) = &self.snp_types {
+            // an index out of range has already stopped the iterator
+            iter.snp_types = Some(
+                iter.range
+                    .to_iter()
+                    .filter_map(|i| types.get(i).copied())
+                    .collect(),
+            );
+        }
+        iter
+    }
+
+    /// Same as `col_chunk_iter`, but the iterator starts after the first
+    /// `start_cursor` SNPs in the range, so that an iteration checkpointed
+    /// with `PlinkColChunkIter::position` can be resumed. The iterator is
+    /// empty if `start_cursor` is at least the number of SNPs in the range.
+    pub fn col_chunk_iter_from(
+        &self,
+        num_snps_per_iter: usize,
+        range: Option<OrderedIntegerSet<usize>>,
+        start_cursor: usize,
+    ) -> PlinkColChunkIter {
+        let mut iter = self.col_chunk_iter(num_snps_per_iter, range);
+        iter.range_cursor = min(start_cursor, iter.num_snps_in_range);
+        // the next read seeks to the SNP at the cursor by itself
+        iter.last_read_file_snp_index = None;
+        iter
+    }
+
+    /// Similar to `col_chunk_iter`, but each SNP is decoded according to its
+    /// type in `snp_types` instead of the type of the file containing it,
+    /// where `snp_types` is aligned to the SNPs in the `range`.
+    pub fn col_chunk_iter_with_types(
+        &self,
+        num_snps_per_iter: usize,
+        range: Option<OrderedIntegerSet<usize>>,
+        snp_types: &[PlinkSnpType],
+    ) -> Result<PlinkColChunkIter, Error> {
+        let num_snps = match &range {
+            None => self.total_num_snps(),
+            Some(range) => range.size(),
+        };
+        if snp_types.len() != num_snps {
+            return Err(Error::Generic(format!(
+                "the number of SNP types {} does not match the number of SNPs \
+                in the range {}",
+                snp_types.len(),
+                num_snps
+            )));
+        }
+        let mut iter = self.col_chunk_iter(num_snps_per_iter, range);
+        iter.snp_types = Some(snp_types.to_vec());
+        Ok(iter)
+    }
+
+    /// Similar to `col_chunk_iter`, but each chunk is paired with the records
+    /// in `bim` of the SNPs in its columns, where `bim` contains the records
+    /// of all the SNPs in order, e.g. from `PlinkBim::get_bim_records`.
+    pub fn annotated_col_chunk_iter<'a>(
+        &self,
+        bim: &'a [BimRecord],
+        num_snps_per_iter: usize,
+        range: Option<OrderedIntegerSet<usize>>,
+    ) -> Result<AnnotatedColChunkIter<'a>, Error> {
+        if bim.len() != self.total_num_snps() {
+            return Err(Error::Generic(format!(
+                "the number of bim records {} does not match the number of \
+                SNPs {}",
+                bim.len(),
+                self.total_num_snps()
+            )));
+        }
+        let range = range.unwrap_or_else(|| {
+            OrderedIntegerSet::from_slice(&[[0, self.total_num_snps() - 1]])
+        });
+        Ok(AnnotatedColChunkIter::new(
+            self.col_chunk_iter(num_snps_per_iter, Some(range.clone())),
+            bim,
+            range,
+        ))
+    }
+
+    /// Similar to `col_chunk_iter`, but skips the monomorphic SNPs, i.e. the
+    /// SNPs whose frequency `sum / (2 * num_people)` is either 0 or 1. Each
+    /// chunk contains up to `num_snps_per_iter` polymorphic SNPs and is paired
+    /// with the indices of those SNPs.
+    pub fn polymorphic_col_chunk_iter(
+        &self,
+        num_snps_per_iter: usize,
+        range: Option<OrderedIntegerSet<usize>>,
+    ) -> PlinkPolymorphicColChunkIter {
+        let range = match range {
+            Some(range) => range,
+            None => {
+                OrderedIntegerSet::from_slice(&[[0, self.total_num_snps() - 1]])
+            }
+        };
+        PlinkPolymorphicColChunkIter {
+            snp_index_iter: range.to_iter(),
+            col_chunk_iter: self.col_chunk_iter(num_snps_per_iter, Some(range)),
+            num_snps_per_iter,
+            num_people: self.num_people,
+            buffered_snps: Vec::new(),
+            buffered_snp_indices: Vec::new(),
         }
     }
 
+    /// Iterates over the blocks of `people_per_chunk` people, where each block
+    /// is a `people_per_chunk x total_num_snps` matrix of the genotypes as
+    /// decoded by `get_genotype_matrix`, except that the last block has the
+    /// remaining people. Unlike `create_bed_t`, no transposed bed file is
+    /// written, and the memory used is bounded by a single block.
+    ///
+    /// The genotype counts of all the SNPs are computed first, or taken from
+    /// the cache of `precompute_snp_stats`, to decode the dominance encoding
+    /// and the mean imputation consistently across the blocks.
+    pub fn transposed_chunk_iter(
+        &self,
+        people_per_chunk: usize,
+    ) -> Result<PlinkTransposedChunkIter, Error> {
+        self.transposed_chunk_iter_in_range(None, people_per_chunk)
+    }
+
+    /// Same as `transposed_chunk_iter`, but the blocks only have the SNPs in
+    /// the `range` if not `None`, whose indices have to be less than the total
+    /// number of SNPs. Only the genotypes of the SNPs in the `range` are
+    /// counted unless the counts are cached.
+    fn transposed_chunk_iter_in_range(
+        &self,
+        range: Option<&OrderedIntegerSet<usize>>,
+        people_per_chunk: usize,
+    ) -> Result<PlinkTransposedChunkIter, Error> {
+        let num_people = self.num_people;
+        let missing_strategy = self.missing_strategy;
+        let dominance_orientation = self.dominance_orientation;
+        let code_values = |counts: &GenotypeCounts, snp_type| {
+            transposed_chunk_iter::snp_code_values(
+                counts,
+                num_people,
+                snp_type,
+                missing_strategy,
+                dominance_orientation,
+            )
+        };
+        let snp_values = match (&self.snp_counts, range) {
+            (Some(counts), Some(range)) => {
+                let snp_types = self.snp_types();
+                range
+                    .to_iter()
+                    .map(|i| code_values(&counts[i], snp_types[i]))
+                    .collect()
+            }
+            (Some(counts), None) => counts
+                .iter()
+                .zip(self.snp_types())
+                .map(|(c, snp_type)| code_values(c, snp_type))
+                .collect(),
+            (None, Some(range)) => self.map_snp_bytes_in_range(
+                range,
+                self.get_default_chunk_size(),
+                |snp_bytes, snp_type| {
+                    code_values(
+                        &GenotypeCounts::from_snp_bytes(snp_bytes, num_people),
+                        snp_type,
+                    )
+                },
+            )?,
+            (None, None) => self.map_snp_bytes(
+                self.get_default_chunk_size(),
+                |snp_bytes, snp_type| {
+                    code_values(
+                        &GenotypeCounts::from_snp_bytes(snp_bytes, num_people),
+                        snp_type,
+                    )
+                },
+            )?,
+        };
+        let file_snp_indices = match range {
+            Some(range) => self.file_snp_indices(range),
+            None => self
+                .file_num_snps
+                .iter()
+                .map(|(num_snps, _)| SnpSelection::All(*num_snps))
+                .collect(),
+        };
+        PlinkTransposedChunkIter::new(
+            &self.bed_path_list,
+            file_snp_indices,
+            num_people,
+            people_per_chunk,
+            snp_values,
+        )
+    }
+
     pub fn byte_chunk_iter(
         &self,
         file_index: usize,
@@ -157,28 +664,176 @@ impl PlinkBed {
                     chunk_size,
                 ))
             }
-            None => Err(Error::Generic(format!(
-                "file index out of range {} >= {}",
-                file_index,
-                self.bed_path_list.len()
-            ))),
+            None => Err(Error::FileIndexOutOfRange {
+                index: file_index,
+                num_files: self.bed_path_list.len(),
+            }),
+        }
+    }
+
+    /// Iterates over the packed bytes of the SNPs of the bed file at
+    /// `file_index` without decoding them, where `range` contains the
+    /// indices of the SNPs within the file, and defaults to all the SNPs of
+    /// the file. Each SNP is yielded with its global index among the SNPs of
+    /// all the files.
+    pub fn snp_byte_iter(
+        &self,
+        file_index: usize,
+        range: Option<OrderedIntegerSet<usize>>,
+    ) -> Result<SnpByteIter, Error> {
+        let num_file_snps = match self.file_num_snps.get(file_index) {
+            Some((num_snps, _)) => *num_snps,
+            None => {
+                return Err(Error::FileIndexOutOfRange {
+                    index: file_index,
+                    num_files: self.bed_path_list.len(),
+                })
+            }
+        };
+        let range = range.unwrap_or_else(|| {
+            OrderedIntegerSet::from_slice(&[[0, num_file_snps - 1]])
+        });
+        if let Some(last) = range.last() {
+            if last >= num_file_snps {
+                return Err(Error::SnpIndexOutOfRange {
+                    index: last,
+                    total: num_file_snps,
+                });
+            }
+        }
+        let first_snp_index = self.file_num_snps[..file_index]
+            .iter()
+            .map(|(num_snps, _)| num_snps)
+            .sum();
+        Ok(SnpByteIter::new(
+            get_file_buf(&self.bed_path_list[file_index])?,
+            range.to_iter(),
+            PlinkBed::num_bytes_per_snp(self.num_people),
+            first_snp_index,
+        ))
+    }
+
+    /// Splits the global SNP indices in the `range`, which have to be less
+    /// than the total number of SNPs, into the indices within each file. A
+    /// file whose SNPs are all in the `range` selects all of its SNPs.
+    fn file_snp_indices(
+        &self,
+        range: &OrderedIntegerSet<usize>,
+    ) -> Vec<SnpSelection> {
+        let indexer = FileSnpIndexer::new(self.file_num_snps.clone());
+        let mut file_snp_indices = vec![Vec::new(); self.file_num_snps.len()];
+        for i in range.to_iter() {
+            let (file_index, file_snp_index, _) =
+                indexer.get_file_snp_index(i).unwrap();
+            file_snp_indices[file_index].push(file_snp_index);
+        }
+        file_snp_indices
+            .into_iter()
+            .zip(self.file_num_snps.iter())
+            .map(|(indices, (num_snps, _))| {
+                if indices.len() == *num_snps {
+                    SnpSelection::All(*num_snps)
+                } else {
+                    SnpSelection::Indices(indices)
+                }
+            })
+            .collect()
+    }
+
+    /// Computes the 64-bit FNV-1a hash of all the bytes of the bed file at
+    /// `file_index`, including the magic bytes, by streaming the file in
+    /// chunks. The digest only depends on the content of the file, so it
+    /// can be stored to detect changes to the file across runs.
+    pub fn content_digest(&self, file_index: usize) -> Result<u64, Error> {
+        let num_bytes = match self.file_num_snps.get(file_index) {
+            Some((num_snps, _)) => cast_byte_offset(snp_byte_offset(
+                *num_snps,
+                PlinkBed::num_bytes_per_snp(self.num_people),
+                0,
+            )?)?,
+            None => {
+                return Err(Error::FileIndexOutOfRange {
+                    index: file_index,
+                    num_files: self.bed_path_list.len(),
+                })
+            }
+        };
+        let mut hasher = Fnv1a::new();
+        for bytes in self.byte_chunk_iter(
+            file_index,
+            0,
+            num_bytes,
+            DIGEST_CHUNK_NUM_BYTES,
+        )? {
+            hasher.write(&bytes);
+        }
+        Ok(hasher.finish())
+    }
+
+    /// Combines the `content_digest` of all the bed files in order by hashing
+    /// their little-endian bytes with FNV-1a, so that the digest changes if
+    /// any of the files changes or the files are reordered.
+    pub fn combined_content_digest(&self) -> Result<u64, Error> {
+        let mut hasher = Fnv1a::new();
+        for file_index in 0..self.bed_path_list.len() {
+            hasher.write(&self.content_digest(file_index)?.to_le_bytes());
         }
+        Ok(hasher.finish())
     }
 
+    /// Reads the SNPs in `snps_range`, or all the SNPs if `None`, into a
+    /// `num_people x num_snps` matrix. The chunks of `chunk_size` SNPs, 100
+    /// by default, are read in parallel. Returns an error if `chunk_size` is
+    /// `Some(0)`.
     pub fn get_genotype_matrix(
         &self,
         snps_range: Option<OrderedIntegerSet<usize>>,
+        chunk_size: Option<usize>,
+    ) -> Result<Array<f32, Ix2>, Error> {
+        self.get_genotype_matrix_with_progress(
+            snps_range,
+            chunk_size,
+            &|_, _| {},
+        )
+    }
+
+    /// Same as `get_genotype_matrix`, but `progress` is called with the
+    /// number of SNPs read so far and the total number of SNPs, at most
+    /// `MAX_PROGRESS_REPORTS` times. As the chunks are read in parallel, the
+    /// calls can come from different threads, and the number of SNPs read in
+    /// a call can be smaller than that in an earlier call.
+    pub fn get_genotype_matrix_with_progress(
+        &self,
+        snps_range: Option<OrderedIntegerSet<usize>>,
+        chunk_size: Option<usize>,
+        progress: &(dyn Fn(usize, usize) + Sync),
     ) -> Result<Array<f32, Ix2>, Error> {
         let num_snps = match &snps_range {
             None => self.total_num_snps(),
             Some(range) => range.size(),
         };
-        let mut v = Vec::with_capacity(self.num_people * num_snps);
+        let chunk_size = chunk_size.unwrap_or(100);
+        if chunk_size == 0 {
+            return Err(Error::Generic(
+                "chunk_size must be positive".to_string(),
+            ));
+        }
+        let mut v = vec![0f32; self.num_people * num_snps];
+        let progress = ProgressReporter::new(progress, num_snps);
 
-        for snp_chunk in self.col_chunk_iter(100, snps_range) {
-            v.append(
-                &mut snp_chunk.t().to_owned().as_slice().unwrap().to_vec(),
-            );
+        // each chunk of SNPs occupies a contiguous slot in the column major v
+        let chunk_iter = self.col_chunk_iter(chunk_size, snps_range);
+        let error_handle = chunk_iter.get_error_handle();
+        v.par_chunks_mut(self.num_people * chunk_size)
+            .zip(chunk_iter.into_par_iter())
+            .for_each(|(slot, snp_chunk)| {
+                for (dest, x) in slot.iter_mut().zip(snp_chunk.t().iter()) {
+                    *dest = *x;
+                }
+                progress.add(snp_chunk.dim().1);
+            });
+        if let Some(error) = error_handle.lock().unwrap().take() {
+            return Err(error);
         }
         let geno_arr = Array::from_shape_vec(
             (self.num_people, num_snps).strides((1, self.num_people)),
@@ -188,858 +843,6182 @@ impl PlinkBed {
         Ok(geno_arr)
     }
 
-    pub fn get_bed_path_list(&self) -> &Vec<String> {
-        &self.bed_path_list
+    /// Reads the SNPs in `snps_range`, or all the SNPs if `None`, into a
+    /// `num_people x num_snps` matrix of the additive genotype codes 0, 1 and
+    /// 2, where the missing genotypes are decoded as `missing_code`. The SNPs
+    /// are decoded as additive regardless of their `PlinkSnpType`.
+    pub fn get_genotype_matrix_u8(
+        &self,
+        snps_range: Option<OrderedIntegerSet<usize>>,
+        missing_code: u8,
+    ) -> Result<Array<u8, Ix2>, Error> {
+        let num_people = self.num_people;
+        let num_snps = match &snps_range {
+            None => self.total_num_snps(),
+            Some(range) => range.size(),
+        };
+        let mut chunk_iter = self.col_chunk_iter(max(num_snps, 1), snps_range);
+        if let Some(error) = chunk_iter.take_error() {
+            return Err(error);
+        }
+        chunk_iter.read_chunk_with(0, num_snps, |snp_bytes, _| {
+            decode_snp_bytes_u8(snp_bytes, num_people, missing_code)
+        })
     }
 
-    pub fn get_file_num_snps(&self) -> &Vec<(usize, PlinkSnpType)> {
-        &self.file_num_snps
+    /// Same as `get_genotype_matrix`, but the SNPs are decoded into `f64`,
+    /// including the dominance encoding and the imputation of the missing
+    /// genotypes, to avoid the loss of precision in `f32` for large cohorts.
+    pub fn get_genotype_matrix_f64(
+        &self,
+        snps_range: Option<OrderedIntegerSet<usize>>,
+    ) -> Result<Array<f64, Ix2>, Error> {
+        let num_people = self.num_people;
+        let missing_strategy = self.missing_strategy;
+        let dominance_orientation = self.dominance_orientation;
+        let num_snps = match &snps_range {
+            None => self.total_num_snps(),
+            Some(range) => range.size(),
+        };
+        let mut chunk_iter = self.col_chunk_iter(max(num_snps, 1), snps_range);
+        if let Some(error) = chunk_iter.take_error() {
+            return Err(error);
+        }
+        chunk_iter.read_chunk_with(0, num_snps, |snp_bytes, snp_type| {
+            decode_snp_bytes_float(
+                snp_bytes,
+                num_people,
+                snp_type,
+                missing_strategy,
+                dominance_orientation,
+            )
+        })
     }
 
-    pub fn total_num_snps(&self) -> usize {
-        self.file_num_snps.iter().map(|pair| pair.0).sum::<usize>()
+    /// Reads the SNPs in `snps_range`, or all the SNPs if `None`, into a
+    /// `num_people x num_snps` matrix in the dominance encoding, regardless of
+    /// the `PlinkSnpType` with which the files were opened.
+    pub fn get_dominance_matrix(
+        &self,
+        snps_range: Option<OrderedIntegerSet<usize>>,
+    ) -> Result<Array<f32, Ix2>, Error> {
+        let num_people = self.num_people;
+        let missing_strategy = self.missing_strategy;
+        let dominance_orientation = self.dominance_orientation;
+        let num_snps = match &snps_range {
+            None => self.total_num_snps(),
+            Some(range) => range.size(),
+        };
+        let mut chunk_iter = self.col_chunk_iter(max(num_snps, 1), snps_range);
+        if let Some(error) = chunk_iter.take_error() {
+            return Err(error);
+        }
+        chunk_iter.read_chunk_with(0, num_snps, |snp_bytes, _| {
+            decode_snp_bytes_float(
+                snp_bytes,
+                num_people,
+                PlinkSnpType::Dominance,
+                missing_strategy,
+                dominance_orientation,
+            )
+        })
     }
 
-    pub fn get_minor_allele_frequencies(
+    /// Writes the genotypes of the SNPs in the `range`, or all the SNPs if
+    /// `None`, as tab-separated rows of people by columns of SNPs. The header
+    /// row contains the `snp_ids`, indexed by the SNP index, or the SNP
+    /// indices if `None`. If the `sample_ids` are provided, each row starts
+    /// with the ID of the person.
+    ///
+    /// The people are written in blocks such that the genotypes of a block
+    /// take up at most `DEFAULT_CHUNK_MEMORY_BUDGET_BYTES`, where each block
+    /// is read by `transposed_chunk_iter`, i.e. only the bytes of the people
+    /// in the block are read from each SNP in the range.
+    pub fn write_matrix_tsv<W: Write>(
         &self,
-        chunk_size: Option<usize>,
-    ) -> Vec<f32> {
-        let num_alleles = (self.num_people * 2) as f32;
-        self.col_chunk_iter(chunk_size.unwrap_or(50), None)
-            .into_par_iter()
-            .flat_map(|snps| {
-                snps.gencolumns()
-                    .into_iter()
-                    .map(|col| sum_f32(col.iter()) / num_alleles)
-                    .collect::<Vec<f32>>()
-            })
-            .collect()
+        writer: W,
+        range: Option<OrderedIntegerSet<usize>>,
+        sample_ids: Option<&[String]>,
+        snp_ids: Option<&[String]>,
+    ) -> Result<(), Error> {
+        let num_snps = match &range {
+            None => self.total_num_snps(),
+            Some(range) => range.size(),
+        };
+        let num_bytes_per_person =
+            std::mem::size_of::<f32>() * max(num_snps, 1);
+        let num_people_per_block =
+            max(1, DEFAULT_CHUNK_MEMORY_BUDGET_BYTES / num_bytes_per_person);
+        self.write_matrix_tsv_in_blocks(
+            writer,
+            range,
+            sample_ids,
+            snp_ids,
+            num_people_per_block,
+        )
     }
 
-    /// save the transpose of the BED file into `out_path`, which should have an
-    /// extension of .bedt wherein the n-th sequence of bytes corresponds to
-    /// the SNPs for the n-th person larger values of `snp_byte_chunk_size`
-    /// lead to faster performance, at the cost of higher memory requirement
-    pub fn create_bed_t(
-        &mut self,
-        file_index: usize,
-        out_path: &str,
-        snp_byte_chunk_size: usize,
+    fn write_matrix_tsv_in_blocks<W: Write>(
+        &self,
+        mut writer: W,
+        range: Option<OrderedIntegerSet<usize>>,
+        sample_ids: Option<&[String]>,
+        snp_ids: Option<&[String]>,
+        num_people_per_block: usize,
     ) -> Result<(), Error> {
-        let total_num_snps = self.total_num_snps();
-        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
-        match self.bed_path_list.get(file_index) {
-            Some(p) => {
-                let mut bed_buf = get_buf(p)?;
-                let mut buf_writer = BufWriter::new(
-                    OpenOptions::new()
-                        .create(true)
-                        .truncate(true)
-                        .write(true)
-                        .open(out_path)?,
-                );
-                let num_bytes_per_person = usize_div_ceil(total_num_snps, 4);
+        if let Some(ids) = sample_ids {
+            if ids.len() != self.num_people {
+                return Err(Error::Generic(format!(
+                    "there are {} sample IDs for {} people",
+                    ids.len(),
+                    self.num_people
+                )));
+            }
+        }
+        if let Some(ids) = snp_ids {
+            if ids.len() != self.total_num_snps() {
+                return Err(Error::Generic(format!(
+                    "there are {} SNP IDs for {} SNPs",
+                    ids.len(),
+                    self.total_num_snps()
+                )));
+            }
+        }
+        let range = range.unwrap_or_else(|| {
+            OrderedIntegerSet::from_slice(&[[0, self.total_num_snps() - 1]])
+        });
+        if let Some(last) = range.last() {
+            if last >= self.total_num_snps() {
+                return Err(Error::SnpIndexOutOfRange {
+                    index: last,
+                    total: self.total_num_snps(),
+                });
+            }
+        }
+        let num_snps = range.size();
 
-                let people_stride = snp_byte_chunk_size * 4;
-                let mut snp_bytes = vec![0u8; snp_byte_chunk_size];
+        let mut header: Vec<String> = Vec::with_capacity(num_snps + 1);
+        if sample_ids.is_some() {
+            header.push("sample_id".to_string());
+        }
+        header.extend(range.to_iter().map(|i| match snp_ids {
+            Some(ids) => ids[i].to_string(),
+            None => i.to_string(),
+        }));
+        writeln!(writer, "{}", header.join("\t"))?;
 
-                // write people_stride people at a time
-                for j in (0..self.num_people).step_by(people_stride) {
-                    let mut people_buf =
-                        vec![vec![0u8; num_bytes_per_person]; people_stride];
-                    if self.num_people - j < people_stride {
-                        let remaining_people = self.num_people % people_stride;
-                        snp_bytes =
-                            vec![0u8; usize_div_ceil(remaining_people, 4)];
-                    }
-                    let relative_seek_offset =
-                        (num_bytes_per_snp - snp_bytes.len()) as i64;
-                    // read 4 SNPs to the buffers at a time
-                    PlinkBed::seek_to_byte_containing_snp_i_person_j(
-                        &mut bed_buf,
-                        0,
-                        j,
-                        num_bytes_per_snp,
-                    )?;
-                    for (snp_byte_index, k) in
-                        (0..total_num_snps).step_by(4).enumerate()
-                    {
-                        for (snp_offset, _) in
-                            (k..min(k + 4, total_num_snps)).enumerate()
-                        {
-                            bed_buf.read_exact(&mut snp_bytes)?;
-                            for w in 0..snp_bytes.len() {
-                                people_buf[w][snp_byte_index] |=
-                                    (snp_bytes[w] & 0b11) << (snp_offset << 1);
-                                people_buf[w + 1][snp_byte_index] |=
-                                    ((snp_bytes[w] >> 2) & 0b11)
-                                        << (snp_offset << 1);
-                                people_buf[w + 2][snp_byte_index] |=
-                                    ((snp_bytes[w] >> 4) & 0b11)
-                                        << (snp_offset << 1);
-                                people_buf[w + 3][snp_byte_index] |=
-                                    ((snp_bytes[w] >> 6) & 0b11)
-                                        << (snp_offset << 1);
-                            }
-                            bed_buf.seek_relative(relative_seek_offset)?;
-                        }
-                    }
-                    for (p, buf) in people_buf.iter().enumerate() {
-                        if j + p < self.num_people {
-                            buf_writer.write_all(buf.as_slice())?;
-                        }
-                    }
+        let mut iter = self.transposed_chunk_iter_in_range(
+            Some(&range),
+            num_people_per_block,
+        )?;
+        let mut person = 0;
+        for block in &mut iter {
+            for row in block.genrows() {
+                let mut line: Vec<String> = Vec::with_capacity(num_snps + 1);
+                if let Some(ids) = sample_ids {
+                    line.push(ids[person].to_string());
                 }
-                Ok(())
+                line.extend(row.iter().map(|x: &f32| x.to_string()));
+                writeln!(writer, "{}", line.join("\t"))?;
+                person += 1;
             }
-            None => Err(Error::Generic(format!(
-                "file index out of range {} >= {}",
-                file_index,
-                self.bed_path_list.len()
-            ))),
         }
+        if let Some(error) = iter.take_error() {
+            return Err(error);
+        }
+        Ok(())
     }
 
-    pub fn create_dominance_geno_bed(
+    /// Writes the SNPs in the `range`, or all the SNPs if `None`, as a minimal
+    /// VCF with only the `GT` field, where `bim` and `fam` are the records of
+    /// all the SNPs and people respectively. Following PLINK, the second
+    /// allele in the bim is the REF allele and the first allele is the ALT
+    /// allele. The sample columns are the individual IDs in the `fam`.
+    pub fn write_vcf<W: Write>(
         &self,
-        file_index: usize,
-        out_path: &str,
+        mut writer: W,
+        bim: &[BimRecord],
+        fam: &[FamRecord],
+        range: Option<OrderedIntegerSet<usize>>,
     ) -> Result<(), Error> {
-        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
-        let mut writer = BufWriter::new(
-            OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(out_path)?,
-        );
-        writer.write_all(&PlinkBed::get_magic_bytes())?;
-        for bytes in self.byte_chunk_iter(
-            file_index,
-            NUM_MAGIC_BYTES,
-            NUM_MAGIC_BYTES + self.total_num_snps() * num_bytes_per_snp,
-            num_bytes_per_snp,
-        )? {
-            let out_bytes = PlinkSnps::from_geno(
-                PlinkSnps::new(bytes, self.num_people)
-                    .into_iter()
-                    .map(|s| match s {
-                        2 => 1,
-                        s => s,
-                    })
-                    .collect(),
-            )
-            .into_bytes();
-            writer.write_all(&out_bytes)?;
+        if bim.len() != self.total_num_snps() {
+            return Err(Error::Generic(format!(
+                "there are {} bim records for {} SNPs",
+                bim.len(),
+                self.total_num_snps()
+            )));
         }
-        Ok(())
-    }
+        if fam.len() != self.num_people {
+            return Err(Error::Generic(format!(
+                "there are {} fam records for {} people",
+                fam.len(),
+                self.num_people
+            )));
+        }
+        writeln!(writer, "##fileformat=VCFv4.2")?;
+        let mut contigs = HashSet::new();
+        for record in bim.iter() {
+            if contigs.insert(record.chrom.as_str()) {
+                writeln!(writer, "##contig=<ID={}>", record.chrom)?;
+            }
+        }
+        writeln!(
+            writer,
+            "##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">"
+        )?;
+        write!(
+            writer,
+            "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT"
+        )?;
+        for record in fam.iter() {
+            write!(writer, "\t{}", record.individual_id)?;
+        }
+        writeln!(writer)?;
 
-    // the first person is the lowest two bits
-    // 00 -> 2 homozygous for the first allele in the .bim file (usually the
-    // minor allele) 01 -> 0 missing genotype
-    // 10 -> 1 heterozygous
-    // 11 -> 0 homozygous for the second allele in the .bim file (usually the
-    // major allele)
-    pub fn create_bed(
-        arr: &Array<u8, Ix2>,
-        out_path: &str,
-    ) -> Result<(), Error> {
-        let (num_people, _num_snps) = arr.dim();
-        let mut buf_writer = BufWriter::new(
-            OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(out_path)?,
-        );
-        buf_writer.write_all(&[0x6c, 0x1b, 0x1])?;
-        for col in arr.gencolumns() {
-            let mut i = 0;
-            for _ in 0..num_people / 4 {
-                buf_writer.write_all(&[geno_to_lowest_two_bits(col[i])
-                    | (geno_to_lowest_two_bits(col[i + 1]) << 2)
-                    | (geno_to_lowest_two_bits(col[i + 2]) << 4)
-                    | (geno_to_lowest_two_bits(col[i + 3]) << 6)])?;
-                i += 4;
-            }
-            let remainder = num_people % 4;
-            if remainder > 0 {
-                let mut byte = 0u8;
-                for j in 0..remainder {
-                    byte |= geno_to_lowest_two_bits(col[i + j]) << (j * 2);
-                }
-                buf_writer.write_all(&[byte])?;
+        let range = range.unwrap_or_else(|| {
+            OrderedIntegerSet::from_slice(&[[0, self.total_num_snps() - 1]])
+        });
+        let mut chunk_iter = self.col_chunk_iter(1, Some(range.clone()));
+        if let Some(error) = chunk_iter.take_error() {
+            return Err(error);
+        }
+        let mut snp_bytes =
+            vec![0u8; PlinkBed::num_bytes_per_snp(self.num_people)];
+        for snp_index in range.to_iter() {
+            chunk_iter.read_snp_bytes(snp_index, &mut snp_bytes)?;
+            let record = &bim[snp_index];
+            write!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t.\t.\t.\tGT",
+                record.chrom,
+                record.coordinate,
+                record.variant_id,
+                record.allele_2,
+                record.allele_1
+            )?;
+            for j in 0..self.num_people {
+                let gt = match (snp_bytes[j / 4] >> (2 * (j % 4))) & 0b11 {
+                    HOMOZYGOUS_FIRST_GENOTYPE_BITS => "1/1",
+                    HETEROZYGOUS_GENOTYPE_BITS => "0/1",
+                    HOMOZYGOUS_SECOND_GENOTYPE_BITS => "0/0",
+                    _ => "./.",
+                };
+                write!(writer, "\t{}", gt)?;
             }
+            writeln!(writer)?;
         }
         Ok(())
     }
 
-    fn verify_magic_bytes(bed_filepath: &str) -> Result<(), Error> {
-        let mut bed_buf = get_buf(bed_filepath)?;
+    /// Writes the SNPs in the `range`, or all the SNPs if `None`, in the PLINK
+    /// `.raw` format of `--recode A`, where `fam` and `bim` are the records of
+    /// all the people and SNPs respectively. The header has the columns
+    /// `FID IID PAT MAT SEX PHENOTYPE` followed by a `{variant_id}_{allele_1}`
+    /// column for each SNP, and each row has the fam fields of a person
+    /// followed by the number of copies of the first allele at each SNP,
+    /// regardless of the SNP type, or `NA` if the genotype is missing.
+    ///
+    /// The people are read in blocks as in `transposed_chunk_iter`, where
+    /// each block is within `DEFAULT_CHUNK_MEMORY_BUDGET_BYTES`.
+    pub fn write_raw<W: Write>(
+        &self,
+        writer: W,
+        fam: &[FamRecord],
+        bim: &[BimRecord],
+        range: Option<OrderedIntegerSet<usize>>,
+    ) -> Result<(), Error> {
+        let num_snps = match &range {
+            None => self.total_num_snps(),
+            Some(range) => range.size(),
+        };
+        let num_bytes_per_person =
+            std::mem::size_of::<f32>() * max(num_snps, 1);
+        let num_people_per_block =
+            max(1, DEFAULT_CHUNK_MEMORY_BUDGET_BYTES / num_bytes_per_person);
+        self.write_raw_in_blocks(writer, fam, bim, range, num_people_per_block)
+    }
 
-        // check if PLINK bed file has the correct file signature
-        let mut magic_bytes = [0u8; 3];
-        if let Err(io_error) = bed_buf.read_exact(&mut magic_bytes) {
-            return Err(Error::IO {
-                why: format!(
-                    "Failed to read the first three bytes of {}: {}",
-                    bed_filepath, io_error
-                ),
-                io_error,
-            });
+    fn write_raw_in_blocks<W: Write>(
+        &self,
+        mut writer: W,
+        fam: &[FamRecord],
+        bim: &[BimRecord],
+        range: Option<OrderedIntegerSet<usize>>,
+        num_people_per_block: usize,
+    ) -> Result<(), Error> {
+        if fam.len() != self.num_people {
+            return Err(Error::Generic(format!(
+                "there are {} fam records for {} people",
+                fam.len(),
+                self.num_people
+            )));
         }
-        let expected_bytes = PlinkBed::get_magic_bytes();
-        if magic_bytes != expected_bytes {
-            return Err(Error::BadFormat(format!(
-                "The first three bytes of the PLINK bed file {} are supposed to be 0x{:x?}, but found 0x{:x?}",
-                bed_filepath, expected_bytes, magic_bytes
+        if bim.len() != self.total_num_snps() {
+            return Err(Error::Generic(format!(
+                "there are {} bim records for {} SNPs",
+                bim.len(),
+                self.total_num_snps()
             )));
         }
+        let range = range.unwrap_or_else(|| {
+            OrderedIntegerSet::from_slice(&[[0, self.total_num_snps() - 1]])
+        });
+        if let Some(last) = range.last() {
+            if last >= self.total_num_snps() {
+                return Err(Error::SnpIndexOutOfRange {
+                    index: last,
+                    total: self.total_num_snps(),
+                });
+            }
+        }
+        write!(writer, "FID IID PAT MAT SEX PHENOTYPE")?;
+        for i in range.to_iter() {
+            write!(writer, " {}_{}", bim[i].variant_id, bim[i].allele_1)?;
+        }
+        writeln!(writer)?;
+
+        let mut a1_counts = [0f32; 4];
+        a1_counts[HOMOZYGOUS_FIRST_GENOTYPE_BITS as usize] = 2.;
+        a1_counts[HETEROZYGOUS_GENOTYPE_BITS as usize] = 1.;
+        a1_counts[HOMOZYGOUS_SECOND_GENOTYPE_BITS as usize] = 0.;
+        a1_counts[MISSING_GENOTYPE_BITS as usize] = f32::NAN;
+        let mut iter = PlinkTransposedChunkIter::new(
+            &self.bed_path_list,
+            self.file_snp_indices(&range),
+            self.num_people,
+            num_people_per_block,
+            vec![a1_counts; range.size()],
+        )?;
+        let mut records = fam.iter();
+        for block in &mut iter {
+            for (row, record) in block.genrows().into_iter().zip(&mut records) {
+                write!(
+                    writer,
+                    "{} {} {} {} {} {}",
+                    record.family_id,
+                    record.individual_id,
+                    record.paternal_id,
+                    record.maternal_id,
+                    record.sex,
+                    record.phenotype
+                )?;
+                for x in row.iter() {
+                    if x.is_nan() {
+                        write!(writer, " NA")?;
+                    } else {
+                        write!(writer, " {}", x)?;
+                    }
+                }
+                writeln!(writer)?;
+            }
+        }
+        if let Some(error) = iter.take_error() {
+            return Err(error);
+        }
         Ok(())
     }
 
-    #[inline]
-    pub fn get_magic_bytes() -> [u8; 3] {
-        MAGIC_BYTES
+    /// Reads the SNP at the `snp_index` into a vector of the genotypes of all
+    /// the people, decoded according to the type of the file containing it.
+    pub fn get_snp(&self, snp_index: usize) -> Result<Array<f32, Ix1>, Error> {
+        let (snp_bytes, file_snp_type) = self.read_packed_snp(snp_index)?;
+        let snp_type = match &self.snp_types {
+            Some(types) => types[snp_index],
+            None => file_snp_type,
+        };
+        Ok(Array::from_vec(decode_snp_bytes_float(
+            &snp_bytes,
+            self.num_people,
+            snp_type,
+            self.missing_strategy,
+            self.dominance_orientation,
+        )))
     }
 
-    #[inline]
-    pub fn get_num_magic_bytes() -> usize {
-        NUM_MAGIC_BYTES
+    /// Reads the `num_bytes_per_snp` bytes of the SNP at the `snp_index` as
+    /// encoded in the bed file, without decoding them, e.g. to copy the SNP
+    /// with `BedWriter::push_packed_snp`.
+    pub fn packed_snp(&self, snp_index: usize) -> Result<Vec<u8>, Error> {
+        Ok(self.read_packed_snp(snp_index)?.0)
     }
 
-    #[inline]
-    fn num_bytes_per_snp(num_people: usize) -> usize {
-        usize_div_ceil(num_people, NUM_PEOPLE_PER_BYTE)
+    /// Reads the bytes of the SNP at the `snp_index` along with the type of
+    /// the file containing it.
+    fn read_packed_snp(
+        &self,
+        snp_index: usize,
+    ) -> Result<(Vec<u8>, PlinkSnpType), Error> {
+        let file_snp_indexer = FileSnpIndexer::new(self.file_num_snps.clone());
+        let (file_index, snp_index_within_file, file_snp_type) =
+            match file_snp_indexer.get_file_snp_index(snp_index) {
+                Some(file_snp_index) => file_snp_index,
+                None => {
+                    return Err(Error::SnpIndexOutOfRange {
+                        index: snp_index,
+                        total: self.total_num_snps(),
+                    })
+                }
+            };
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
+        let mut buf = get_file_buf(&self.bed_path_list[file_index])?;
+        PlinkBed::seek_to_byte_containing_snp_i_person_j(
+            &mut buf,
+            snp_index_within_file,
+            0,
+            num_bytes_per_snp,
+        )?;
+        let mut snp_bytes = vec![0u8; num_bytes_per_snp];
+        buf.read_exact(&mut snp_bytes)?;
+        Ok((snp_bytes, file_snp_type))
     }
 
-    /// makes the BufReader point to the start of the byte containing the SNP i
-    /// individual j 0-indexing
-    fn seek_to_byte_containing_snp_i_person_j<B: Seek>(
-        buf: &mut B,
-        snp_i: usize,
-        person_j: usize,
-        num_bytes_per_snp: usize,
-    ) -> Result<(), io::Error> {
-        // the first NUM_MAGIC_BYTES bytes are the file signature
-        buf.seek(SeekFrom::Start(
-            (NUM_MAGIC_BYTES
-                + num_bytes_per_snp * snp_i
-                + person_j / NUM_PEOPLE_PER_BYTE) as u64,
-        ))?;
-        Ok(())
+    /// Returns the (family ID, individual ID) of each person in the order of
+    /// the rows of the genotype matrices, after verifying that all the fam
+    /// files list the same IDs in the same order.
+    pub fn sample_ids(&self) -> Result<Vec<(String, String)>, Error> {
+        let sample_ids = read_fam_sample_ids(&self.fam_path_list[0])?;
+        for path in self.fam_path_list.iter().skip(1) {
+            let other_ids = read_fam_sample_ids(path)?;
+            if let Some((i, (id, other_id))) = sample_ids
+                .iter()
+                .zip(other_ids.iter())
+                .enumerate()
+                .find(|(_, (id, other_id))| id != other_id)
+            {
+                return Err(Error::Generic(format!(
+                    "the sample ID {:?} on line {} of {} differs from the \
+                    sample ID {:?} in {}",
+                    other_id,
+                    i + 1,
+                    path,
+                    id,
+                    self.fam_path_list[0]
+                )));
+            }
+        }
+        Ok(sample_ids)
     }
-}
-
-fn usize_div_ceil(a: usize, divisor: usize) -> usize {
-    a / divisor + (a % divisor != 0) as usize
-}
-
-pub fn lowest_two_bits_to_geno(byte: u8) -> u8 {
-    // 00 -> 2 homozygous for the first allele in the .bim file (usually the
-    // minor allele) 01 -> 0 missing genotype
-    // 10 -> 1 heterozygous
-    // 11 -> 0 homozygous for the second allele in the .bim file (usually the
-    // major allele)
-    let a = (byte & 0b10) >> 1;
-    let b = byte & 1;
-    (((a | b) ^ 1) << 1) | (a & (!b))
-}
 
-pub fn geno_to_lowest_two_bits(geno: u8) -> u8 {
-    // 00 -> 2 homozygous for the first allele in the .bim file (usually the
-    // minor allele) 01 -> 0 missing genotype
-    // 10 -> 1 heterozygous
-    // 11 -> 0 homozygous for the second allele in the .bim file (usually the
-    // major allele)
-    let not_a = ((geno & 0b10) >> 1) ^ 1;
-    let not_b = (geno & 1) ^ 1;
-    (not_a << 1) | (not_b & not_a)
-}
+    pub fn get_bed_path_list(&self) -> &Vec<String> {
+        &self.bed_path_list
+    }
 
-fn get_num_people_last_byte(total_num_people: usize) -> Option<usize> {
-    if total_num_people == 0 {
-        None
-    } else {
-        match total_num_people % NUM_PEOPLE_PER_BYTE {
-            0 => Some(NUM_PEOPLE_PER_BYTE),
-            x => Some(x),
-        }
+    pub fn get_file_num_snps(&self) -> &Vec<(usize, PlinkSnpType)> {
+        &self.file_num_snps
     }
-}
 
-fn get_line_count(filename: &str) -> Result<usize, Error> {
-    let fam_buf = get_buf(filename)?;
-    Ok(fam_buf.lines().count())
-}
+    pub fn total_num_snps(&self) -> usize {
+        self.total_num_snps
+    }
 
-struct FileSnpIndexer {
-    file_num_snps: Vec<(usize, PlinkSnpType)>,
-}
+    /// Returns the number of SNPs per chunk such that a chunk of `f32`
+    /// genotypes takes up at most `DEFAULT_CHUNK_MEMORY_BUDGET_BYTES`, i.e.
+    /// `max(1, DEFAULT_CHUNK_MEMORY_BUDGET_BYTES / (4 * num_people))`.
+    pub fn get_default_chunk_size(&self) -> usize {
+        let num_bytes_per_snp = std::mem::size_of::<f32>() * self.num_people;
+        max(1, DEFAULT_CHUNK_MEMORY_BUDGET_BYTES / num_bytes_per_snp)
+    }
 
-impl FileSnpIndexer {
-    fn new(file_num_snps: Vec<(usize, PlinkSnpType)>) -> FileSnpIndexer {
-        FileSnpIndexer {
-            file_num_snps,
+    /// Computes the frequency `sum / (2 * num_people)` of the first allele in
+    /// the .bim file of each SNP, which is not necessarily the minor allele.
+    /// If `chunk_size` is `None`, the chunk size will be determined by
+    /// `get_default_chunk_size`. The i-th frequency is that of the i-th SNP.
+    ///
+    /// The frequencies are computed from the cached genotype counts without
+    /// reading the bed files if `precompute_snp_stats` has been called and all
+    /// the SNPs are of the additive type. Otherwise, an error is returned if
+    /// any of the SNPs cannot be read.
+    pub fn get_allele_frequencies(
+        &self,
+        chunk_size: Option<usize>,
+    ) -> Result<Vec<f32>, Error> {
+        if let Some(counts) = &self.snp_counts {
+            if self.all_snps_additive() {
+                let num_people = self.num_people;
+                let missing_strategy = self.missing_strategy;
+                return Ok(counts
+                    .iter()
+                    .map(|c| {
+                        allele_frequency_from_counts(
+                            c,
+                            num_people,
+                            missing_strategy,
+                        )
+                    })
+                    .collect());
+            }
         }
+        let chunk_size =
+            chunk_size.unwrap_or_else(|| self.get_default_chunk_size());
+        self.get_allele_frequencies_in_range(None, chunk_size)
     }
 
-    /// returns a `Some` of a tuple (file_index, snp_index_within_file)
-    /// if the SNP is within range. `None` otherwise.
-    fn get_file_snp_index(
+    /// Computes the minor allele frequency `min(f, 1 - f)` of each SNP, where
+    /// `f` is the frequency of the first allele from `get_allele_frequencies`.
+    pub fn get_minor_allele_frequencies(
         &self,
-        snp_index: usize,
-    ) -> Option<(usize, usize, PlinkSnpType)> {
-        let mut acc = 0;
-        for (file_index, (count, snp_type)) in
-            self.file_num_snps.iter().enumerate()
-        {
-            if snp_index < acc + *count {
-                return Some((file_index, snp_index - acc, *snp_type));
+        chunk_size: Option<usize>,
+    ) -> Result<Vec<f32>, Error> {
+        Ok(self
+            .get_allele_frequencies(chunk_size)?
+            .into_iter()
+            .map(|f| f.min(1. - f))
+            .collect())
+    }
+
+    /// Computes the frequencies of the SNPs in the `range`, or all the SNPs
+    /// if `None`, where the i-th frequency is that of the i-th SNP in the
+    /// range.
+    fn get_allele_frequencies_in_range(
+        &self,
+        range: Option<OrderedIntegerSet<usize>>,
+        chunk_size: usize,
+    ) -> Result<Vec<f32>, Error> {
+        let num_alleles = (self.num_people * 2) as f32;
+        let num_snps = match &range {
+            None => self.total_num_snps(),
+            Some(range) => range.size(),
+        };
+        let mut frequencies = vec![0f32; num_snps];
+
+        // each chunk of SNPs fills the slot at its position in the range
+        let chunk_iter = self.col_chunk_iter(chunk_size, range);
+        let error_handle = chunk_iter.get_error_handle();
+        frequencies
+            .par_chunks_mut(chunk_size)
+            .zip(chunk_iter.into_par_iter())
+            .for_each(|(slot, snps)| {
+                for (dest, col) in slot.iter_mut().zip(snps.gencolumns()) {
+                    *dest = sum_f32(col.iter()) / num_alleles;
+                }
+            });
+        if let Some(error) = error_handle.lock().unwrap().take() {
+            return Err(error);
+        }
+        Ok(frequencies)
+    }
+
+    /// Computes for each SNP the Pearson correlation between its genotypes and
+    /// the `phenotype` of the people, excluding the people whose genotypes are
+    /// missing. The correlation is NaN for the monomorphic SNPs. The chunks of
+    /// `chunk_size` SNPs, determined by `get_default_chunk_size` if `None`,
+    /// are processed in parallel.
+    pub fn snp_phenotype_correlations(
+        &self,
+        phenotype: &[f32],
+        chunk_size: Option<usize>,
+    ) -> Result<Vec<f32>, Error> {
+        if phenotype.len() != self.num_people {
+            return Err(Error::Generic(format!(
+                "the phenotype has {} values but there are {} people",
+                phenotype.len(),
+                self.num_people
+            )));
+        }
+        let num_people = self.num_people;
+        let dominance_orientation = self.dominance_orientation;
+        let chunk_size =
+            chunk_size.unwrap_or_else(|| self.get_default_chunk_size());
+        self.map_snp_bytes(chunk_size, |snp_bytes, snp_type| {
+            // the missing genotypes are decoded as NaN
+            let mut snp_vec = decode_snp_bytes_float(
+                snp_bytes,
+                num_people,
+                snp_type,
+                MissingStrategy::Zero,
+                dominance_orientation,
+            );
+            let is_missing =
+                decode_snp_bytes_with(snp_bytes, num_people, |bits| {
+                    bits == MISSING_GENOTYPE_BITS
+                });
+            for (x, is_missing) in snp_vec.iter_mut().zip(is_missing) {
+                if is_missing {
+                    *x = f32::NAN;
+                }
             }
-            acc += *count;
+            pearson_correlation_skipping_nan(snp_vec.iter(), phenotype.iter())
+        })
+    }
+
+    /// Computes for each SNP the `(sum, sum_sq, num_non_missing)` of its
+    /// genotypes over the people whose genotypes are not missing, where
+    /// `sum_sq` is the sum of the squared genotypes, in a single pass over the
+    /// bed files without reading the genotype matrix into memory. The SNPs of
+    /// the dominance type are summed in the dominance encoding. The chunks of
+    /// `chunk_size` SNPs, determined by `get_default_chunk_size` if `None`,
+    /// are processed in parallel, and the sums are accumulated in `f64`.
+    pub fn snp_sums(
+        &self,
+        chunk_size: Option<usize>,
+    ) -> Result<Vec<(f64, f64, u32)>, Error> {
+        let num_people = self.num_people;
+        let dominance_orientation = self.dominance_orientation;
+        let chunk_size =
+            chunk_size.unwrap_or_else(|| self.get_default_chunk_size());
+        self.map_snp_bytes(chunk_size, |snp_bytes, snp_type| {
+            let snp_vec: Vec<f64> = decode_snp_bytes_float(
+                snp_bytes,
+                num_people,
+                snp_type,
+                MissingStrategy::Zero,
+                dominance_orientation,
+            );
+            let is_missing =
+                decode_snp_bytes_with(snp_bytes, num_people, |bits| {
+                    bits == MISSING_GENOTYPE_BITS
+                });
+            snp_vec
+                .iter()
+                .zip(is_missing)
+                .filter(|(_, is_missing)| !is_missing)
+                .fold((0., 0., 0), |(sum, sum_sq, n), (&x, _)| {
+                    (sum + x, sum_sq + x * x, n + 1)
+                })
+        })
+    }
+
+    /// Counts the genotypes of each SNP in a single pass over the bed files,
+    /// where the chunks of `chunk_size` SNPs, determined by
+    /// `get_default_chunk_size` if `None`, are processed in parallel. The
+    /// cached counts are returned instead if `precompute_snp_stats` has been
+    /// called.
+    pub fn genotype_counts(
+        &self,
+        chunk_size: Option<usize>,
+    ) -> Result<Vec<GenotypeCounts>, Error> {
+        if let Some(counts) = &self.snp_counts {
+            return Ok(counts.clone());
         }
-        None
+        let num_people = self.num_people;
+        let chunk_size =
+            chunk_size.unwrap_or_else(|| self.get_default_chunk_size());
+        self.map_snp_bytes(chunk_size, |snp_bytes, _| {
+            GenotypeCounts::from_snp_bytes(snp_bytes, num_people)
+        })
     }
-}
 
-#[derive(Copy, Clone, PartialEq, Debug)]
-pub enum PlinkSnpType {
-    Additive,
-    Dominance,
-}
+    /// Counts the genotypes of all the SNPs in one pass over the bed files and
+    /// caches the counts, so that the subsequent calls to `genotype_counts`,
+    /// `snp_flags` and `get_allele_frequencies` do not read the bed
+    /// files again. Any previously cached counts are recomputed. The cache
+    /// has to be cleared with `invalidate_snp_stats` if the bed files change.
+    pub fn precompute_snp_stats(&mut self) -> Result<(), Error> {
+        self.snp_counts = None;
+        self.snp_counts = Some(self.genotype_counts(None)?);
+        Ok(())
+    }
 
-pub struct PlinkColChunkIter {
-    buf: Vec<BufReader<File>>,
-    file_num_snps: Vec<(usize, PlinkSnpType)>,
-    range: OrderedIntegerSet<usize>,
-    num_snps_per_iter: usize,
-    num_people: usize,
-    num_snps_in_range: usize,
-    range_cursor: usize,
-    last_read_file_snp_index: Option<(usize, usize)>,
-    bed_path_list: Vec<String>,
-    file_snp_indexer: FileSnpIndexer,
-}
+    /// Clears the genotype counts cached by `precompute_snp_stats`.
+    pub fn invalidate_snp_stats(&mut self) {
+        self.snp_counts = None;
+    }
 
-impl PlinkColChunkIter {
-    pub fn new(
-        file_num_snps: Vec<(usize, PlinkSnpType)>,
-        range: OrderedIntegerSet<usize>,
-        num_snps_per_iter: usize,
-        num_people: usize,
-        bed_path_list: Vec<String>,
-    ) -> PlinkColChunkIter {
-        let num_snps_in_range = range.size();
-        let first = range.first();
-        let buf = PlinkColChunkIter::get_buf_list(&bed_path_list).unwrap();
-        let file_snp_indexer = FileSnpIndexer::new(file_num_snps.clone());
-        let mut iter = PlinkColChunkIter {
-            buf,
-            file_num_snps,
-            range,
-            num_snps_per_iter,
-            num_people,
-            num_snps_in_range,
-            range_cursor: 0,
-            last_read_file_snp_index: None,
-            bed_path_list,
-            file_snp_indexer,
-        };
-        if let Some(start) = first {
-            iter.seek_to_snp(start).unwrap();
+    /// Summarizes the dataset from the `genotype_counts` of all the SNPs,
+    /// which are read in a single pass over the bed files unless cached by
+    /// `precompute_snp_stats`.
+    pub fn dataset_summary(&self) -> Result<DatasetSummary, Error> {
+        Ok(DatasetSummary::from_counts(
+            &self.genotype_counts(None)?,
+            self.num_people,
+            self.bed_path_list.len(),
+        ))
+    }
+
+    /// Flags the SNPs whose genotypes are all missing or that are
+    /// monomorphic, along with their minor allele frequencies, from the
+    /// `genotype_counts`.
+    pub fn snp_flags(
+        &self,
+        chunk_size: Option<usize>,
+    ) -> Result<Vec<SnpFlag>, Error> {
+        Ok(self
+            .genotype_counts(chunk_size)?
+            .into_iter()
+            .map(SnpFlag::from)
+            .collect())
+    }
+
+    /// Computes for each person the polygenic score, i.e. the sum of the
+    /// genotypes of the SNPs in the `range`, or all the SNPs if `None`,
+    /// weighted by the `weights`, where `weights` is aligned to the SNPs in the
+    /// `range`. The missing genotypes are decoded according to `missing`, so
+    /// that they contribute nothing under `MissingStrategy::Zero` and the mean
+    /// genotype of the SNP times its weight under
+    /// `MissingStrategy::MeanImpute`.
+    pub fn polygenic_score(
+        &self,
+        weights: &[f32],
+        range: Option<OrderedIntegerSet<usize>>,
+        missing: MissingStrategy,
+    ) -> Result<Vec<f32>, Error> {
+        self.weighted_genotype_sum(weights, None, range, missing)
+    }
+
+    /// Same as `polygenic_score`, except that the weights are for the
+    /// `effect_alleles`, which are aligned to the SNPs in the `range`, and
+    /// `bim` contains the records of all the SNPs. As the genotype counts the
+    /// first allele in the bim, the contribution of a SNP becomes
+    /// `(2 - genotype) * weight` if its effect allele is the second allele.
+    /// If an effect allele matches neither allele of the SNP, the SNP is
+    /// skipped if `skip_mismatched` is true, and an error is returned
+    /// otherwise. The alleles are compared case-insensitively.
+    ///
+    /// As the dominance encoding of a genotype is not a count of the first
+    /// allele, an error is returned if the effect allele of a SNP of the
+    /// `PlinkSnpType::Dominance` type is the second allele.
+    ///
+    /// Returns the scores along with the number of skipped SNPs.
+    #[allow(clippy::too_many_arguments)]
+    pub fn polygenic_score_with_effect_alleles(
+        &self,
+        weights: &[f32],
+        effect_alleles: &[String],
+        bim: &[BimRecord],
+        range: Option<OrderedIntegerSet<usize>>,
+        missing: MissingStrategy,
+        skip_mismatched: bool,
+    ) -> Result<(Vec<f32>, usize), Error> {
+        if bim.len() != self.total_num_snps() {
+            return Err(Error::Generic(format!(
+                "there are {} bim records for {} SNPs",
+                bim.len(),
+                self.total_num_snps()
+            )));
+        }
+        let range = range.unwrap_or_else(|| {
+            OrderedIntegerSet::from_slice(&[[0, self.total_num_snps() - 1]])
+        });
+        let num_snps = range.size();
+        if weights.len() != num_snps || effect_alleles.len() != num_snps {
+            return Err(Error::Generic(format!(
+                "there are {} weights and {} effect alleles for {} SNPs",
+                weights.len(),
+                effect_alleles.len(),
+                num_snps
+            )));
+        }
+        let snp_types = self.snp_types();
+        let mut oriented_weights = weights.to_vec();
+        let mut flipped = vec![false; num_snps];
+        let mut num_skipped = 0;
+        for (k, snp_index) in range.to_iter().enumerate() {
+            let record = &bim[snp_index];
+            let effect_allele = &effect_alleles[k];
+            if effect_allele.eq_ignore_ascii_case(&record.allele_1) {
+                continue;
+            } else if effect_allele.eq_ignore_ascii_case(&record.allele_2) {
+                if snp_types[snp_index] == PlinkSnpType::Dominance {
+                    return Err(Error::Generic(format!(
+                        "cannot flip the effect allele {} of SNP {} of the \
+                        dominance type",
+                        effect_allele, record.variant_id
+                    )));
+                }
+                flipped[k] = true;
+            } else if skip_mismatched {
+                oriented_weights[k] = 0.;
+                num_skipped += 1;
+            } else {
+                return Err(Error::Generic(format!(
+                    "the effect allele {} of SNP {} matches neither {} nor {}",
+                    effect_allele,
+                    record.variant_id,
+                    record.allele_1,
+                    record.allele_2
+                )));
+            }
+        }
+        let scores = self.weighted_genotype_sum(
+            &oriented_weights,
+            Some(&flipped),
+            Some(range),
+            missing,
+        )?;
+        Ok((scores, num_skipped))
+    }
+
+    /// Sums the genotypes of the SNPs in the `range` weighted by the
+    /// `weights` for each person, where the non-missing genotypes of the SNPs
+    /// marked in `flipped` are replaced by 2 minus the genotype. Both
+    /// `weights` and `flipped` are aligned to the SNPs in the `range`.
+    fn weighted_genotype_sum(
+        &self,
+        weights: &[f32],
+        flipped: Option<&[bool]>,
+        range: Option<OrderedIntegerSet<usize>>,
+        missing: MissingStrategy,
+    ) -> Result<Vec<f32>, Error> {
+        let num_people = self.num_people;
+        let dominance_orientation = self.dominance_orientation;
+        let chunk_size = self.get_default_chunk_size();
+        let mut chunk_iter = self.col_chunk_iter(chunk_size, range);
+        if let Some(error) = chunk_iter.take_error() {
+            return Err(error);
+        }
+        let num_snps = chunk_iter.num_snps_in_range;
+        if weights.len() != num_snps {
+            return Err(Error::Generic(format!(
+                "there are {} weights for {} SNPs",
+                weights.len(),
+                num_snps
+            )));
+        }
+        let mut scores = vec![0f32; num_people];
+        for start in (0..num_snps).step_by(chunk_size) {
+            let mut k = start;
+            let snps = chunk_iter.read_chunk_with(
+                start,
+                chunk_size,
+                |snp_bytes, snp_type| {
+                    let mut snp_vec = decode_snp_bytes_float(
+                        snp_bytes,
+                        num_people,
+                        snp_type,
+                        missing,
+                        dominance_orientation,
+                    );
+                    if flipped.is_some_and(|flipped| flipped[k]) {
+                        let is_missing = decode_snp_bytes_with(
+                            snp_bytes,
+                            num_people,
+                            |bits| bits == MISSING_GENOTYPE_BITS,
+                        );
+                        for (x, is_missing) in
+                            snp_vec.iter_mut().zip(is_missing)
+                        {
+                            if !is_missing
+                                || missing == MissingStrategy::MeanImpute
+                            {
+                                *x = 2. - *x;
+                            }
+                        }
+                    }
+                    k += 1;
+                    snp_vec
+                },
+            )?;
+            let chunk_weights =
+                Array::from_vec(weights[start..start + snps.dim().1].to_vec());
+            scores.par_iter_mut().enumerate().for_each(|(i, score)| {
+                *score += snps.row(i).dot(&chunk_weights);
+            });
+        }
+        Ok(scores)
+    }
+
+    /// Maps the bytes of each SNP along with its type to a value by `f`, where
+    /// the chunks of `chunk_size` SNPs are processed in parallel. The i-th
+    /// value is that of the i-th SNP.
+    fn map_snp_bytes<T, F>(
+        &self,
+        chunk_size: usize,
+        f: F,
+    ) -> Result<Vec<T>, Error>
+    where
+        T: Clone + Default + Send,
+        F: Fn(&[u8], PlinkSnpType) -> T + Sync, {
+        self.map_snp_bytes_in_range(
+            &OrderedIntegerSet::from_slice(&[[0, self.total_num_snps() - 1]]),
+            chunk_size,
+            f,
+        )
+    }
+
+    /// Same as `map_snp_bytes`, but only for the SNPs in the `range`, whose
+    /// indices have to be less than the total number of SNPs. The i-th value
+    /// is that of the i-th SNP in the `range`.
+    fn map_snp_bytes_in_range<T, F>(
+        &self,
+        range: &OrderedIntegerSet<usize>,
+        chunk_size: usize,
+        f: F,
+    ) -> Result<Vec<T>, Error>
+    where
+        T: Clone + Default + Send,
+        F: Fn(&[u8], PlinkSnpType) -> T + Sync, {
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
+        let mut values = vec![T::default(); range.size()];
+        values.par_chunks_mut(chunk_size).enumerate().try_for_each(
+            |(k, slot)| {
+                let start = k * chunk_size;
+                let chunk_range = range.slice(start..start + slot.len());
+                let mut chunk_iter =
+                    self.col_chunk_iter(slot.len(), Some(chunk_range.clone()));
+                if let Some(error) = chunk_iter.take_error() {
+                    return Err(error);
+                }
+                let mut snp_bytes = vec![0u8; num_bytes_per_snp];
+                for (snp_index, dest) in
+                    chunk_range.to_iter().zip(slot.iter_mut())
+                {
+                    let file_snp_type =
+                        chunk_iter.read_snp_bytes(snp_index, &mut snp_bytes)?;
+                    let snp_type = match &self.snp_types {
+                        Some(types) => types[snp_index],
+                        None => file_snp_type,
+                    };
+                    *dest = f(&snp_bytes, snp_type);
+                }
+                Ok(())
+            },
+        )?;
+        Ok(values)
+    }
+
+    /// Draws `k` distinct SNP indices uniformly at random, using a generator
+    /// seeded with `seed`, and returns them as a set ready for
+    /// `col_chunk_iter`. The generator is implemented in this crate, so that
+    /// the same `k` and `seed` give the same set across runs and platforms.
+    pub fn random_snp_subset(
+        &self,
+        k: usize,
+        seed: u64,
+    ) -> Result<OrderedIntegerSet<usize>, Error> {
+        let num_snps = self.total_num_snps();
+        if k > num_snps {
+            return Err(Error::Generic(format!(
+                "cannot draw {} distinct SNPs out of {}",
+                k, num_snps
+            )));
+        }
+        // Floyd's algorithm, which draws k random numbers for any num_snps
+        let mut rng = SplitMix64::new(seed);
+        let mut chosen = HashSet::with_capacity(k);
+        for j in num_snps - k..num_snps {
+            let t = rng.next_below(j as u64 + 1) as usize;
+            if !chosen.insert(t) {
+                chosen.insert(j);
+            }
+        }
+        let mut indices: Vec<usize> = chosen.into_iter().collect();
+        indices.sort_unstable();
+        let mut set = OrderedIntegerSet::new();
+        for i in indices {
+            set.collect(i);
+        }
+        Ok(set)
+    }
+
+    /// Averages the allele frequencies from `get_minor_allele_frequencies` in
+    /// sliding windows of `window` consecutive SNPs, where the windows start
+    /// every `step` SNPs. The last window is shortened to end at the last SNP.
+    /// Returns an error if either `window` or `step` is 0.
+    pub fn windowed_maf(
+        &self,
+        window: usize,
+        step: usize,
+    ) -> Result<Vec<f32>, Error> {
+        if window == 0 || step == 0 {
+            return Err(Error::Generic(format!(
+                "window ({}) and step ({}) must be positive",
+                window, step
+            )));
+        }
+        Ok(windowed_mean(
+            &self.get_minor_allele_frequencies(None)?,
+            window,
+            step,
+        ))
+    }
+
+    /// Computes the symmetric matrix of the squared correlations r^2 between
+    /// the genotypes of every pair of SNPs in the `set`, where the i-th row
+    /// and column correspond to the i-th SNP in the `set`. The missing
+    /// genotypes are decoded according to the missing strategy. The row and
+    /// the column of a monomorphic SNP are NaN.
+    ///
+    /// The standardized genotypes of the `set` are decoded once if they take
+    /// up at most `DEFAULT_CHUNK_MEMORY_BUDGET_BYTES`. Otherwise, the SNPs
+    /// are read in blocks of `LD_MATRIX_BLOCK_NUM_SNPS`, and each block is
+    /// read again for every block before it, i.e. about `n^2 / 2` reads of
+    /// the blocks in total for `n` blocks, which can be slow for a large
+    /// `set`.
+    pub fn ld_matrix(
+        &self,
+        set: &OrderedIntegerSet<usize>,
+    ) -> Result<Array<f32, Ix2>, Error> {
+        self.ld_matrix_in_blocks(
+            set,
+            LD_MATRIX_BLOCK_NUM_SNPS,
+            DEFAULT_CHUNK_MEMORY_BUDGET_BYTES,
+        )
+    }
+
+    /// Computes the `ld_matrix` in blocks of `block_num_snps` x
+    /// `block_num_snps`. The bands of `block_num_snps` rows are filled in
+    /// parallel on and above the diagonal, where each block is computed from
+    /// its two column blocks of standardized genotypes and written directly
+    /// into the result, and the lower triangle is then mirrored in place.
+    ///
+    /// If the standardized genotypes of the `set` take up at most
+    /// `memory_budget_bytes`, all the column blocks are decoded once up front
+    /// and shared by the bands. Otherwise, each band decodes its own column
+    /// blocks, so that the memory used is that of the result plus two column
+    /// blocks per thread, at the cost of decoding a column block once for
+    /// every band on or above it.
+    fn ld_matrix_in_blocks(
+        &self,
+        set: &OrderedIntegerSet<usize>,
+        block_num_snps: usize,
+        memory_budget_bytes: usize,
+    ) -> Result<Array<f32, Ix2>, Error> {
+        let num_snps = set.size();
+        let block_range =
+            |a: usize| set.slice(a..min(a + block_num_snps, num_snps));
+        let decode_once =
+            self.num_people * num_snps * std::mem::size_of::<f32>()
+                <= memory_budget_bytes;
+        let blocks: Vec<Array<f32, Ix2>> = if decode_once {
+            (0..num_snps)
+                .step_by(block_num_snps)
+                .collect::<Vec<usize>>()
+                .into_par_iter()
+                .map(|a| self.standardized_genotypes(block_range(a)))
+                .collect::<Result<_, Error>>()?
         } else {
-            iter.seek_to_snp(0).unwrap();
+            Vec::new()
+        };
+        let get_block = |a: usize| -> Result<Cow<Array<f32, Ix2>>, Error> {
+            if decode_once {
+                Ok(Cow::Borrowed(&blocks[a / block_num_snps]))
+            } else {
+                Ok(Cow::Owned(self.standardized_genotypes(block_range(a))?))
+            }
+        };
+
+        let mut r2 = Array::zeros((num_snps, num_snps));
+        let bands: Vec<ArrayViewMut<f32, Ix2>> =
+            r2.axis_chunks_iter_mut(Axis(0), block_num_snps).collect();
+        bands.into_par_iter().enumerate().try_for_each(
+            |(k, mut band)| -> Result<(), Error> {
+                let a = k * block_num_snps;
+                let a_block = get_block(a)?;
+                for b in (a..num_snps).step_by(block_num_snps) {
+                    let b_end = min(b + block_num_snps, num_snps);
+                    let mut dest = band.slice_mut(s![.., b..b_end]);
+                    if b == a {
+                        general_mat_mul(
+                            1.,
+                            &a_block.t(),
+                            &*a_block,
+                            0.,
+                            &mut dest,
+                        );
+                    } else {
+                        general_mat_mul(
+                            1.,
+                            &a_block.t(),
+                            &*get_block(b)?,
+                            0.,
+                            &mut dest,
+                        );
+                    }
+                    dest.mapv_inplace(|r| r * r);
+                }
+                Ok(())
+            },
+        )?;
+        for i in 1..num_snps {
+            for j in 0..i {
+                r2[[i, j]] = r2[[j, i]];
+            }
         }
-        iter
+        Ok(r2)
     }
 
-    fn get_buf_list(
-        bed_path_list: &[String],
-    ) -> Result<Vec<BufReader<File>>, Error> {
-        Ok(bed_path_list
-            .iter()
-            .map(|p| Ok(get_buf(p)?))
-            .collect::<Result<Vec<BufReader<File>>, Error>>()?)
+    /// Reads the genotypes of the SNPs in the `range` with each SNP
+    /// standardized to have zero mean and unit norm, so that the correlation
+    /// between two SNPs is the dot product. A monomorphic SNP is NaN.
+    fn standardized_genotypes(
+        &self,
+        range: OrderedIntegerSet<usize>,
+    ) -> Result<Array<f32, Ix2>, Error> {
+        let mut geno = self.get_genotype_matrix(Some(range), None)?;
+        for mut col in geno.gencolumns_mut() {
+            let mean = sum_f32(col.iter()) / col.len() as f32;
+            col.mapv_inplace(|x| x - mean);
+            let norm = col.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if norm == 0. {
+                col.fill(f32::NAN);
+            } else {
+                col.mapv_inplace(|x| x / norm);
+            }
+        }
+        Ok(geno)
     }
 
-    fn seek_to_snp(&mut self, snp_index: usize) -> Result<(), Error> {
-        if !self.range.contains(&snp_index) {
+    /// Computes the `num_snps x num_snps` covariance matrix `X^T X / n`
+    /// between the SNPs in the `range`, which defaults to all the SNPs, where
+    /// `X` is the `n x num_snps` genotype matrix of the `n` people with each
+    /// SNP centered at its mean. If `standardize` is true, each SNP is also
+    /// scaled to unit variance, so that the result is the correlation matrix,
+    /// and the row and the column of a SNP without variance are NaN. The
+    /// missing genotypes are mean imputed regardless of the missing strategy.
+    ///
+    /// The products are accumulated over blocks of people read from the bed
+    /// files as in `transposed_chunk_iter`, so the memory used is that of the
+    /// result plus a single block. As the result alone takes
+    /// `4 * num_snps^2` bytes, an error is returned if the `range` has more
+    /// than `MAX_SNP_COVARIANCE_NUM_SNPS` SNPs.
+    pub fn snp_covariance(
+        &self,
+        range: Option<OrderedIntegerSet<usize>>,
+        standardize: bool,
+    ) -> Result<Array<f32, Ix2>, Error> {
+        self.snp_covariance_in_blocks(
+            range,
+            standardize,
+            SNP_COVARIANCE_BLOCK_NUM_PEOPLE,
+        )
+    }
+
+    /// Computes the `snp_covariance` over blocks of `block_num_people`
+    /// people.
+    fn snp_covariance_in_blocks(
+        &self,
+        range: Option<OrderedIntegerSet<usize>>,
+        standardize: bool,
+        block_num_people: usize,
+    ) -> Result<Array<f32, Ix2>, Error> {
+        let total_num_snps = self.total_num_snps();
+        let range = match range {
+            Some(range) => range,
+            None if total_num_snps == 0 => OrderedIntegerSet::new(),
+            None => OrderedIntegerSet::from_slice(&[[0, total_num_snps - 1]]),
+        };
+        if let Some(last) = range.last() {
+            if last >= total_num_snps {
+                return Err(Error::SnpIndexOutOfRange {
+                    index: last,
+                    total: total_num_snps,
+                });
+            }
+        }
+        let num_snps = range.size();
+        if num_snps > MAX_SNP_COVARIANCE_NUM_SNPS {
             return Err(Error::Generic(format!(
-                "SNP index {} is not in the iterator range",
-                snp_index
+                "the SNP covariance of {} SNPs exceeds the maximum of {} SNPs",
+                num_snps, MAX_SNP_COVARIANCE_NUM_SNPS
+            )));
+        }
+        let num_people = self.num_people;
+        // only the SNPs in the range are counted unless all are cached
+        let counts: Vec<GenotypeCounts> = match &self.snp_counts {
+            Some(counts) => range.to_iter().map(|i| counts[i]).collect(),
+            None => self.map_snp_bytes_in_range(
+                &range,
+                self.get_default_chunk_size(),
+                |snp_bytes, _| {
+                    GenotypeCounts::from_snp_bytes(snp_bytes, num_people)
+                },
+            )?,
+        };
+        let snp_types = self.snp_types();
+        let mut snp_values = Vec::with_capacity(num_snps);
+        for (i, c) in range.to_iter().zip(counts.iter()) {
+            let values = transposed_chunk_iter::snp_code_values(
+                c,
+                num_people,
+                snp_types[i],
+                MissingStrategy::MeanImpute,
+                self.dominance_orientation,
+            );
+            snp_values.push(center_code_values(values, c, standardize));
+        }
+        let mut iter = PlinkTransposedChunkIter::new(
+            &self.bed_path_list,
+            self.file_snp_indices(&range),
+            num_people,
+            block_num_people,
+            snp_values,
+        )?;
+        let mut cov = Array::zeros((num_snps, num_snps));
+        for block in &mut iter {
+            general_mat_mul(1., &block.t(), &block, 1., &mut cov);
+        }
+        if let Some(error) = iter.take_error() {
+            return Err(error);
+        }
+        let n = num_people as f32;
+        cov.mapv_inplace(|x| x / n);
+        Ok(cov)
+    }
+
+    /// Prunes the SNPs in linkage disequilibrium (LD) as in PLINK
+    /// `--indep-pairwise`. The windows of `window_snps` consecutive SNPs
+    /// start every `step` SNPs. Within each window, for every pair of the
+    /// remaining SNPs whose squared correlation r^2 between the genotypes
+    /// exceeds the `r2_threshold`, the second SNP of the pair is removed.
+    /// Returns the indices of the retained SNPs, which can be used as the
+    /// range of `col_chunk_iter`.
+    pub fn ld_prune(
+        &self,
+        window_snps: usize,
+        step: usize,
+        r2_threshold: f32,
+    ) -> Result<OrderedIntegerSet<usize>, Error> {
+        if window_snps == 0 || step == 0 {
+            return Err(Error::Generic(format!(
+                "window_snps ({}) and step ({}) must be positive",
+                window_snps, step
             )));
         }
+        let num_snps = self.total_num_snps();
+        let mut is_removed = vec![false; num_snps];
+
+        // the centered genotypes and their sum of squares of the SNPs from
+        // the index buffer_start onwards
+        let mut buffer: VecDeque<(Vec<f32>, f32)> = VecDeque::new();
+        let mut buffer_start = 0;
+        let mut chunk_iter =
+            self.col_chunk_iter(self.get_default_chunk_size(), None);
+
+        let mut start = 0;
+        while start < num_snps {
+            let end = min(start + window_snps, num_snps);
+            while buffer_start + buffer.len() < end {
+                let chunk = match chunk_iter.next() {
+                    Some(chunk) => chunk,
+                    None => {
+                        return Err(match chunk_iter.take_error() {
+                            Some(error) => error,
+                            None => Error::Generic(
+                                "the SNPs ended before the window".to_string(),
+                            ),
+                        })
+                    }
+                };
+                for col in chunk.gencolumns() {
+                    let mean = sum_f32(col.iter()) / col.len() as f32;
+                    let centered: Vec<f32> =
+                        col.iter().map(|x| x - mean).collect();
+                    let sum_squares = centered.iter().map(|x| x * x).sum();
+                    buffer.push_back((centered, sum_squares));
+                }
+            }
+            while buffer_start < start {
+                buffer.pop_front();
+                buffer_start += 1;
+            }
+            for i in start..end {
+                if is_removed[i] {
+                    continue;
+                }
+                let (x, x_sum_squares) = &buffer[i - buffer_start];
+                for j in i + 1..end {
+                    if is_removed[j] {
+                        continue;
+                    }
+                    let (y, y_sum_squares) = &buffer[j - buffer_start];
+                    // monomorphic SNPs are not correlated with any SNP
+                    if *x_sum_squares == 0. || *y_sum_squares == 0. {
+                        continue;
+                    }
+                    let cov =
+                        x.iter().zip(y.iter()).map(|(a, b)| a * b).sum::<f32>();
+                    if cov * cov / (x_sum_squares * y_sum_squares)
+                        > r2_threshold
+                    {
+                        is_removed[j] = true;
+                    }
+                }
+            }
+            if end == num_snps {
+                break;
+            }
+            start += step;
+        }
+        Ok(OrderedIntegerSet::from_slice(
+            &is_removed
+                .iter()
+                .enumerate()
+                .filter(|(_, removed)| !**removed)
+                .map(|(i, _)| [i, i])
+                .collect::<Vec<[usize; 2]>>(),
+        ))
+    }
+
+    /// Returns for each person the fraction of the non-missing SNPs that are
+    /// heterozygous, computed in a single pass over the bed files. The
+    /// fraction is NaN for a person whose genotypes are all missing.
+    pub fn sample_heterozygosity(&self) -> Result<Vec<f32>, Error> {
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
+        let mut het_counts = vec![0usize; self.num_people];
+        let mut non_missing_counts = vec![0usize; self.num_people];
+        let mut snp_bytes = vec![0u8; num_bytes_per_snp];
+        for (path, (num_snps, _)) in
+            self.bed_path_list.iter().zip(self.file_num_snps.iter())
+        {
+            let mut buf = get_file_buf(path)?;
+            buf.seek(SeekFrom::Start(NUM_MAGIC_BYTES as u64))?;
+            for _ in 0..*num_snps {
+                buf.read_exact(&mut snp_bytes)?;
+                // decoding the bits of the people excludes the padding bits
+                let snp_bits = decode_snp_bytes_with(
+                    &snp_bytes,
+                    self.num_people,
+                    |bits| bits,
+                );
+                for (j, bits) in snp_bits.into_iter().enumerate() {
+                    if bits != MISSING_GENOTYPE_BITS {
+                        non_missing_counts[j] += 1;
+                        if bits == HETEROZYGOUS_GENOTYPE_BITS {
+                            het_counts[j] += 1;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(het_counts
+            .into_iter()
+            .zip(non_missing_counts)
+            .map(|(het_count, non_missing_count)| {
+                het_count as f32 / non_missing_count as f32
+            })
+            .collect())
+    }
+
+    /// Computes for each person the method-of-moments inbreeding coefficient
+    /// F as in PLINK `--het`, i.e. `(O - E) / (N - E)`, where `N` is the
+    /// number of SNPs at which the genotype of the person is not missing, `O`
+    /// the number of those SNPs at which the person is homozygous, and `E`
+    /// the expected number of homozygous SNPs `sum_i 1 - 2p_i(1 - p_i)` over
+    /// those SNPs, where `p_i` is the allele frequency of SNP `i` among the
+    /// non-missing genotypes. F is NaN if `N` equals `E`, e.g. if all the
+    /// genotypes of the person are missing.
+    ///
+    /// The frequencies are computed in a first pass over the bed files,
+    /// unless cached by `precompute_snp_stats`, and the homozygous counts are
+    /// accumulated in a second pass.
+    pub fn inbreeding_coefficients(&self) -> Result<Vec<f32>, Error> {
+        let expected_hom: Vec<f64> = self
+            .genotype_counts(None)?
+            .iter()
+            .map(|c| {
+                let num_alleles = 2 * c.num_non_missing();
+                let p = (2 * c.num_hom_first + c.num_het) as f64
+                    / num_alleles as f64;
+                1. - 2. * p * (1. - p)
+            })
+            .collect();
         let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
-        match self.file_snp_indexer.get_file_snp_index(snp_index) {
-            Some((file_index, snp_index_within_file, _snp_type)) => {
-                // skip the first NUM_MAGIC_BYTES magic bytes
-                self.buf[file_index].seek(SeekFrom::Start(
-                    NUM_MAGIC_BYTES as u64
-                        + (num_bytes_per_snp * snp_index_within_file) as u64,
-                ))?;
-                Ok(())
+        let mut observed = vec![0f64; self.num_people];
+        let mut expected = vec![0f64; self.num_people];
+        let mut num_non_missing = vec![0usize; self.num_people];
+        let mut snp_bytes = vec![0u8; num_bytes_per_snp];
+        let mut snp_index = 0;
+        for (path, (num_snps, _)) in
+            self.bed_path_list.iter().zip(self.file_num_snps.iter())
+        {
+            let mut buf = get_file_buf(path)?;
+            buf.seek(SeekFrom::Start(NUM_MAGIC_BYTES as u64))?;
+            for _ in 0..*num_snps {
+                buf.read_exact(&mut snp_bytes)?;
+                let e = expected_hom[snp_index];
+                snp_index += 1;
+                let snp_bits = decode_snp_bytes_with(
+                    &snp_bytes,
+                    self.num_people,
+                    |bits| bits,
+                );
+                for (j, bits) in snp_bits.into_iter().enumerate() {
+                    if bits == MISSING_GENOTYPE_BITS {
+                        continue;
+                    }
+                    num_non_missing[j] += 1;
+                    expected[j] += e;
+                    if bits != HETEROZYGOUS_GENOTYPE_BITS {
+                        observed[j] += 1.;
+                    }
+                }
             }
-            None => Err(Error::Generic(format!(
-                "failed to get file snp index for snp_index {}",
-                snp_index
-            ))),
+        }
+        Ok(observed
+            .into_iter()
+            .zip(expected)
+            .zip(num_non_missing)
+            .map(|((o, e), n)| ((o - e) / (n as f64 - e)) as f32)
+            .collect())
+    }
+
+    /// Computes the identity-by-state (IBS) of the people `person_a` and
+    /// `person_b` over the SNPs in `range`, or all the SNPs if `None`, i.e.
+    /// the average of `(2 - |g_a - g_b|) / 2` over the SNPs at which neither
+    /// genotype is missing, where `g_a` and `g_b` are the genotypes of the two
+    /// people. Only the bits of the two people are decoded for each SNP.
+    /// Returns NaN if there is no such SNP.
+    pub fn pairwise_ibs(
+        &self,
+        person_a: usize,
+        person_b: usize,
+        range: Option<OrderedIntegerSet<usize>>,
+    ) -> Result<f32, Error> {
+        for &j in [person_a, person_b].iter() {
+            if j >= self.num_people {
+                return Err(Error::Generic(format!(
+                    "person index out of range: {} >= {}",
+                    j, self.num_people
+                )));
+            }
+        }
+        let range = range.unwrap_or_else(|| {
+            OrderedIntegerSet::from_slice(&[[0, self.total_num_snps() - 1]])
+        });
+        let mut chunk_iter = self.col_chunk_iter(1, Some(range.clone()));
+        if let Some(error) = chunk_iter.take_error() {
+            return Err(error);
+        }
+        let get_bits = |snp_bytes: &[u8], j: usize| {
+            (snp_bytes[j / NUM_PEOPLE_PER_BYTE]
+                >> (2 * (j % NUM_PEOPLE_PER_BYTE)))
+                & 0b11
+        };
+        let mut snp_bytes =
+            vec![0u8; PlinkBed::num_bytes_per_snp(self.num_people)];
+        let mut ibs_sum = 0u64;
+        let mut num_non_missing = 0u64;
+        for i in range.to_iter() {
+            chunk_iter.read_snp_bytes(i, &mut snp_bytes)?;
+            let bits_a = get_bits(&snp_bytes, person_a);
+            let bits_b = get_bits(&snp_bytes, person_b);
+            if bits_a == MISSING_GENOTYPE_BITS
+                || bits_b == MISSING_GENOTYPE_BITS
+            {
+                continue;
+            }
+            let g_a = lowest_two_bits_to_geno(bits_a) as i64;
+            let g_b = lowest_two_bits_to_geno(bits_b) as i64;
+            ibs_sum += (2 - (g_a - g_b).abs()) as u64;
+            num_non_missing += 1;
+        }
+        Ok((ibs_sum as f64 / (2 * num_non_missing) as f64) as f32)
+    }
+
+    /// Computes the `num_people x num_people` genomic relationship matrix
+    /// (GRM) over all the SNPs as in GCTA. Let `x_ij` be the genotype of
+    /// person `j` at SNP `i`, `p_i` the frequency `sum_j x_ij / (2N)`, where
+    /// `N` is the number of people, `v_i = 2p_i(1 - p_i)` and `M` the number
+    /// of polymorphic SNPs, then
+    ///
+    /// `A_jk = (1 / M) * sum_i (x_ij - 2p_i)(x_ik - 2p_i) / v_i`
+    ///
+    /// If `grm_adjust_diagonal` is true, the diagonal is replaced by the GCTA
+    /// `--make-grm` estimate corrected for the sampling noise:
+    ///
+    /// `A_jj = 1 + (1 / M) * sum_i (x_ij^2 - (1 + 2p_i)x_ij + 2p_i^2) / v_i`
+    ///
+    /// Monomorphic SNPs, i.e. SNPs with `p_i` equal to 0 or 1, are excluded.
+    ///
+    /// A warning is logged if the SNPs have mixed types, see
+    /// `has_mixed_snp_types`. The SNPs are processed in parallel chunks of
+    /// `chunk_size` SNPs, 50 by default, and an error is returned if
+    /// `chunk_size` is `Some(0)`.
+    pub fn compute_grm(
+        &self,
+        chunk_size: Option<usize>,
+        grm_adjust_diagonal: bool,
+    ) -> Result<Array<f32, Ix2>, Error> {
+        self.compute_grm_with_progress(
+            chunk_size,
+            grm_adjust_diagonal,
+            &|_, _| {},
+        )
+    }
+
+    /// Same as `compute_grm`, but `progress` is called with the number of
+    /// SNPs processed so far and the total number of SNPs, at most
+    /// `MAX_PROGRESS_REPORTS` times. As the chunks are processed in parallel,
+    /// the calls can come from different threads, and the number of SNPs
+    /// processed in a call can be smaller than that in an earlier call.
+    pub fn compute_grm_with_progress(
+        &self,
+        chunk_size: Option<usize>,
+        grm_adjust_diagonal: bool,
+        progress: &(dyn Fn(usize, usize) + Sync),
+    ) -> Result<Array<f32, Ix2>, Error> {
+        let chunk_size = chunk_size.unwrap_or(50);
+        if chunk_size == 0 {
+            return Err(Error::Generic(
+                "chunk_size must be positive".to_string(),
+            ));
+        }
+        if self.has_mixed_snp_types() {
+            warn!(
+                "computing the GRM over SNPs of mixed additive and dominance \
+                types"
+            );
+        }
+        let num_people = self.num_people;
+        let num_alleles = (num_people * 2) as f32;
+        let chunk_iter = self.col_chunk_iter(chunk_size, None);
+        let error_handle = chunk_iter.get_error_handle();
+        let progress = ProgressReporter::new(progress, self.total_num_snps());
+        let (mut grm, adjusted_diag, num_polymorphic_snps) = chunk_iter
+            .into_par_iter()
+            .map(|mut snps| {
+                let mut adjusted_diag = Array::<f32, _>::zeros(num_people);
+                let mut num_polymorphic_snps = 0usize;
+                for mut col in snps.gencolumns_mut() {
+                    let p = sum_f32(col.iter()) / num_alleles;
+                    if p <= 0. || p >= 1. {
+                        col.fill(0.);
+                        continue;
+                    }
+                    num_polymorphic_snps += 1;
+                    let two_p = 2. * p;
+                    let var = two_p * (1. - p);
+                    for (j, x) in col.iter().enumerate() {
+                        adjusted_diag[j] +=
+                            (x * x - (1. + two_p) * x + two_p * p) / var;
+                    }
+                    let std = var.sqrt();
+                    col.mapv_inplace(|x| (x - two_p) / std);
+                }
+                let snps_grm = snps.dot(&snps.t());
+                progress.add(snps.dim().1);
+                (snps_grm, adjusted_diag, num_polymorphic_snps)
+            })
+            .reduce(
+                || {
+                    (
+                        Array::zeros((num_people, num_people)),
+                        Array::zeros(num_people),
+                        0,
+                    )
+                },
+                |(mut grm, mut diag, n), (grm_2, diag_2, n_2)| {
+                    grm += &grm_2;
+                    diag += &diag_2;
+                    (grm, diag, n + n_2)
+                },
+            );
+        if let Some(error) = error_handle.lock().unwrap().take() {
+            return Err(error);
+        }
+        if num_polymorphic_snps == 0 {
+            return Err(Error::Generic(
+                "cannot compute the GRM without any polymorphic SNP"
+                    .to_string(),
+            ));
+        }
+        let m = num_polymorphic_snps as f32;
+        grm.mapv_inplace(|x| x / m);
+        if grm_adjust_diagonal {
+            grm.diag_mut().assign(&adjusted_diag.mapv(|x| 1. + x / m));
+        }
+        Ok(grm)
+    }
+
+    /// save the transpose of the BED file into `out_path`, which should have an
+    /// extension of .bedt wherein the n-th sequence of bytes corresponds to
+    /// the SNPs for the n-th person larger values of `snp_byte_chunk_size`
+    /// lead to faster performance, at the cost of higher memory requirement
+    pub fn create_bed_t(
+        &mut self,
+        file_index: usize,
+        out_path: &str,
+        snp_byte_chunk_size: usize,
+    ) -> Result<(), Error> {
+        self.create_bed_t_with_progress(
+            file_index,
+            out_path,
+            snp_byte_chunk_size,
+            &|_, _| {},
+        )
+    }
+
+    /// Same as `create_bed_t`, but `progress` is called with the number of
+    /// people written so far and the total number of people, at most
+    /// `MAX_PROGRESS_REPORTS` times.
+    pub fn create_bed_t_with_progress(
+        &mut self,
+        file_index: usize,
+        out_path: &str,
+        snp_byte_chunk_size: usize,
+        progress: &(dyn Fn(usize, usize) + Sync),
+    ) -> Result<(), Error> {
+        let total_num_snps = self.total_num_snps();
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
+        match self.bed_path_list.get(file_index) {
+            Some(p) => {
+                let mut bed_buf = get_file_buf(p)?;
+                let mut buf_writer = BufWriter::new(
+                    OpenOptions::new()
+                        .create(true)
+                        .truncate(true)
+                        .write(true)
+                        .open(out_path)?,
+                );
+                let num_bytes_per_person = usize_div_ceil(total_num_snps, 4);
+
+                let people_stride = snp_byte_chunk_size * 4;
+                let mut snp_bytes = vec![0u8; snp_byte_chunk_size];
+                let progress = ProgressReporter::new(progress, self.num_people);
+
+                // write people_stride people at a time
+                for j in (0..self.num_people).step_by(people_stride) {
+                    let mut people_buf =
+                        vec![vec![0u8; num_bytes_per_person]; people_stride];
+                    if self.num_people - j < people_stride {
+                        let remaining_people = self.num_people % people_stride;
+                        snp_bytes =
+                            vec![0u8; usize_div_ceil(remaining_people, 4)];
+                    }
+                    let relative_seek_offset =
+                        (num_bytes_per_snp - snp_bytes.len()) as i64;
+                    // read 4 SNPs to the buffers at a time
+                    PlinkBed::seek_to_byte_containing_snp_i_person_j(
+                        &mut bed_buf,
+                        0,
+                        j,
+                        num_bytes_per_snp,
+                    )?;
+                    for (snp_byte_index, k) in
+                        (0..total_num_snps).step_by(4).enumerate()
+                    {
+                        for (snp_offset, _) in
+                            (k..min(k + 4, total_num_snps)).enumerate()
+                        {
+                            bed_buf.read_exact(&mut snp_bytes)?;
+                            for w in 0..snp_bytes.len() {
+                                people_buf[w][snp_byte_index] |=
+                                    (snp_bytes[w] & 0b11) << (snp_offset << 1);
+                                people_buf[w + 1][snp_byte_index] |=
+                                    ((snp_bytes[w] >> 2) & 0b11)
+                                        << (snp_offset << 1);
+                                people_buf[w + 2][snp_byte_index] |=
+                                    ((snp_bytes[w] >> 4) & 0b11)
+                                        << (snp_offset << 1);
+                                people_buf[w + 3][snp_byte_index] |=
+                                    ((snp_bytes[w] >> 6) & 0b11)
+                                        << (snp_offset << 1);
+                            }
+                            bed_buf.seek_relative(relative_seek_offset)?;
+                        }
+                    }
+                    for (p, buf) in people_buf.iter().enumerate() {
+                        if j + p < self.num_people {
+                            buf_writer.write_all(buf.as_slice())?;
+                        }
+                    }
+                    progress.add(min(people_stride, self.num_people - j));
+                }
+                Ok(())
+            }
+            None => Err(Error::FileIndexOutOfRange {
+                index: file_index,
+                num_files: self.bed_path_list.len(),
+            }),
+        }
+    }
+
+    pub fn create_dominance_geno_bed(
+        &self,
+        file_index: usize,
+        out_path: &str,
+    ) -> Result<(), Error> {
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
+        let mut writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(out_path)?,
+        );
+        writer.write_all(&PlinkBed::get_magic_bytes())?;
+        let end_byte_index = cast_byte_offset(snp_byte_offset(
+            self.total_num_snps(),
+            num_bytes_per_snp,
+            0,
+        )?)?;
+        for bytes in self.byte_chunk_iter(
+            file_index,
+            NUM_MAGIC_BYTES,
+            end_byte_index,
+            num_bytes_per_snp,
+        )? {
+            let out_bytes = PlinkSnps::from_geno(
+                PlinkSnps::new(bytes, self.num_people)
+                    .into_iter()
+                    .map(|s| match s {
+                        2 => 1,
+                        s => s,
+                    })
+                    .collect(),
+            )
+            .into_bytes();
+            writer.write_all(&out_bytes)?;
+        }
+        Ok(())
+    }
+
+    // the first person is the lowest two bits
+    // 00 -> 2 homozygous for the first allele in the .bim file (usually the
+    // minor allele) 01 -> 0 missing genotype
+    // 10 -> 1 heterozygous
+    // 11 -> 0 homozygous for the second allele in the .bim file (usually the
+    // major allele)
+    //
+    // If the number of people is not a multiple of 4, the unused high bits of
+    // the last byte of each SNP are set to 0 as in the PLINK specification,
+    // i.e. with `PaddingBits::Zero`.
+    pub fn create_bed(
+        arr: &Array<u8, Ix2>,
+        out_path: &str,
+    ) -> Result<(), Error> {
+        PlinkBed::create_bed_from_view(arr.view(), out_path)
+    }
+
+    /// Same as `create_bed`, but the unused high bits of the last byte of each
+    /// SNP are filled according to `padding`. The padding bits are ignored
+    /// when reading the SNPs either way.
+    pub fn create_bed_with_padding(
+        arr: &Array<u8, Ix2>,
+        out_path: &str,
+        padding: PaddingBits,
+    ) -> Result<(), Error> {
+        PlinkBed::create_bed_from_view_with_padding(
+            arr.view(),
+            out_path,
+            padding,
+        )
+    }
+
+    /// Hard-calls the `num_people x num_snps` matrix of imputed `dosages` and
+    /// writes the genotypes to the bed file `out_path` as in `create_bed`. A
+    /// dosage below `threshold_het_low` is called 0, a dosage above
+    /// `threshold_het_high` is called 2, and any other dosage, including the
+    /// thresholds themselves, is called 1. NaN dosages are written as missing.
+    pub fn create_bed_from_dosages(
+        dosages: &Array<f32, Ix2>,
+        threshold_het_low: f32,
+        threshold_het_high: f32,
+        out_path: &str,
+    ) -> Result<(), Error> {
+        if threshold_het_low.is_nan()
+            || threshold_het_high.is_nan()
+            || threshold_het_low > threshold_het_high
+        {
+            return Err(Error::Generic(format!(
+                "threshold_het_low ({}) has to be at most threshold_het_high \
+                ({})",
+                threshold_het_low, threshold_het_high
+            )));
+        }
+        let dosage_to_bits = |dosage: f32| {
+            if dosage.is_nan() {
+                MISSING_GENOTYPE_BITS
+            } else if dosage < threshold_het_low {
+                geno_to_lowest_two_bits(0)
+            } else if dosage > threshold_het_high {
+                geno_to_lowest_two_bits(2)
+            } else {
+                geno_to_lowest_two_bits(1)
+            }
+        };
+        let num_people = dosages.dim().0;
+        let mut buf_writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(out_path)?,
+        );
+        buf_writer.write_all(&MAGIC_BYTES)?;
+        let mut snp_bytes = vec![0u8; PlinkBed::num_bytes_per_snp(num_people)];
+        for col in dosages.gencolumns() {
+            pack_snp_bytes(col.iter(), &mut snp_bytes, 0b00, |&dosage| {
+                Ok(dosage_to_bits(dosage))
+            })?;
+            buf_writer.write_all(&snp_bytes)?;
+        }
+        buf_writer.flush()?;
+        Ok(())
+    }
+
+    /// Splits the columns of `arr` into shards of `snps_per_shard` SNPs, the
+    /// last of which can be smaller, and writes the i-th shard to
+    /// `{out_prefix}.{i}.bed`. Returns the paths of the bed files in order,
+    /// which together contain all the SNPs.
+    pub fn create_sharded_bed(
+        arr: &Array<u8, Ix2>,
+        out_prefix: &str,
+        snps_per_shard: usize,
+    ) -> Result<Vec<String>, Error> {
+        if snps_per_shard == 0 {
+            return Err(Error::Generic(
+                "snps_per_shard has to be positive".to_string(),
+            ));
+        }
+        let num_snps = arr.dim().1;
+        let mut paths =
+            Vec::with_capacity(usize_div_ceil(num_snps, snps_per_shard));
+        for (i, start) in (0..num_snps).step_by(snps_per_shard).enumerate() {
+            let end = min(start + snps_per_shard, num_snps);
+            let path = format!("{}.{}.bed", out_prefix, i);
+            PlinkBed::create_bed_from_view(
+                arr.slice(s![.., start..end]),
+                &path,
+            )?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Concatenates the SNPs of the bed files in `inputs`, all of which have
+    /// the same `num_people` people, into the bed file `out_path` in order.
+    /// The SNP bytes are copied as is without decoding, so the bim files of
+    /// the inputs have to be concatenated in the same order to match.
+    ///
+    /// Every input is checked to have the magic bytes and a size consisting
+    /// of whole SNPs of `num_people` people before `out_path` is written.
+    pub fn concat_beds(
+        inputs: &[&str],
+        num_people: usize,
+        out_path: &str,
+    ) -> Result<(), Error> {
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(num_people);
+        if num_bytes_per_snp == 0 {
+            return Err(Error::Generic(
+                "cannot concatenate bed files with 0 people".to_string(),
+            ));
+        }
+        for &path in inputs.iter() {
+            let file_size = PlinkBed::verify_magic_bytes(path)?;
+            let num_snp_bytes = file_size - NUM_MAGIC_BYTES as u64;
+            if !num_snp_bytes.is_multiple_of(num_bytes_per_snp as u64) {
+                return Err(Error::BadFormat(format!(
+                    "The PLINK bed file {} has {} bytes after the magic \
+                    bytes, which is not a multiple of the {} bytes per SNP of \
+                    {} people",
+                    path, num_snp_bytes, num_bytes_per_snp, num_people
+                )));
+            }
+        }
+        let mut buf_writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(out_path)?,
+        );
+        buf_writer.write_all(&MAGIC_BYTES)?;
+        for &path in inputs.iter() {
+            let mut buf = get_file_buf(path)?;
+            buf.seek(SeekFrom::Start(NUM_MAGIC_BYTES as u64))?;
+            io::copy(&mut buf, &mut buf_writer)?;
+        }
+        buf_writer.flush()?;
+        Ok(())
+    }
+
+    /// Splits the SNPs by chromosome according to the `bim` records of all
+    /// the SNPs, and writes the bfile `{out_prefix}.{chrom}` for each
+    /// chromosome, consisting of the bed file with the SNP bytes copied
+    /// without decoding, the bim file of the records of the chromosome and a
+    /// copy of the fam file. The SNPs of a chromosome are written in their
+    /// original order even if they are not contiguous.
+    ///
+    /// Returns the prefixes of the bfiles in the order in which the
+    /// chromosomes first appear, which can be opened with `from_prefixes`.
+    pub fn split_by_chromosome(
+        &self,
+        bim: &[BimRecord],
+        out_prefix: &str,
+    ) -> Result<Vec<String>, Error> {
+        if bim.len() != self.total_num_snps() {
+            return Err(Error::Generic(format!(
+                "the number of bim records {} does not match the number of \
+                SNPs {}",
+                bim.len(),
+                self.total_num_snps()
+            )));
+        }
+        let mut chroms: Vec<&str> = Vec::new();
+        let mut chrom_snp_indices: Vec<Vec<usize>> = Vec::new();
+        for (i, record) in bim.iter().enumerate() {
+            match chroms.iter().position(|c| *c == record.chrom) {
+                Some(k) => chrom_snp_indices[k].push(i),
+                None => {
+                    chroms.push(&record.chrom);
+                    chrom_snp_indices.push(vec![i]);
+                }
+            }
+        }
+        let mut prefixes = Vec::with_capacity(chroms.len());
+        for (chrom, snp_indices) in chroms.iter().zip(chrom_snp_indices.iter())
+        {
+            let prefix = format!("{}.{}", out_prefix, chrom);
+            let (bed_path, bim_path, fam_path) = bfile_paths(&prefix)?;
+            self.write_snp_bytes(snp_indices, &bed_path)?;
+            Bim::new(snp_indices.iter().map(|&i| bim[i].clone()).collect())
+                .write(&bim_path)?;
+            std::fs::copy(&self.fam_path_list[0], &fam_path)?;
+            prefixes.push(prefix);
+        }
+        Ok(prefixes)
+    }
+
+    /// Writes the bed file `out_path` of the SNPs at the `snp_indices` in
+    /// order by copying their bytes.
+    fn write_snp_bytes(
+        &self,
+        snp_indices: &[usize],
+        out_path: &str,
+    ) -> Result<(), Error> {
+        let mut chunk_iter = self.col_chunk_iter(1, None);
+        if let Some(error) = chunk_iter.take_error() {
+            return Err(error);
+        }
+        let mut buf_writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(out_path)?,
+        );
+        buf_writer.write_all(&MAGIC_BYTES)?;
+        let mut snp_bytes =
+            vec![0u8; PlinkBed::num_bytes_per_snp(self.num_people)];
+        for &i in snp_indices.iter() {
+            chunk_iter.read_snp_bytes(i, &mut snp_bytes)?;
+            buf_writer.write_all(&snp_bytes)?;
+        }
+        buf_writer.flush()?;
+        Ok(())
+    }
+
+    fn create_bed_from_view(
+        arr: ArrayView<u8, Ix2>,
+        out_path: &str,
+    ) -> Result<(), Error> {
+        PlinkBed::create_bed_from_view_with_padding(
+            arr,
+            out_path,
+            PaddingBits::default(),
+        )
+    }
+
+    fn create_bed_from_view_with_padding(
+        arr: ArrayView<u8, Ix2>,
+        out_path: &str,
+        padding: PaddingBits,
+    ) -> Result<(), Error> {
+        let (num_people, _num_snps) = arr.dim();
+        let padding_bits = match padding {
+            PaddingBits::Zero => 0b00,
+            PaddingBits::Missing => MISSING_GENOTYPE_BITS,
+        };
+        let mut buf_writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(out_path)?,
+        );
+        buf_writer.write_all(&MAGIC_BYTES)?;
+        let mut snp_bytes = vec![0u8; PlinkBed::num_bytes_per_snp(num_people)];
+        for col in arr.gencolumns() {
+            pack_snp_bytes(
+                col.iter(),
+                &mut snp_bytes,
+                padding_bits,
+                |&geno| checked_geno_to_lowest_two_bits(geno),
+            )?;
+            buf_writer.write_all(&snp_bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Same as `create_bed`, but the i-th SNP written is the column
+    /// `col_perm[i]` of `arr`, and the j-th person within each SNP is the row
+    /// `row_perm[j]`. Nothing is written if either `row_perm` or `col_perm`
+    /// is not a permutation of the row or column indices respectively.
+    pub fn create_bed_permuted(
+        arr: &Array<u8, Ix2>,
+        row_perm: &[usize],
+        col_perm: &[usize],
+        out_path: &str,
+    ) -> Result<(), Error> {
+        let (num_people, num_snps) = arr.dim();
+        validate_permutation(row_perm, num_people, "row_perm")?;
+        validate_permutation(col_perm, num_snps, "col_perm")?;
+        let mut buf_writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(out_path)?,
+        );
+        buf_writer.write_all(&MAGIC_BYTES)?;
+        let mut snp_bytes = vec![0u8; PlinkBed::num_bytes_per_snp(num_people)];
+        for &j in col_perm.iter() {
+            pack_snp_bytes(
+                row_perm.iter().map(|&i| arr[[i, j]]),
+                &mut snp_bytes,
+                0b00,
+                checked_geno_to_lowest_two_bits,
+            )?;
+            buf_writer.write_all(&snp_bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Checks the magic bytes of the bed file and returns its size in bytes,
+    /// both of which are read from the same opened file.
+    fn verify_magic_bytes(bed_filepath: &str) -> Result<u64, Error> {
+        let (mut bed_buf, file_size) = get_buf_with_len(bed_filepath)?;
+
+        // check if PLINK bed file has the correct file signature
+        let mut magic_bytes = [0u8; 3];
+        if let Err(io_error) = bed_buf.read_exact(&mut magic_bytes) {
+            return Err(Error::IO {
+                why: format!(
+                    "Failed to read the first three bytes of {}: {}",
+                    bed_filepath, io_error
+                ),
+                io_error,
+            });
+        }
+        let expected_bytes = PlinkBed::get_magic_bytes();
+        if magic_bytes != expected_bytes {
+            return Err(Error::BadFormat(format!(
+                "The first three bytes of the PLINK bed file {} are supposed to be 0x{:x?}, but found 0x{:x?}",
+                bed_filepath, expected_bytes, magic_bytes
+            )));
+        }
+        Ok(file_size)
+    }
+
+    /// Checks that the `file_size` of the bed file is exactly that of the
+    /// magic bytes followed by `num_snps` SNPs of `num_people` people.
+    fn verify_bed_size(
+        bed_filepath: &str,
+        file_size: u64,
+        num_people: usize,
+        num_snps: usize,
+    ) -> Result<(), Error> {
+        let expected_size = snp_byte_offset(
+            num_snps,
+            PlinkBed::num_bytes_per_snp(num_people),
+            0,
+        )?;
+        if file_size != expected_size {
+            return Err(Error::BadFormat(format!(
+                "The PLINK bed file {} with {} SNPs and {} people is supposed \
+                to have {} bytes, but found {} bytes",
+                bed_filepath, num_snps, num_people, expected_size, file_size
+            )));
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn get_magic_bytes() -> [u8; 3] {
+        MAGIC_BYTES
+    }
+
+    #[inline]
+    pub fn get_num_magic_bytes() -> usize {
+        NUM_MAGIC_BYTES
+    }
+
+    #[inline]
+    fn num_bytes_per_snp(num_people: usize) -> usize {
+        usize_div_ceil(num_people, NUM_PEOPLE_PER_BYTE)
+    }
+
+    /// makes the BufReader point to the start of the byte containing the SNP i
+    /// individual j 0-indexing
+    fn seek_to_byte_containing_snp_i_person_j<B: Seek>(
+        buf: &mut B,
+        snp_i: usize,
+        person_j: usize,
+        num_bytes_per_snp: usize,
+    ) -> Result<(), Error> {
+        buf.seek(SeekFrom::Start(snp_byte_offset(
+            snp_i,
+            num_bytes_per_snp,
+            person_j / NUM_PEOPLE_PER_BYTE,
+        )?))?;
+        Ok(())
+    }
+}
+
+/// Returns the number of bytes spanned by `num_snps` consecutive SNPs of
+/// `num_bytes_per_snp` bytes each. The product is computed in `u64` so that it
+/// does not wrap around on 32-bit targets, and an error is returned if it
+/// overflows `u64`.
+pub(crate) fn num_snp_bytes(
+    num_snps: usize,
+    num_bytes_per_snp: usize,
+) -> Result<u64, Error> {
+    (num_snps as u64)
+        .checked_mul(num_bytes_per_snp as u64)
+        .ok_or_else(|| {
+            Error::Generic(format!(
+                "the number of bytes of {} SNPs of {} bytes each overflows u64",
+                num_snps, num_bytes_per_snp
+            ))
+        })
+}
+
+/// Returns the offset from the start of a bed file of the `byte_index`-th
+/// byte of the SNP `snp_index`, skipping the `NUM_MAGIC_BYTES` magic bytes,
+/// or an error if the offset overflows `u64`.
+pub(crate) fn snp_byte_offset(
+    snp_index: usize,
+    num_bytes_per_snp: usize,
+    byte_index: usize,
+) -> Result<u64, Error> {
+    num_snp_bytes(snp_index, num_bytes_per_snp)?
+        .checked_add((NUM_MAGIC_BYTES + byte_index) as u64)
+        .ok_or_else(|| {
+            Error::Generic(format!(
+                "the offset of byte {} of SNP {} with {} bytes per SNP \
+                overflows u64",
+                byte_index, snp_index, num_bytes_per_snp
+            ))
+        })
+}
+
+/// Converts the byte offset to the type expected by the reader, e.g. `usize`
+/// for the in-memory indices, which are 32 bits wide on 32-bit targets, or
+/// `i64` for the relative seeks.
+pub(crate) fn cast_byte_offset<T: TryFrom<u64>>(
+    offset: u64,
+) -> Result<T, Error> {
+    T::try_from(offset).map_err(|_| {
+        Error::Generic(format!(
+            "the byte offset {} does not fit in {}",
+            offset,
+            std::any::type_name::<T>()
+        ))
+    })
+}
+
+/// Returns `a / divisor` rounded up to the nearest integer.
+pub(crate) fn usize_div_ceil(a: usize, divisor: usize) -> usize {
+    a / divisor + (a % divisor != 0) as usize
+}
+
+pub fn lowest_two_bits_to_geno(byte: u8) -> u8 {
+    // 00 -> 2 homozygous for the first allele in the .bim file (usually the
+    // minor allele) 01 -> 0 missing genotype
+    // 10 -> 1 heterozygous
+    // 11 -> 0 homozygous for the second allele in the .bim file (usually the
+    // major allele)
+    debug_assert!(byte <= 0b11, "{:#b} has bits above the lowest two", byte);
+    let a = (byte & 0b10) >> 1;
+    let b = byte & 1;
+    (((a | b) ^ 1) << 1) | (a & (!b))
+}
+
+pub fn geno_to_lowest_two_bits(geno: u8) -> u8 {
+    // 00 -> 2 homozygous for the first allele in the .bim file (usually the
+    // minor allele) 01 -> 0 missing genotype
+    // 10 -> 1 heterozygous
+    // 11 -> 0 homozygous for the second allele in the .bim file (usually the
+    // major allele)
+    debug_assert!(geno <= 2, "invalid genotype {}", geno);
+    let not_a = ((geno & 0b10) >> 1) ^ 1;
+    let not_b = (geno & 1) ^ 1;
+    (not_a << 1) | (not_b & not_a)
+}
+
+/// Same as `geno_to_lowest_two_bits` but returns an error if `geno` is not 0,
+/// 1 or 2.
+pub fn checked_geno_to_lowest_two_bits(geno: u8) -> Result<u8, Error> {
+    if geno > 2 {
+        return Err(Error::BadFormat(format!(
+            "invalid genotype {}, expected 0, 1 or 2",
+            geno
+        )));
+    }
+    Ok(geno_to_lowest_two_bits(geno))
+}
+
+/// Decodes the `bytes` of a SNP, e.g. a chunk from `byte_chunk_iter` of
+/// `ceil(num_people / 4)` bytes, into the genotypes 0, 1 or 2 of the
+/// `num_people` people, where the padding bits in the last byte are skipped
+/// and the missing genotypes are decoded as 0.
+///
+/// With the `simd` feature, the bytes are decoded 16 at a time with vector
+/// instructions, producing the same genotypes as the scalar path.
+pub fn decode_snp_bytes(bytes: &[u8], num_people: usize) -> Vec<f32> {
+    let mut snp_vec = vec![0f32; num_people];
+    decode_snp_bytes_into(bytes, &mut snp_vec);
+    snp_vec
+}
+
+/// Same as `decode_snp_bytes`, but decodes the genotypes of the
+/// `snp_vec.len()` people into `snp_vec`.
+pub(crate) fn decode_snp_bytes_into(bytes: &[u8], snp_vec: &mut [f32]) {
+    #[cfg(feature = "simd")]
+    return simd_decode::decode_snp_bytes_into(bytes, snp_vec);
+
+    #[cfg(not(feature = "simd"))]
+    for (people, byte) in snp_vec.chunks_mut(NUM_PEOPLE_PER_BYTE).zip(bytes) {
+        for (k, x) in people.iter_mut().enumerate() {
+            *x = lowest_two_bits_to_geno((byte >> (k << 1)) & 0b11) as f32;
+        }
+    }
+}
+
+/// Same as `decode_snp_bytes` but decodes the genotypes into `u8`, where the
+/// missing genotypes are decoded as `missing_code`.
+pub fn decode_snp_bytes_u8(
+    bytes: &[u8],
+    num_people: usize,
+    missing_code: u8,
+) -> Vec<u8> {
+    decode_snp_bytes_with(bytes, num_people, |bits| {
+        if bits == MISSING_GENOTYPE_BITS {
+            missing_code
+        } else {
+            lowest_two_bits_to_geno(bits)
+        }
+    })
+}
+
+/// Packs the 2-bit codes of the people of a SNP into its `snp_bytes` as in a
+/// bed file, where `to_bits` maps the value of each person in `values` to its
+/// code, and the first person is in the lowest two bits of the first byte.
+/// The slots in `snp_bytes` after the last person are filled with the
+/// `padding_bits`.
+pub(crate) fn pack_snp_bytes<T, I, F>(
+    values: I,
+    snp_bytes: &mut [u8],
+    padding_bits: u8,
+    mut to_bits: F,
+) -> Result<(), Error>
+where
+    I: IntoIterator<Item = T>,
+    F: FnMut(T) -> Result<u8, Error>, {
+    for byte in snp_bytes.iter_mut() {
+        *byte = 0;
+    }
+    let mut num_people = 0;
+    for (j, value) in values.into_iter().enumerate() {
+        snp_bytes[j / NUM_PEOPLE_PER_BYTE] |=
+            to_bits(value)? << (2 * (j % NUM_PEOPLE_PER_BYTE));
+        num_people = j + 1;
+    }
+    for j in num_people..snp_bytes.len() * NUM_PEOPLE_PER_BYTE {
+        snp_bytes[j / NUM_PEOPLE_PER_BYTE] |=
+            padding_bits << (2 * (j % NUM_PEOPLE_PER_BYTE));
+    }
+    Ok(())
+}
+
+/// Decodes the genotypes of the `num_people` people in the `snp_bytes` of a
+/// SNP, where `decode` maps the two bits of each person to its genotype, and
+/// the first person is in the lowest two bits of the first byte.
+pub(crate) fn decode_snp_bytes_with<T, F: Fn(u8) -> T>(
+    snp_bytes: &[u8],
+    num_people: usize,
+    decode: F,
+) -> Vec<T> {
+    let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(num_people);
+    let mut snp_vec = Vec::with_capacity(num_people);
+    for byte in snp_bytes.iter().take(num_bytes_per_snp.saturating_sub(1)) {
+        snp_vec.push(decode(byte & 0b11));
+        snp_vec.push(decode((byte >> 2) & 0b11));
+        snp_vec.push(decode((byte >> 4) & 0b11));
+        snp_vec.push(decode((byte >> 6) & 0b11));
+    }
+    // last byte
+    if let Some(num_people_last_byte) = get_num_people_last_byte(num_people) {
+        let last_byte = snp_bytes[num_bytes_per_snp - 1];
+        for k in 0..num_people_last_byte {
+            snp_vec.push(decode((last_byte >> (k << 1)) & 0b11));
+        }
+    }
+    snp_vec
+}
+
+/// The SplitMix64 pseudorandom number generator, whose output is fully
+/// determined by the seed.
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 {
+            state: seed,
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniformly random number in [0, `bound`), rejecting the
+    /// draws that would bias the result towards the small numbers.
+    fn next_below(&mut self, bound: u64) -> u64 {
+        // 2^64 mod bound
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let x = self.next_u64();
+            if x >= threshold {
+                return x % bound;
+            }
+        }
+    }
+}
+
+/// The 64-bit FNV-1a hash function.
+struct Fnv1a {
+    hash: u64,
+}
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a {
+            hash: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes.iter() {
+            self.hash ^= b as u64;
+            self.hash = self.hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Reports the progress of a long-running operation, possibly from multiple
+/// threads, to a callback taking the amount of work done so far and the
+/// `total`. The callback is invoked when the work done crosses a multiple
+/// of `total / MAX_PROGRESS_REPORTS` or reaches the `total`, so that it is
+/// invoked at most `MAX_PROGRESS_REPORTS` times.
+struct ProgressReporter<'a> {
+    callback: &'a (dyn Fn(usize, usize) + Sync),
+    total: usize,
+    step: usize,
+    done: AtomicUsize,
+}
+
+impl<'a> ProgressReporter<'a> {
+    fn new(
+        callback: &'a (dyn Fn(usize, usize) + Sync),
+        total: usize,
+    ) -> ProgressReporter<'a> {
+        ProgressReporter {
+            callback,
+            total,
+            step: max(1, usize_div_ceil(total, MAX_PROGRESS_REPORTS)),
+            done: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records `amount` more work done.
+    fn add(&self, amount: usize) {
+        let prev = self.done.fetch_add(amount, Ordering::Relaxed);
+        let done = prev + amount;
+        if done == self.total || done / self.step > prev / self.step {
+            (self.callback)(done, self.total);
+        }
+    }
+}
+
+/// Computes the frequency of the first allele of a SNP from its genotype
+/// `counts` as in `get_allele_frequencies_in_range`, i.e. the sum of the
+/// genotypes of the `num_people` people, with the missing genotypes decoded
+/// according to the `missing_strategy`, divided by `2 * num_people`.
+fn allele_frequency_from_counts(
+    counts: &GenotypeCounts,
+    num_people: usize,
+    missing_strategy: MissingStrategy,
+) -> f32 {
+    let num_first_alleles = (2 * counts.num_hom_first + counts.num_het) as f32;
+    match missing_strategy {
+        MissingStrategy::Zero => num_first_alleles / (2 * num_people) as f32,
+        // the imputed genotypes have the mean of the non-missing genotypes
+        MissingStrategy::MeanImpute => {
+            let num_non_missing = counts.num_non_missing();
+            if num_non_missing == 0 {
+                0.
+            } else {
+                num_first_alleles / (2 * num_non_missing) as f32
+            }
+        }
+    }
+}
+
+/// Shifts the `values` of the four 2-bit codes of a SNP with the genotype
+/// `counts` so that the mean over all the people is zero, and scales them to
+/// unit variance if `standardize` is true, where a SNP without variance has
+/// NaN values.
+fn center_code_values(
+    values: [f32; 4],
+    counts: &GenotypeCounts,
+    standardize: bool,
+) -> [f32; 4] {
+    let mut code_counts = [0f32; 4];
+    code_counts[HOMOZYGOUS_FIRST_GENOTYPE_BITS as usize] =
+        counts.num_hom_first as f32;
+    code_counts[HETEROZYGOUS_GENOTYPE_BITS as usize] = counts.num_het as f32;
+    code_counts[HOMOZYGOUS_SECOND_GENOTYPE_BITS as usize] =
+        counts.num_hom_second as f32;
+    code_counts[MISSING_GENOTYPE_BITS as usize] = counts.num_missing as f32;
+    let n: f32 = code_counts.iter().sum();
+    let mean = code_counts
+        .iter()
+        .zip(values.iter())
+        .map(|(c, v)| c * v)
+        .sum::<f32>()
+        / n;
+    let mut centered = values.map(|v| v - mean);
+    if standardize {
+        // compares the values directly since the variance computed from the
+        // non-integer dominance values may not be exactly zero
+        let mut present_values = code_counts
+            .iter()
+            .zip(values.iter())
+            .filter(|(&c, _)| c > 0.)
+            .map(|(_, &v)| v);
+        let first = present_values.next();
+        if present_values.all(|v| Some(v) == first) {
+            return [f32::NAN; 4];
+        }
+        let std = (code_counts
+            .iter()
+            .zip(centered.iter())
+            .map(|(c, v)| c * v * v)
+            .sum::<f32>()
+            / n)
+            .sqrt();
+        centered = centered.map(|v| v / std);
+    }
+    centered
+}
+
+/// Computes the Pearson correlation between the pairs of `x` and `y` values
+/// where neither is NaN. Returns NaN if either has no variance.
+fn pearson_correlation_skipping_nan<'a, X, Y>(x: X, y: Y) -> f32
+where
+    X: Iterator<Item = &'a f32>,
+    Y: Iterator<Item = &'a f32>, {
+    let pairs: Vec<(f64, f64)> = x
+        .zip(y)
+        .filter(|(a, b)| !a.is_nan() && !b.is_nan())
+        .map(|(&a, &b)| (a as f64, b as f64))
+        .collect();
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(a, _)| a).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, b)| b).sum::<f64>() / n;
+    let (mut cov, mut var_x, mut var_y) = (0., 0., 0.);
+    for (a, b) in pairs.iter() {
+        cov += (a - mean_x) * (b - mean_y);
+        var_x += (a - mean_x) * (a - mean_x);
+        var_y += (b - mean_y) * (b - mean_y);
+    }
+    if var_x == 0. || var_y == 0. {
+        return f32::NAN;
+    }
+    (cov / (var_x * var_y).sqrt()) as f32
+}
+
+/// Checks that `perm` is a permutation of `0..n`.
+fn validate_permutation(
+    perm: &[usize],
+    n: usize,
+    name: &str,
+) -> Result<(), Error> {
+    if perm.len() != n {
+        return Err(Error::Generic(format!(
+            "{} has length {} but is expected to be a permutation of 0..{}",
+            name,
+            perm.len(),
+            n
+        )));
+    }
+    let mut is_visited = vec![false; n];
+    for &i in perm.iter() {
+        if i >= n || is_visited[i] {
+            return Err(Error::Generic(format!(
+                "{} is not a permutation of 0..{}: {} is {}",
+                name,
+                n,
+                i,
+                if i >= n { "out of range" } else { "repeated" }
+            )));
+        }
+        is_visited[i] = true;
+    }
+    Ok(())
+}
+
+/// Returns the means of the `values` in the windows of size `window` starting
+/// every `step` values, where the last window ends at the last value.
+fn windowed_mean(values: &[f32], window: usize, step: usize) -> Vec<f32> {
+    assert!(window > 0, "window must be positive");
+    assert!(step > 0, "step must be positive");
+    let mut means = Vec::new();
+    let mut start = 0;
+    while start < values.len() {
+        let end = min(start + window, values.len());
+        means.push(sum_f32(values[start..end].iter()) / (end - start) as f32);
+        if end == values.len() {
+            break;
+        }
+        start += step;
+    }
+    means
+}
+
+/// Returns the number of people whose genotypes are in the last byte of each
+/// SNP, i.e. the number of the four two-bit slots of the last byte that are
+/// not padding, which is between 1 and 4. Returns `None` only if
+/// `total_num_people` is 0.
+pub fn get_num_people_last_byte(total_num_people: usize) -> Option<usize> {
+    if total_num_people == 0 {
+        None
+    } else {
+        match total_num_people % NUM_PEOPLE_PER_BYTE {
+            0 => Some(NUM_PEOPLE_PER_BYTE),
+            x => Some(x),
+        }
+    }
+}
+
+/// Reads the (family ID, individual ID) in the first two columns of each line
+/// of the fam file.
+fn read_fam_sample_ids(fam_path: &str) -> Result<Vec<(String, String)>, Error> {
+    get_buf(fam_path)?
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let line = line?;
+            let mut toks = line.split_whitespace();
+            match (toks.next(), toks.next()) {
+                (Some(family_id), Some(individual_id)) => {
+                    Ok((family_id.to_string(), individual_id.to_string()))
+                }
+                _ => Err(Error::BadFormat(format!(
+                    "line {} of {} has fewer than 2 columns: {}",
+                    i + 1,
+                    fam_path,
+                    line
+                ))),
+            }
+        })
+        .collect()
+}
+
+/// Counts the lines of the file by scanning its newline bytes, where a final
+/// line without a trailing newline is also counted.
+fn get_line_count(filename: &str) -> Result<usize, Error> {
+    let mut buf = get_buf(filename)?;
+    let mut count = 0;
+    let mut last_byte = None;
+    loop {
+        let bytes = buf.fill_buf()?;
+        if bytes.is_empty() {
+            break;
+        }
+        count += bytes.iter().filter(|&&b| b == b'\n').count();
+        last_byte = bytes.last().copied();
+        let len = bytes.len();
+        buf.consume(len);
+    }
+    if last_byte.is_some() && last_byte != Some(b'\n') {
+        count += 1;
+    }
+    Ok(count)
+}
+
+struct FileSnpIndexer {
+    file_num_snps: Vec<(usize, PlinkSnpType)>,
+}
+
+impl FileSnpIndexer {
+    fn new(file_num_snps: Vec<(usize, PlinkSnpType)>) -> FileSnpIndexer {
+        FileSnpIndexer {
+            file_num_snps,
+        }
+    }
+
+    /// returns a `Some` of a tuple (file_index, snp_index_within_file)
+    /// if the SNP is within range. `None` otherwise.
+    fn get_file_snp_index(
+        &self,
+        snp_index: usize,
+    ) -> Option<(usize, usize, PlinkSnpType)> {
+        let mut acc = 0;
+        for (file_index, (count, snp_type)) in
+            self.file_num_snps.iter().enumerate()
+        {
+            if snp_index < acc + *count {
+                return Some((file_index, snp_index - acc, *snp_type));
+            }
+            acc += *count;
+        }
+        None
+    }
+
+    fn total_num_snps(&self) -> usize {
+        self.file_num_snps.iter().map(|pair| pair.0).sum::<usize>()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PlinkSnpType {
+    Additive,
+    Dominance,
+}
+
+impl FromStr for PlinkSnpType {
+    type Err = Error;
+
+    /// Parses `additive` or `dominance`, ignoring the case and the
+    /// surrounding whitespace.
+    fn from_str(s: &str) -> Result<PlinkSnpType, Error> {
+        match s.trim().to_lowercase().as_str() {
+            "additive" => Ok(PlinkSnpType::Additive),
+            "dominance" => Ok(PlinkSnpType::Dominance),
+            _ => Err(Error::BadFormat(format!(
+                "unrecognized SNP type {}, expected additive or dominance",
+                s
+            ))),
+        }
+    }
+}
+
+/// Determines the bits in the unused slots of the last byte of each SNP when
+/// writing a bed file whose number of people is not a multiple of 4.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum PaddingBits {
+    /// The slots are filled with 00, as required by the PLINK specification.
+    #[default]
+    Zero,
+    /// The slots are filled with the missing genotype code 01.
+    Missing,
+}
+
+/// Determines the value of a missing genotype in the decoded SNPs.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum MissingStrategy {
+    /// Missing genotypes are decoded as 0.
+    #[default]
+    Zero,
+    /// Missing genotypes are replaced by the mean of the non-missing values
+    /// of the SNP, after any dominance encoding.
+    MeanImpute,
+}
+
+/// Determines the allele taken as the minor allele in the dominance encoding
+/// of each SNP, as described in
+/// `convert_geno_arr_to_dominance_representation_with_orientation`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum DominanceOrientation {
+    /// The first allele in the .bim file is the minor allele.
+    #[default]
+    FirstAllele,
+    /// The second allele in the .bim file is the minor allele.
+    SecondAllele,
+    /// The less frequent allele of each SNP among the non-missing genotypes
+    /// is the minor allele, where the first allele is taken on a tie.
+    MinorAllele,
+}
+
+impl DominanceOrientation {
+    /// Returns whether the first allele is taken as the minor allele of a SNP,
+    /// where `counts` computes the genotype counts of the SNP, which are only
+    /// needed for `MinorAllele`.
+    fn assume_minor_is_first<F: FnOnce() -> GenotypeCounts>(
+        self,
+        counts: F,
+    ) -> bool {
+        match self {
+            DominanceOrientation::FirstAllele => true,
+            DominanceOrientation::SecondAllele => false,
+            DominanceOrientation::MinorAllele => {
+                let counts = counts();
+                counts.num_hom_first <= counts.num_hom_second
+            }
+        }
+    }
+}
+
+pub struct PlinkColChunkIter {
+    buf: Vec<BufReader<SharedFile>>,
+    /// The open bed files shared by this iterator and the iterators split
+    /// from it, each of which reads the files at its own positions.
+    files: Option<Arc<Vec<File>>>,
+    file_num_snps: Vec<(usize, PlinkSnpType)>,
+    range: OrderedIntegerSet<usize>,
+    num_snps_per_iter: usize,
+    num_people: usize,
+    num_snps_in_range: usize,
+    range_cursor: usize,
+    last_read_file_snp_index: Option<(usize, usize)>,
+    bed_path_list: Vec<String>,
+    file_snp_indexer: FileSnpIndexer,
+    /// The first error encountered by this iterator or by any of the
+    /// iterators split from it during parallel iteration.
+    error: Arc<Mutex<Option<Error>>>,
+    missing_strategy: MissingStrategy,
+    dominance_orientation: DominanceOrientation,
+    /// The types of the SNPs aligned to the range, which override the types
+    /// of the files if present.
+    snp_types: Option<Vec<PlinkSnpType>>,
+    /// The minimum number of SNPs processed by a task in parallel iteration.
+    min_snps_per_split: usize,
+}
+
+impl PlinkColChunkIter {
+    /// The iterator does not panic on IO errors. Instead, it stops yielding
+    /// chunks once an error occurs, and the error can be retrieved by
+    /// `take_error` after the iteration.
+    ///
+    /// The `range` is checked against the total number of SNPs up front. If
+    /// its last index is out of range, the `SnpIndexOutOfRange` error is
+    /// recorded right away, so that `take_error` returns it before any
+    /// iteration, and the iterator yields nothing.
+    pub fn new(
+        file_num_snps: Vec<(usize, PlinkSnpType)>,
+        range: OrderedIntegerSet<usize>,
+        num_snps_per_iter: usize,
+        num_people: usize,
+        bed_path_list: Vec<String>,
+    ) -> PlinkColChunkIter {
+        let mut iter = PlinkColChunkIter::new_with_error_slot(
+            file_num_snps,
+            range,
+            num_snps_per_iter,
+            num_people,
+            bed_path_list,
+            Arc::new(Mutex::new(None)),
+        );
+        if let Some(last) = iter.range.last() {
+            let total = iter.file_snp_indexer.total_num_snps();
+            if last >= total {
+                iter.poison(Error::SnpIndexOutOfRange {
+                    index: last,
+                    total,
+                });
+                iter.range_cursor = iter.num_snps_in_range;
+                return iter;
+            }
+        }
+        if let Some(start) = iter.range.first() {
+            if let Err(error) = iter.seek_to_snp(start) {
+                iter.poison(error);
+            }
+        } else if let Err(error) = iter.open_files() {
+            iter.poison(error);
+        }
+        iter
+    }
+
+    /// Creates the iterator without opening the bed files, which are opened
+    /// by `open_files` before the first seek or read.
+    fn new_with_error_slot(
+        file_num_snps: Vec<(usize, PlinkSnpType)>,
+        range: OrderedIntegerSet<usize>,
+        num_snps_per_iter: usize,
+        num_people: usize,
+        bed_path_list: Vec<String>,
+        error: Arc<Mutex<Option<Error>>>,
+    ) -> PlinkColChunkIter {
+        let num_snps_in_range = range.size();
+        let file_snp_indexer = FileSnpIndexer::new(file_num_snps.clone());
+        PlinkColChunkIter {
+            buf: Vec::new(),
+            files: None,
+            file_num_snps,
+            range,
+            num_snps_per_iter,
+            num_people,
+            num_snps_in_range,
+            range_cursor: 0,
+            last_read_file_snp_index: None,
+            bed_path_list,
+            file_snp_indexer,
+            error,
+            missing_strategy: MissingStrategy::default(),
+            dominance_orientation: DominanceOrientation::default(),
+            snp_types: None,
+            min_snps_per_split: 1,
+        }
+    }
+
+    /// Creates the buffered readers of the bed files unless they already
+    /// exist, where the files are opened only if they are not yet shared with
+    /// this iterator.
+    fn open_files(&mut self) -> Result<(), Error> {
+        if !self.buf.is_empty() {
+            return Ok(());
+        }
+        let files = match &self.files {
+            Some(files) => files.clone(),
+            None => {
+                let files = Arc::new(PlinkColChunkIter::open_file_list(
+                    &self.bed_path_list,
+                )?);
+                self.files = Some(files.clone());
+                files
+            }
+        };
+        self.buf = (0..files.len())
+            .map(|i| BufReader::new(SharedFile::new(files.clone(), i)))
+            .collect();
+        Ok(())
+    }
+
+    /// Sets the minimum number of SNPs processed by each task when iterating
+    /// in parallel, below which the iterator is not split further, so that
+    /// the tasks are not dominated by the overhead of setting up the readers.
+    /// The default is a single chunk of `num_snps_per_iter` SNPs.
+    pub fn set_min_snps_per_split(&mut self, min_snps_per_split: usize) {
+        self.min_snps_per_split = min_snps_per_split;
+    }
+
+    /// Returns the error that stopped the iteration, if any, leaving `None`
+    /// in its place.
+    pub fn take_error(&self) -> Option<Error> {
+        self.error.lock().unwrap().take()
+    }
+
+    /// Returns a handle to the error slot shared by this iterator and the
+    /// iterators split from it, so that the error can be retrieved after
+    /// consuming the iterator in parallel.
+    pub fn get_error_handle(&self) -> Arc<Mutex<Option<Error>>> {
+        self.error.clone()
+    }
+
+    /// Returns the number of SNPs in the range that have been read from the
+    /// front, which can be passed to `PlinkBed::col_chunk_iter_from` to
+    /// resume the iteration.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.range_cursor
+    }
+
+    /// Returns the indices of the SNPs that are yet to be read from either
+    /// end of the iterator.
+    pub fn remaining_range(&self) -> OrderedIntegerSet<usize> {
+        self.range.slice(self.range_cursor..self.num_snps_in_range)
+    }
+
+    /// Returns the indices of the SNPs in the chunk that the next call to
+    /// `next` will read, which is empty if the iteration has ended.
+    pub fn current_snp_indices(&self) -> OrderedIntegerSet<usize> {
+        let end = min(
+            self.range_cursor + self.num_snps_per_iter,
+            self.num_snps_in_range,
+        );
+        self.range.slice(self.range_cursor..end)
+    }
+
+    #[inline]
+    fn is_poisoned(&self) -> bool {
+        self.error.lock().unwrap().is_some()
+    }
+
+    /// Records the `error` unless an earlier error has already been recorded.
+    fn poison(&self, error: Error) {
+        let mut slot = self.error.lock().unwrap();
+        if slot.is_none() {
+            *slot = Some(error);
+        }
+    }
+
+    fn open_file_list(bed_path_list: &[String]) -> Result<Vec<File>, Error> {
+        bed_path_list
+            .iter()
+            .map(|p| get_file_buf(p).map(BufReader::into_inner))
+            .collect()
+    }
+
+    fn seek_to_snp(&mut self, snp_index: usize) -> Result<(), Error> {
+        if !self.range.contains(&snp_index) {
+            return Err(Error::Generic(format!(
+                "SNP index {} is not in the iterator range",
+                snp_index
+            )));
+        }
+        self.open_files()?;
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
+        match self.file_snp_indexer.get_file_snp_index(snp_index) {
+            Some((file_index, snp_index_within_file, _snp_type)) => {
+                // skip the first NUM_MAGIC_BYTES magic bytes
+                self.buf[file_index].seek(SeekFrom::Start(snp_byte_offset(
+                    snp_index_within_file,
+                    num_bytes_per_snp,
+                    0,
+                )?))?;
+                Ok(())
+            }
+            None => Err(Error::SnpIndexOutOfRange {
+                index: snp_index,
+                total: self.file_snp_indexer.total_num_snps(),
+            }),
+        }
+    }
+
+    fn read_snp_bytes(
+        &mut self,
+        snp_index: usize,
+        mut snp_bytes_buf: &mut Vec<u8>,
+    ) -> Result<PlinkSnpType, Error> {
+        self.open_files()?;
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
+        match self.file_snp_indexer.get_file_snp_index(snp_index) {
+            Some((file_index, snp_index_within_file, snp_type)) => {
+                if let Some((last_file_index, last_snp_index_within_file)) =
+                    self.last_read_file_snp_index
+                {
+                    if file_index == last_file_index {
+                        let snp_index_gap =
+                            snp_index_within_file - last_snp_index_within_file;
+                        if snp_index_gap > 1 {
+                            self.buf[file_index].seek_relative(
+                                cast_byte_offset(num_snp_bytes(
+                                    snp_index_gap - 1,
+                                    num_bytes_per_snp,
+                                )?)?,
+                            )?;
+                        }
+                        self.buf[file_index].read_exact(&mut snp_bytes_buf)?;
+                        self.last_read_file_snp_index =
+                            Some((file_index, snp_index_within_file));
+                        return Ok(snp_type);
+                    }
+                }
+                self.seek_to_snp(snp_index)?;
+                self.buf[file_index].read_exact(&mut snp_bytes_buf)?;
+                self.last_read_file_snp_index =
+                    Some((file_index, snp_index_within_file));
+                Ok(snp_type)
+            }
+            None => Err(Error::SnpIndexOutOfRange {
+                index: snp_index,
+                total: self.file_snp_indexer.total_num_snps(),
+            }),
+        }
+    }
+
+    /// Clones the iterator over the SNPs at the positions [`start`, `end`) in
+    /// the range. Indices are 0 based. The clone shares the open bed files
+    /// with this iterator instead of reopening them, and creates its readers
+    /// only when it is read, so that the clones that are split further during
+    /// parallel iteration never read.
+    #[inline]
+    fn clone_with_range(&self, start: usize, end: usize) -> PlinkColChunkIter {
+        let mut iter = PlinkColChunkIter::new_with_error_slot(
+            self.file_num_snps.clone(),
+            self.range.slice(start..end),
+            self.num_snps_per_iter,
+            self.num_people,
+            self.bed_path_list.clone(),
+            self.error.clone(),
+        );
+        iter.missing_strategy = self.missing_strategy;
+        iter.dominance_orientation = self.dominance_orientation;
+        iter.snp_types = self
+            .snp_types
+            .as_ref()
+            .map(|types| types[start..end].to_vec());
+        iter.min_snps_per_split = self.min_snps_per_split;
+        iter.files = self.files.clone();
+        iter
+    }
+
+    /// Reads the SNPs at the positions [`start`, `start + chunk_size`) in
+    /// the range, where each SNP is decoded directly into its column of the
+    /// returned chunk.
+    fn read_chunk(
+        &mut self,
+        start: usize,
+        chunk_size: usize,
+    ) -> Result<Array<f32, Ix2>, Error> {
+        let num_people = self.num_people;
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(num_people);
+
+        let snp_indices = self.range.slice(start..start + chunk_size);
+        let actual_chunk_size = snp_indices.size();
+
+        let mut v = vec![0f32; num_people * actual_chunk_size];
+        let mut snp_bytes = vec![0u8; num_bytes_per_snp];
+        for (offset, index) in snp_indices.to_iter().enumerate() {
+            let file_snp_type = self.read_snp_bytes(index, &mut snp_bytes)?;
+            let snp_type = match &self.snp_types {
+                Some(types) => types[start + offset],
+                None => file_snp_type,
+            };
+            let col = &mut v[offset * num_people..(offset + 1) * num_people];
+            decode_snp_bytes_into(&snp_bytes, col);
+            encode_decoded_snp(
+                col,
+                &snp_bytes,
+                snp_type,
+                self.missing_strategy,
+                self.dominance_orientation,
+            );
+        }
+        Ok(Array::from_shape_vec(
+            (num_people, actual_chunk_size).strides((1, num_people)),
+            v,
+        )
+        .unwrap())
+    }
+
+    /// Reads the SNPs at the positions [`start`, `start + chunk_size`) in
+    /// the range, where `decode_snp` decodes the bytes of each SNP into a
+    /// column of the returned chunk.
+    fn read_chunk_with<T, F>(
+        &mut self,
+        start: usize,
+        chunk_size: usize,
+        mut decode_snp: F,
+    ) -> Result<Array<T, Ix2>, Error>
+    where
+        F: FnMut(&[u8], PlinkSnpType) -> Vec<T>, {
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
+
+        let snp_indices = self.range.slice(start..start + chunk_size);
+        let actual_chunk_size = snp_indices.size();
+
+        let mut v = Vec::with_capacity(self.num_people * actual_chunk_size);
+        let mut snp_bytes = vec![0u8; num_bytes_per_snp];
+        for (offset, index) in snp_indices.to_iter().enumerate() {
+            let file_snp_type = self.read_snp_bytes(index, &mut snp_bytes)?;
+            let snp_type = match &self.snp_types {
+                Some(types) => types[start + offset],
+                None => file_snp_type,
+            };
+            v.append(&mut decode_snp(&snp_bytes, snp_type));
+        }
+        Ok(Array::from_shape_vec(
+            (self.num_people, actual_chunk_size).strides((1, self.num_people)),
+            v,
+        )
+        .unwrap())
+    }
+
+    fn read_chunk_from_back(
+        &mut self,
+        chunk_size: usize,
+    ) -> Result<Array<f32, Ix2>, Error> {
+        // reading from the back is equivalent to reducing the number of SNPs in
+        // range
+        self.num_snps_in_range -= chunk_size;
+
+        // without a last read SNP, self.read_chunk seeks to the first SNP of
+        // the chunk instead of reading on from the buffer position, and so
+        // does the next forward read after the buffer has been moved here
+        self.last_read_file_snp_index = None;
+        let chunk = self.read_chunk(self.num_snps_in_range, chunk_size)?;
+        self.last_read_file_snp_index = None;
+        Ok(chunk)
+    }
+}
+
+/// Decodes the `snp_bytes` of a SNP into the genotypes of the `num_people`
+/// people in the float type `T`, encoded according to the `snp_type`, where
+/// the missing genotypes are handled according to the `missing_strategy`. The
+/// dominance encoding and the mean imputation are computed in `T`, e.g. in
+/// `f64` to avoid the loss of precision in `f32` for large cohorts.
+fn decode_snp_bytes_float<T: Float>(
+    snp_bytes: &[u8],
+    num_people: usize,
+    snp_type: PlinkSnpType,
+    missing_strategy: MissingStrategy,
+    dominance_orientation: DominanceOrientation,
+) -> Vec<T> {
+    // the conversion reuses the buffer of the decoded genotypes if T is f32
+    let mut snp_vec: Vec<T> = decode_snp_bytes(snp_bytes, num_people)
+        .into_iter()
+        .map(|x| T::from(x).unwrap())
+        .collect();
+    encode_decoded_snp(
+        &mut snp_vec,
+        snp_bytes,
+        snp_type,
+        missing_strategy,
+        dominance_orientation,
+    );
+    snp_vec
+}
+
+/// Encodes the genotypes in `snp_vec`, decoded from the `snp_bytes` by
+/// `decode_snp_bytes`, in place as in `decode_snp_bytes_float`.
+fn encode_decoded_snp<T: Float>(
+    snp_vec: &mut [T],
+    snp_bytes: &[u8],
+    snp_type: PlinkSnpType,
+    missing_strategy: MissingStrategy,
+    dominance_orientation: DominanceOrientation,
+) {
+    if snp_type == PlinkSnpType::Dominance {
+        let assume_minor_is_first = dominance_orientation
+            .assume_minor_is_first(|| {
+                GenotypeCounts::from_snp_bytes(snp_bytes, snp_vec.len())
+            });
+        convert_geno_vec_to_dominance_representation(
+            snp_vec,
+            assume_minor_is_first,
+        );
+    }
+    // the missing genotypes are decoded as 0, which the dominance encoding
+    // maps to the value of the genotype 0
+    if snp_type == PlinkSnpType::Dominance
+        || missing_strategy == MissingStrategy::MeanImpute
+    {
+        fill_missing(snp_vec, snp_bytes, missing_strategy);
+    }
+}
+
+/// Replaces the values in `snp_vec` whose genotypes are missing in
+/// `snp_bytes` with 0, or with the mean of the non-missing values if the
+/// `missing_strategy` is `MeanImpute` and not all the genotypes are missing.
+fn fill_missing<T: Float>(
+    snp_vec: &mut [T],
+    snp_bytes: &[u8],
+    missing_strategy: MissingStrategy,
+) {
+    let missing_indices: Vec<usize> =
+        decode_snp_bytes_with(snp_bytes, snp_vec.len(), |bits| {
+            bits == MISSING_GENOTYPE_BITS
+        })
+        .into_iter()
+        .enumerate()
+        .filter(|(_, is_missing)| *is_missing)
+        .map(|(j, _)| j)
+        .collect();
+    let num_non_missing = snp_vec.len() - missing_indices.len();
+    let value = match missing_strategy {
+        MissingStrategy::MeanImpute if num_non_missing > 0 => {
+            let mut sum: T = kahan_sigma(snp_vec.iter(), |&x| x);
+            for &j in missing_indices.iter() {
+                sum = sum - snp_vec[j];
+            }
+            sum / T::from(num_non_missing).unwrap()
+        }
+        _ => T::zero(),
+    };
+    for &j in missing_indices.iter() {
+        snp_vec[j] = value;
+    }
+}
+
+/// Returns the dominance encoding of the genotypes 0, 1 and 2 in that order,
+/// where `p` is the frequency of the first allele. See
+/// `convert_geno_arr_to_dominance_representation` for the formulas.
+fn dominance_code_values<T: Float>(
+    p: T,
+    assume_minor_is_first: bool,
+) -> [T; 3] {
+    let two = T::from(2).unwrap();
+    let four = T::from(4).unwrap();
+    if assume_minor_is_first {
+        [T::zero(), two * p, four * p - two]
+    } else {
+        let q = T::one() - p;
+        [four * q - two, two * q, T::zero()]
+    }
+}
+
+/// Same as `convert_geno_arr_to_dominance_representation` for the genotypes
+/// of a single SNP.
+fn convert_geno_vec_to_dominance_representation<T: Float>(
+    geno_vec: &mut [T],
+    assume_minor_is_first: bool,
+) {
+    let num_people = geno_vec.len();
+    let p: T =
+        kahan_sigma(geno_vec.iter(), |&x| x) / T::from(2 * num_people).unwrap();
+    let values = dominance_code_values(p, assume_minor_is_first);
+    for x in geno_vec.iter_mut() {
+        *x = if *x == T::zero() {
+            values[0]
+        } else if *x == T::one() {
+            values[1]
+        } else if *x == T::from(2).unwrap() {
+            values[2]
+        } else {
+            T::zero()
+        };
+    }
+}
+
+/// Converts each column of the `geno_arr`, where each genotype is the number
+/// of copies of the first allele in the .bim file, into the dominance
+/// encoding. With `p` being the frequency of the first allele in the column,
+/// i.e. the sum of the column divided by twice the number of people:
+///
+/// - if `assume_minor_is_first` is true, the first allele is taken as the minor
+///   allele, and the genotypes 2, 1 and 0 are encoded as `4p - 2`, `2p` and `0`
+///   respectively.
+/// - otherwise, the second allele is taken as the minor allele with frequency
+///   `q = 1 - p`, and the genotypes 0, 1 and 2 are encoded as `4q - 2`, `2q`
+///   and `0` respectively.
+///
+/// Any other value, e.g. NaN, is encoded as `0`. The same orientation is used
+/// for all the columns, so the caller should pass `false` when the first
+/// allele is the major allele, e.g. as indicated by the frequencies in
+/// `PlinkBed::get_allele_frequencies`. The SNPs of the dominance type read by
+/// `PlinkBed` are oriented according to its `DominanceOrientation`.
+pub fn convert_geno_arr_to_dominance_representation_with_orientation(
+    mut geno_arr: Array<f32, Ix2>,
+    assume_minor_is_first: bool,
+) -> Array<f32, Ix2> {
+    let num_people = geno_arr.dim().0;
+    let double_num_people = (2 * num_people) as f32;
+    for mut col in geno_arr.axis_iter_mut(Axis(1)) {
+        let p = sum_f32(col.iter()) / double_num_people;
+        let values = dominance_code_values(p, assume_minor_is_first);
+        for x in col.iter_mut() {
+            *x = if *x == 0. {
+                values[0]
+            } else if *x == 1. {
+                values[1]
+            } else if *x == 2. {
+                values[2]
+            } else {
+                0.
+            };
+        }
+    }
+    geno_arr
+}
+
+/// Same as `convert_geno_arr_to_dominance_representation_with_orientation`
+/// with the first allele taken as the minor allele, i.e. the genotypes 2, 1
+/// and 0 are encoded as `4p - 2`, `2p` and `0` respectively.
+pub fn convert_geno_arr_to_dominance_representation(
+    geno_arr: Array<f32, Ix2>,
+) -> Array<f32, Ix2> {
+    convert_geno_arr_to_dominance_representation_with_orientation(
+        geno_arr, true,
+    )
+}
+
+impl IntoParallelIterator for PlinkColChunkIter {
+    type Item = <PlinkColChunkParallelIter as ParallelIterator>::Item;
+    type Iter = PlinkColChunkParallelIter;
+
+    fn into_par_iter(self) -> Self::Iter {
+        PlinkColChunkParallelIter {
+            iter: self,
+        }
+    }
+}
+
+impl Iterator for PlinkColChunkIter {
+    type Item = Array<f32, Ix2>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.range_cursor >= self.num_snps_in_range || self.is_poisoned() {
+            return None;
+        }
+        let chunk_size = min(
+            self.num_snps_per_iter,
+            self.num_snps_in_range - self.range_cursor,
+        );
+        let start = self.range_cursor;
+        self.range_cursor += chunk_size;
+        match self.read_chunk(start, chunk_size) {
+            Ok(chunk) => Some(chunk),
+            Err(error) => {
+                self.poison(error);
+                None
+            }
+        }
+    }
+}
+
+impl ExactSizeIterator for PlinkColChunkIter {
+    fn len(&self) -> usize {
+        usize_div_ceil(
+            self.num_snps_in_range - self.range_cursor,
+            self.num_snps_per_iter,
+        )
+    }
+}
+
+impl DoubleEndedIterator for PlinkColChunkIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.range_cursor >= self.num_snps_in_range || self.is_poisoned() {
+            return None;
+        }
+        let chunk_size = min(
+            self.num_snps_per_iter,
+            self.num_snps_in_range - self.range_cursor,
+        );
+        match self.read_chunk_from_back(chunk_size) {
+            Ok(chunk) => Some(chunk),
+            Err(error) => {
+                self.poison(error);
+                None
+            }
+        }
+    }
+}
+
+pub struct PlinkPolymorphicColChunkIter {
+    col_chunk_iter: PlinkColChunkIter,
+    snp_index_iter: IntegerSetIter<usize>,
+    num_snps_per_iter: usize,
+    num_people: usize,
+    // the polymorphic SNPs read but not yet returned, in column major order
+    buffered_snps: Vec<f32>,
+    buffered_snp_indices: Vec<usize>,
+}
+
+impl Iterator for PlinkPolymorphicColChunkIter {
+    type Item = (Array<f32, Ix2>, Vec<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let num_alleles = (self.num_people * 2) as f32;
+        while self.buffered_snp_indices.len() < self.num_snps_per_iter {
+            let snps = match self.col_chunk_iter.next() {
+                None => break,
+                Some(snps) => snps,
+            };
+            for col in snps.gencolumns() {
+                let snp_index = self.snp_index_iter.next().unwrap();
+                let frequency = sum_f32(col.iter()) / num_alleles;
+                if frequency > 0. && frequency < 1. {
+                    self.buffered_snps.extend(col.iter());
+                    self.buffered_snp_indices.push(snp_index);
+                }
+            }
+        }
+        if self.buffered_snp_indices.is_empty() {
+            return None;
+        }
+        let chunk_size =
+            min(self.num_snps_per_iter, self.buffered_snp_indices.len());
+        let v: Vec<f32> = self
+            .buffered_snps
+            .drain(..chunk_size * self.num_people)
+            .collect();
+        let snp_indices: Vec<usize> =
+            self.buffered_snp_indices.drain(..chunk_size).collect();
+        Some((
+            Array::from_shape_vec(
+                (self.num_people, chunk_size).strides((1, self.num_people)),
+                v,
+            )
+            .unwrap(),
+            snp_indices,
+        ))
+    }
+}
+
+struct ColChunkIterProducer {
+    iter: PlinkColChunkIter,
+}
+
+impl Producer for ColChunkIterProducer {
+    type IntoIter = PlinkColChunkIter;
+    type Item = <PlinkColChunkIter as Iterator>::Item;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter
+    }
+
+    fn min_len(&self) -> usize {
+        max(
+            1,
+            usize_div_ceil(
+                self.iter.min_snps_per_split,
+                self.iter.num_snps_per_iter,
+            ),
+        )
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let mid_range_index =
+            min(self.iter.num_snps_per_iter * index, self.iter.range.size());
+        (
+            ColChunkIterProducer {
+                iter: self.iter.clone_with_range(0, mid_range_index),
+            },
+            ColChunkIterProducer {
+                iter: self
+                    .iter
+                    .clone_with_range(mid_range_index, self.iter.range.size()),
+            },
+        )
+    }
+}
+
+impl IntoIterator for ColChunkIterProducer {
+    type IntoIter = PlinkColChunkIter;
+    type Item = <PlinkColChunkIter as Iterator>::Item;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter
+    }
+}
+
+pub struct PlinkColChunkParallelIter {
+    iter: PlinkColChunkIter,
+}
+
+impl ParallelIterator for PlinkColChunkParallelIter {
+    type Item = <PlinkColChunkIter as Iterator>::Item;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>, {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+impl IndexedParallelIterator for PlinkColChunkParallelIter {
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+
+    fn drive<C>(self, consumer: C) -> C::Result
+    where
+        C: Consumer<Self::Item>, {
+        bridge(self, consumer)
+    }
+
+    fn with_producer<CB>(self, callback: CB) -> CB::Output
+    where
+        CB: ProducerCallback<Self::Item>, {
+        callback.callback(ColChunkIterProducer {
+            iter: self.iter,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::iter::{
+        plumbing::Producer, IntoParallelIterator, ParallelIterator,
+    };
+    use std::{
+        cmp::{max, min},
+        fs::OpenOptions,
+        io,
+        io::{Cursor, Write},
+        path::Path,
+        sync::{Arc, Mutex},
+    };
+
+    use flate2::{write::GzEncoder, Compression};
+    use math::{
+        set::{ordered_integer_set::OrderedIntegerSet, traits::Finite},
+        traits::ToIterator,
+    };
+    use ndarray::{array, s, stack, Array, ArrayView, Axis, Ix2};
+    use ndarray_rand::RandomExt;
+    use rand::distributions::Uniform;
+    use tempfile::{tempdir, NamedTempFile, TempPath};
+
+    use crate::{
+        error::Error,
+        plink_bed::{
+            cast_byte_offset, checked_geno_to_lowest_two_bits,
+            convert_geno_arr_to_dominance_representation,
+            convert_geno_arr_to_dominance_representation_with_orientation,
+            convert_geno_vec_to_dominance_representation, decode_snp_bytes,
+            decode_snp_bytes_u8, geno_to_lowest_two_bits, get_line_count,
+            get_num_people_last_byte, lowest_two_bits_to_geno, num_snp_bytes,
+            snp_byte_offset, usize_div_ceil, windowed_mean, BedWriter,
+            ColChunkIterProducer, DominanceOrientation, Fnv1a, GenotypeCounts,
+            MissingStrategy, PaddingBits, PlinkBed, PlinkSnpType,
+            ProgressReporter, SnpFlag, DEFAULT_CHUNK_MEMORY_BUDGET_BYTES,
+            MAGIC_BYTES, MAX_PROGRESS_REPORTS, MISSING_GENOTYPE_BITS,
+            NUM_MAGIC_BYTES,
+        },
+        plink_bim::{Bim, BimRecord},
+        plink_fam::FamRecord,
+    };
+
+    fn create_dummy_bim_fam(
+        mut bim: &mut NamedTempFile,
+        mut fam: &mut NamedTempFile,
+        num_people: usize,
+        num_snps: usize,
+    ) -> Result<(), io::Error> {
+        write_dummy_bim(&mut bim, num_snps)?;
+        write_dummy_fam(&mut fam, num_people)?;
+        Ok(())
+    }
+
+    fn write_dummy_bim(
+        bim: &mut NamedTempFile,
+        num_snps: usize,
+    ) -> Result<(), io::Error> {
+        for i in 1..=num_snps {
+            bim.write_fmt(format_args!("{}\n", i))?;
+        }
+        Ok(())
+    }
+
+    fn write_dummy_fam(
+        fam: &mut NamedTempFile,
+        num_people: usize,
+    ) -> Result<(), io::Error> {
+        for i in 1..=num_people {
+            fam.write_fmt(format_args!("{}\n", i))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_bed() {
+        fn test(geno: &Array<u8, Ix2>) {
+            let mut bim = NamedTempFile::new().unwrap();
+            let mut fam = NamedTempFile::new().unwrap();
+            create_dummy_bim_fam(
+                &mut bim,
+                &mut fam,
+                geno.dim().0,
+                geno.dim().1,
+            )
+            .unwrap();
+            let path = NamedTempFile::new().unwrap().into_temp_path();
+            let path_str = path.to_str().unwrap().to_string();
+            PlinkBed::create_bed(&geno, &path_str).unwrap();
+            let geno_bed = PlinkBed::new(&[(
+                path_str,
+                bim.into_temp_path().to_str().unwrap().to_string(),
+                fam.into_temp_path().to_str().unwrap().to_string(),
+                PlinkSnpType::Additive,
+            )])
+            .unwrap();
+            assert_eq!(
+                geno.mapv(|x| x as f32),
+                geno_bed.get_genotype_matrix(None, None).unwrap()
+            );
+        }
+        test(&array![[0],]);
+        test(&array![[1],]);
+        test(&array![[2],]);
+        test(&array![[0, 1, 2],]);
+        test(&array![[0], [1], [2],]);
+        test(&array![[0, 0, 1], [1, 1, 2], [0, 2, 1],]);
+        test(&array![
+            [0, 0, 1, 2],
+            [1, 1, 2, 1],
+            [2, 0, 0, 0],
+            [1, 0, 0, 2],
+            [0, 2, 1, 0],
+        ]);
+        test(&array![
+            [0, 0, 1, 2, 1],
+            [1, 1, 2, 1, 2],
+            [2, 0, 0, 0, 0],
+            [1, 0, 0, 2, 2],
+            [0, 2, 1, 0, 1],
+        ]);
+        test(&array![
+            [0, 0, 1, 2, 1],
+            [1, 0, 0, 2, 1],
+            [2, 0, 2, 0, 0],
+            [1, 1, 0, 2, 2],
+            [0, 2, 2, 1, 1],
+            [2, 1, 2, 0, 0],
+            [1, 2, 0, 1, 2],
+            [2, 0, 1, 0, 1],
+        ]);
+        test(&array![
+            [0, 0, 1, 2, 1, 2, 2, 0],
+            [1, 0, 0, 2, 1, 2, 1, 1],
+            [2, 0, 2, 0, 0, 0, 2, 1],
+            [1, 1, 0, 2, 2, 1, 1, 1],
+            [0, 2, 2, 1, 1, 2, 0, 2],
+            [2, 1, 2, 0, 0, 0, 2, 2],
+            [1, 2, 0, 1, 2, 1, 1, 0],
+            [2, 0, 1, 0, 1, 0, 0, 2],
+        ]);
+    }
+
+    #[test]
+    fn test_multiple_bfiles() {
+        let (num_people, num_snps_1, num_snps_2) = (137usize, 71usize, 37usize);
+        let geno_1 =
+            Array::random((num_people, num_snps_1), Uniform::from(0..3));
+        let geno_2 =
+            Array::random((num_people, num_snps_2), Uniform::from(0..3));
+        let mut bim_1 = NamedTempFile::new().unwrap();
+        let mut bim_2 = NamedTempFile::new().unwrap();
+        let mut fam = NamedTempFile::new().unwrap();
+        write_dummy_fam(&mut fam, num_people).unwrap();
+        write_dummy_bim(&mut bim_1, num_snps_1).unwrap();
+        write_dummy_bim(&mut bim_2, num_snps_2).unwrap();
+        let bed_path_1 = NamedTempFile::new().unwrap().into_temp_path();
+        let bed_path_2 = NamedTempFile::new().unwrap().into_temp_path();
+        let bim_path_1 = bim_1.into_temp_path();
+        let bim_path_2 = bim_2.into_temp_path();
+        let fam_path = fam.into_temp_path();
+        PlinkBed::create_bed(&geno_1, bed_path_1.to_str().unwrap()).unwrap();
+        PlinkBed::create_bed(&geno_2, bed_path_2.to_str().unwrap()).unwrap();
+
+        let bed = PlinkBed::new(&[
+            (
+                bed_path_1.to_str().unwrap().to_string(),
+                bim_path_1.to_str().unwrap().to_string(),
+                fam_path.to_str().unwrap().to_string(),
+                PlinkSnpType::Additive,
+            ),
+            (
+                bed_path_2.to_str().unwrap().to_string(),
+                bim_path_2.to_str().unwrap().to_string(),
+                fam_path.to_str().unwrap().to_string(),
+                PlinkSnpType::Additive,
+            ),
+        ])
+        .unwrap();
+        let true_geno_arr = stack![Axis(1), geno_1, geno_2].mapv(|x| x as f32);
+        assert_eq!(true_geno_arr, bed.get_genotype_matrix(None, None).unwrap());
+    }
+
+    #[test]
+    fn test_error_variants() {
+        let (bed_1, bim_1, fam_1) =
+            create_temp_geno_bfile(&array![[0, 1], [2, 0], [1, 1]]);
+        let (bed_2, bim_2, fam_2) = create_temp_geno_bfile(&array![[0], [2]]);
+        let to_tuple = |bed: &TempPath, bim: &TempPath, fam: &TempPath| {
+            (
+                bed.to_str().unwrap().to_string(),
+                bim.to_str().unwrap().to_string(),
+                fam.to_str().unwrap().to_string(),
+                PlinkSnpType::Additive,
+            )
+        };
+        match PlinkBed::new(&[
+            to_tuple(&bed_1, &bim_1, &fam_1),
+            to_tuple(&bed_2, &bim_2, &fam_2),
+        ]) {
+            Err(Error::InconsistentSampleCount {
+                counts,
+            }) => assert_eq!(counts, vec![3, 2]),
+            _ => panic!("expected an InconsistentSampleCount error"),
+        }
+
+        let bed = PlinkBed::new(&[to_tuple(&bed_1, &bim_1, &fam_1)]).unwrap();
+        match bed.byte_chunk_iter(1, 0, 1, 1) {
+            Err(Error::FileIndexOutOfRange {
+                index,
+                num_files,
+            }) => assert_eq!((index, num_files), (1, 1)),
+            _ => panic!("expected a FileIndexOutOfRange error"),
+        }
+    }
+
+    #[test]
+    fn test_from_prefix() {
+        let dir = tempdir().unwrap();
+        let prefix_1 = dir.path().join("geno_1");
+        let prefix_1 = prefix_1.to_str().unwrap();
+        let prefix_2 = dir.path().join("geno_2");
+        let prefix_2 = prefix_2.to_str().unwrap();
+        let geno_1 = array![[0u8, 1], [2, 1], [1, 0]];
+        let geno_2 = array![[2u8], [0], [1]];
+        for (prefix, geno) in [(prefix_1, &geno_1), (prefix_2, &geno_2)].iter()
+        {
+            PlinkBed::create_bed(geno, &format!("{}.bed", prefix)).unwrap();
+            let mut bim = NamedTempFile::new().unwrap();
+            let mut fam = NamedTempFile::new().unwrap();
+            create_dummy_bim_fam(&mut bim, &mut fam, 3, geno.dim().1).unwrap();
+            std::fs::copy(bim.path(), format!("{}.bim", prefix)).unwrap();
+            std::fs::copy(fam.path(), format!("{}.fam", prefix)).unwrap();
+        }
+
+        let bed =
+            PlinkBed::from_prefix(prefix_1, PlinkSnpType::Additive).unwrap();
+        assert_eq!(
+            bed.get_genotype_matrix(None, None).unwrap(),
+            geno_1.mapv(|x| x as f32)
+        );
+        let bed = PlinkBed::from_prefixes(&[
+            (prefix_1, PlinkSnpType::Additive),
+            (prefix_2, PlinkSnpType::Additive),
+        ])
+        .unwrap();
+        assert_eq!(
+            bed.get_genotype_matrix(None, None).unwrap(),
+            stack![Axis(1), geno_1, geno_2].mapv(|x| x as f32)
+        );
+
+        std::fs::remove_file(format!("{}.fam", prefix_2)).unwrap();
+        match PlinkBed::from_prefixes(&[
+            (prefix_1, PlinkSnpType::Additive),
+            (prefix_2, PlinkSnpType::Additive),
+        ]) {
+            Err(Error::IO {
+                why, ..
+            }) => assert!(why.contains(".fam file")),
+            _ => panic!("expected an IO error"),
+        }
+    }
+
+    #[test]
+    fn test_get_genotype_matrix_u8() {
+        let geno =
+            array![[0u8, 1, 2], [2, 1, 0], [1, 0, 0], [2, 2, 1], [0, 1, 1]];
+        let (bed, (bed_path, _bim_path, _fam_path)) = open_temp_bed(&geno);
+        // mark the genotype of the second person in the second SNP as missing
+        set_missing(&bed_path, 5, &[(1, 1)]);
+        let mut expected = geno.clone();
+        expected[[1, 1]] = 255;
+        assert_eq!(bed.get_genotype_matrix_u8(None, 255).unwrap(), expected);
+
+        let range = OrderedIntegerSet::from_slice(&[[1, 2]]);
+        assert_eq!(
+            bed.get_genotype_matrix_u8(Some(range.clone()), 9).unwrap(),
+            array![[1u8, 2], [9, 0], [0, 0], [2, 1], [1, 1]]
+        );
+        let mut expected_f32 = geno.slice(s![.., 1..3]).mapv(|x| x as f32);
+        expected_f32[[1, 0]] = 0.;
+        assert_eq!(
+            bed.get_genotype_matrix(Some(range), None).unwrap(),
+            expected_f32
+        );
+    }
+
+    #[test]
+    fn test_sample_heterozygosity() {
+        let geno =
+            array![[0u8, 1, 2], [2, 1, 0], [1, 0, 0], [2, 2, 1], [0, 1, 1]];
+        let (bed, (bed_path, _bim_path, _fam_path)) = open_temp_bed(&geno);
+        // mark the genotype of the second person in the second SNP as missing
+        set_missing(&bed_path, 5, &[(1, 1)]);
+        let het = bed.sample_heterozygosity().unwrap();
+        let expected = [1. / 3., 0., 1. / 3., 1. / 3., 2. / 3.];
+        assert_eq!(het.len(), expected.len());
+        for (h, e) in het.iter().zip(expected.iter()) {
+            assert!((h - e).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_get_dominance_matrix() {
+        let geno = Array::random((23, 17), Uniform::from(0..3));
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let geno_f32 = geno.mapv(|x| x as f32);
+        assert_arr_almost_eq_f32(
+            &bed.get_dominance_matrix(None).unwrap(),
+            &convert_geno_arr_to_dominance_representation(geno_f32.clone()),
+            1e-6,
+        );
+        let range = OrderedIntegerSet::from_slice(&[[2, 4], [10, 10]]);
+        assert_arr_almost_eq_f32(
+            &bed.get_dominance_matrix(Some(range.clone())).unwrap(),
+            &convert_geno_arr_to_dominance_representation(stack![
+                Axis(1),
+                geno_f32.slice(s![.., 2..5]),
+                geno_f32.slice(s![.., 10..11])
+            ]),
+            1e-6,
+        );
+        // the additive decoding of the same bed is unaffected
+        assert_eq!(bed.get_genotype_matrix(None, None).unwrap(), geno_f32);
+    }
+
+    #[test]
+    fn test_col_chunk_iter_with_types() {
+        let geno = Array::random((23, 17), Uniform::from(0..3));
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let range = OrderedIntegerSet::from_slice(&[[1, 4], [8, 12], [16, 16]]);
+        let range_vec = range.to_iter().collect::<Vec<usize>>();
+        let snp_types: Vec<PlinkSnpType> = (0..range_vec.len())
+            .map(|i| {
+                if i % 3 == 0 {
+                    PlinkSnpType::Dominance
+                } else {
+                    PlinkSnpType::Additive
+                }
+            })
+            .collect();
+        let geno_f32 = geno.mapv(|x| x as f32);
+        let dominance =
+            convert_geno_arr_to_dominance_representation(geno_f32.clone());
+        let mut expected = Array::zeros((23, range_vec.len()));
+        for (i, (&snp_index, snp_type)) in
+            range_vec.iter().zip(snp_types.iter()).enumerate()
+        {
+            expected.column_mut(i).assign(&match snp_type {
+                PlinkSnpType::Additive => geno_f32.column(snp_index),
+                PlinkSnpType::Dominance => dominance.column(snp_index),
+            });
+        }
+
+        let chunk_size = 4;
+        let chunks: Vec<Array<f32, Ix2>> = bed
+            .col_chunk_iter_with_types(
+                chunk_size,
+                Some(range.clone()),
+                &snp_types,
+            )
+            .unwrap()
+            .collect();
+        assert_arr_almost_eq_f32(
+            &stack(
+                Axis(1),
+                &chunks.iter().map(|c| c.view()).collect::<Vec<_>>(),
+            )
+            .unwrap(),
+            &expected,
+            1e-6,
+        );
+
+        // reading from the back and in parallel yields the same SNPs
+        let chunks_from_back: Vec<Array<f32, Ix2>> = bed
+            .col_chunk_iter_with_types(
+                chunk_size,
+                Some(range.clone()),
+                &snp_types,
+            )
+            .unwrap()
+            .rev()
+            .collect();
+        assert_arr_almost_eq_f32(
+            &stack(
+                Axis(1),
+                &chunks_from_back
+                    .iter()
+                    .rev()
+                    .map(|c| c.view())
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap(),
+            &expected,
+            1e-6,
+        );
+        let par_chunks: Vec<Array<f32, Ix2>> = bed
+            .col_chunk_iter_with_types(
+                chunk_size,
+                Some(range.clone()),
+                &snp_types,
+            )
+            .unwrap()
+            .into_par_iter()
+            .collect();
+        for (a, b) in par_chunks.iter().zip(chunks.iter()) {
+            assert_arr_almost_eq_f32(a, b, 1e-6);
+        }
+
+        assert!(bed
+            .col_chunk_iter_with_types(chunk_size, Some(range), &snp_types[1..])
+            .is_err());
+    }
+
+    #[test]
+    fn test_min_snps_per_split() {
+        let geno = Array::random((7, 37), Uniform::from(0..3));
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let range = OrderedIntegerSet::from_slice(&[[1, 10], [14, 33]]);
+        let expected: Vec<Array<f32, Ix2>> =
+            bed.col_chunk_iter(3, Some(range.clone())).collect();
+
+        // the clones share the open files and create their readers when read
+        let iter = bed.col_chunk_iter(3, Some(range.clone()));
+        let mut clone = iter.clone_with_range(6, 30);
+        assert!(clone.buf.is_empty());
+        assert!(Arc::ptr_eq(
+            clone.files.as_ref().unwrap(),
+            iter.files.as_ref().unwrap()
+        ));
+        assert_eq!(clone.next().unwrap(), expected[2]);
+        assert_eq!(clone.buf.len(), 1);
+        let mut other_clone = iter.clone_with_range(0, 6);
+        assert_eq!(other_clone.next().unwrap(), expected[0]);
+        assert_eq!(clone.next().unwrap(), expected[3]);
+        assert_eq!(other_clone.next().unwrap(), expected[1]);
+        assert!(other_clone.next().is_none());
+        assert!(Arc::ptr_eq(
+            clone.files.as_ref().unwrap(),
+            other_clone.files.as_ref().unwrap()
+        ));
+        let expected = expected.clone();
+        let chunks: Vec<Array<f32, Ix2>> = clone.collect();
+        assert_eq!(chunks, expected[4..].to_vec());
+
+        for min_snps_per_split in [0, 1, 2, 3, 7, 30, 100] {
+            let mut iter = bed.col_chunk_iter(3, Some(range.clone()));
+            iter.set_min_snps_per_split(min_snps_per_split);
+            let producer = ColChunkIterProducer {
+                iter,
+            };
+            assert_eq!(
+                producer.min_len(),
+                max(1, usize_div_ceil(min_snps_per_split, 3))
+            );
+            let mut iter = bed.col_chunk_iter(3, Some(range.clone()));
+            iter.set_min_snps_per_split(min_snps_per_split);
+            let chunks: Vec<Array<f32, Ix2>> = iter.into_par_iter().collect();
+            assert_eq!(chunks, expected);
+        }
+    }
+
+    #[test]
+    fn test_get_minor_allele_frequencies() {
+        let geno = array![[0, 1, 2], [2, 1, 0], [1, 0, 0]];
+        let (bed, _bfile) = open_temp_bed(&geno);
+        assert_eq!(
+            bed.get_default_chunk_size(),
+            DEFAULT_CHUNK_MEMORY_BUDGET_BYTES / 12
+        );
+        let expected = vec![0.5, 1. / 3., 1. / 3.];
+        assert_eq!(bed.get_minor_allele_frequencies(None).unwrap(), expected);
+        assert_eq!(
+            bed.get_minor_allele_frequencies(Some(1)).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_get_allele_frequencies() {
+        // the first allele is the major allele of SNPs 0 and 2
+        let geno = array![[2, 1, 2], [2, 0, 1], [1, 0, 0]];
+        let (mut bed, _bfile) = open_temp_bed(&geno);
+        for precompute in [false, true] {
+            if precompute {
+                bed.precompute_snp_stats().unwrap();
+            }
+            let frequencies = bed.get_allele_frequencies(None).unwrap();
+            for (f, expected) in
+                frequencies.iter().zip([5. / 6., 1. / 6., 0.5].iter())
+            {
+                assert!((f - expected).abs() < 1e-6);
+            }
+            let minor_frequencies =
+                bed.get_minor_allele_frequencies(None).unwrap();
+            for (f, expected) in
+                minor_frequencies.iter().zip([1. / 6., 1. / 6., 0.5].iter())
+            {
+                assert!((f - expected).abs() < 1e-6);
+                assert!(*f <= 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_precompute_snp_stats() {
+        let geno = array![[0, 1, 2], [2, 1, 0], [1, 0, 0]];
+        let (mut bed, (bed_path, _bim_path, _fam_path)) = open_temp_bed(&geno);
+        // mark the genotype of person 1 at SNP 2 as missing
+        set_missing(&bed_path, 3, &[(1, 2)]);
+        let counts = bed.genotype_counts(None).unwrap();
+        let frequencies = bed.get_allele_frequencies(None).unwrap();
+        bed.set_missing_strategy(MissingStrategy::MeanImpute);
+        let imputed_frequencies = bed.get_allele_frequencies(None).unwrap();
+        bed.set_missing_strategy(MissingStrategy::Zero);
+
+        bed.precompute_snp_stats().unwrap();
+        // the cached stats are used even after the bed file is overwritten
+        let (other_bed_path, _, _) =
+            create_temp_geno_bfile(&array![[2, 2, 2], [2, 2, 2], [2, 2, 2]]);
+        std::fs::copy(&other_bed_path, &bed_path).unwrap();
+        assert_eq!(bed.genotype_counts(None).unwrap(), counts);
+        assert_eq!(bed.get_allele_frequencies(None).unwrap(), frequencies);
+        bed.set_missing_strategy(MissingStrategy::MeanImpute);
+        for (f, expected) in bed
+            .get_allele_frequencies(None)
+            .unwrap()
+            .iter()
+            .zip(imputed_frequencies.iter())
+        {
+            assert!((f - expected).abs() < 1e-6);
+        }
+        bed.set_missing_strategy(MissingStrategy::Zero);
+        assert_eq!(
+            bed.snp_flags(None).unwrap(),
+            counts
+                .into_iter()
+                .map(SnpFlag::from)
+                .collect::<Vec<SnpFlag>>()
+        );
+
+        bed.invalidate_snp_stats();
+        assert_eq!(bed.get_allele_frequencies(None).unwrap(), vec![1., 1., 1.]);
+        assert_eq!(bed.genotype_counts(None).unwrap()[0].num_hom_first, 3);
+    }
+
+    #[test]
+    fn test_remaining_range() {
+        let geno = Array::random((5, 20), Uniform::from(0..3));
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let range = OrderedIntegerSet::from_slice(&[[1, 4], [8, 9], [12, 16]]);
+        let mut iter = bed.col_chunk_iter(3, Some(range.clone()));
+        assert_eq!(iter.remaining_range(), range);
+        assert_eq!(
+            iter.current_snp_indices(),
+            OrderedIntegerSet::from_slice(&[[1, 3]])
+        );
+
+        iter.next().unwrap();
+        assert_eq!(
+            iter.remaining_range(),
+            OrderedIntegerSet::from_slice(&[[4, 4], [8, 9], [12, 16]])
+        );
+        assert_eq!(
+            iter.current_snp_indices(),
+            OrderedIntegerSet::from_slice(&[[4, 4], [8, 9]])
+        );
+
+        // next_back reads the last 3 SNPs in the range
+        let last = iter.next_back().unwrap();
+        assert_eq!(last.dim(), (5, 3));
+        assert_eq!(
+            iter.remaining_range(),
+            OrderedIntegerSet::from_slice(&[[4, 4], [8, 9], [12, 13]])
+        );
+        assert_eq!(
+            iter.current_snp_indices(),
+            OrderedIntegerSet::from_slice(&[[4, 4], [8, 9]])
+        );
+
+        while iter.next().is_some() {}
+        assert_eq!(iter.remaining_range(), OrderedIntegerSet::new());
+        assert_eq!(iter.current_snp_indices(), OrderedIntegerSet::new());
+    }
+
+    #[test]
+    fn test_col_chunk_iter_len_with_next_back() {
+        let geno = Array::random((5, 20), Uniform::from(0..3));
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let range =
+            OrderedIntegerSet::from_slice(&[[1, 4], [8, 9], [12, 16], [
+                19, 19,
+            ]]);
+        let expected =
+            bed.get_genotype_matrix(Some(range.clone()), None).unwrap();
+        for num_snps_per_iter in 1..=13 {
+            // the i-th bit of the pattern reads the i-th chunk from the back
+            for pattern in 0..64u32 {
+                let mut iter =
+                    bed.col_chunk_iter(num_snps_per_iter, Some(range.clone()));
+                let mut lens = Vec::new();
+                let mut front_chunks = Vec::new();
+                let mut back_chunks = Vec::new();
+                for i in 0.. {
+                    lens.push(iter.len());
+                    let from_back = i < 32 && (pattern >> i) & 1 == 1;
+                    let chunk = if from_back {
+                        iter.next_back()
+                    } else {
+                        iter.next()
+                    };
+                    match chunk {
+                        Some(chunk) if from_back => back_chunks.push(chunk),
+                        Some(chunk) => front_chunks.push(chunk),
+                        None => break,
+                    }
+                }
+                assert!(iter.take_error().is_none());
+                let num_calls = lens.len() - 1;
+                assert_eq!(lens, (0..=num_calls).rev().collect::<Vec<usize>>());
+                back_chunks.reverse();
+                let chunks: Vec<ArrayView<f32, Ix2>> = front_chunks
+                    .iter()
+                    .chain(back_chunks.iter())
+                    .map(|c| c.view())
+                    .collect();
+                assert_eq!(stack(Axis(1), &chunks).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_col_chunk_iter_from() {
+        let geno = Array::random((7, 30), Uniform::from(0..3));
+        let (bed, _bfile) = open_temp_bed(&geno);
+        for range in [
+            None,
+            Some(OrderedIntegerSet::from_slice(&[[2, 5], [9, 9], [14, 25]])),
+        ] {
+            let chunks: Vec<Array<f32, Ix2>> =
+                bed.col_chunk_iter(4, range.clone()).collect();
+            let mut iter = bed.col_chunk_iter(4, range.clone());
+            for i in 0..=chunks.len() {
+                let position = iter.position();
+                assert_eq!(position, min(4 * i, iter.num_snps_in_range));
+                let resumed: Vec<Array<f32, Ix2>> = bed
+                    .col_chunk_iter_from(4, range.clone(), position)
+                    .collect();
+                assert_eq!(resumed, chunks[i..].to_vec());
+                iter.next();
+            }
+            assert_eq!(
+                bed.col_chunk_iter_from(4, range.clone(), 100).next(),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn test_fewer_than_four_people() {
+        for num_people in 1..=3 {
+            let num_snps = 9;
+            let geno =
+                Array::random((num_people, num_snps), Uniform::from(0..3));
+            let (bed, (bed_path, _bim_path, _fam_path)) = open_temp_bed(&geno);
+            // fill the padding bits of each single-byte SNP with 1s so that
+            // decoding them would be detected
+            {
+                let mut bytes = std::fs::read(&bed_path).unwrap();
+                assert_eq!(bytes.len(), NUM_MAGIC_BYTES + num_snps);
+                for b in bytes.iter_mut().skip(NUM_MAGIC_BYTES) {
+                    *b |= 0xffu8 << (2 * num_people);
+                }
+                std::fs::write(&bed_path, bytes).unwrap();
+            }
+            assert_eq!(bed.num_people, num_people);
+            let expected = geno.mapv(|g| g as f32);
+            for chunk_size in 1..=num_snps {
+                assert_eq!(
+                    bed.get_genotype_matrix(None, Some(chunk_size)).unwrap(),
+                    expected
+                );
+                let chunks: Vec<Array<f32, Ix2>> =
+                    bed.col_chunk_iter(chunk_size, None).collect();
+                let views: Vec<_> = chunks.iter().map(|c| c.view()).collect();
+                assert_eq!(stack(Axis(1), &views).unwrap(), expected);
+
+                let mut back_chunks: Vec<Array<f32, Ix2>> =
+                    bed.col_chunk_iter(chunk_size, None).rev().collect();
+                back_chunks.reverse();
+                let views: Vec<_> =
+                    back_chunks.iter().map(|c| c.view()).collect();
+                assert_eq!(stack(Axis(1), &views).unwrap(), expected);
+            }
+            let range = OrderedIntegerSet::from_slice(&[[1, 2], [5, 7]]);
+            assert_eq!(
+                bed.get_genotype_matrix(Some(range.clone()), None).unwrap(),
+                stack(Axis(1), &[
+                    expected.slice(s![.., 1..3]),
+                    expected.slice(s![.., 5..8])
+                ])
+                .unwrap()
+            );
+            assert_eq!(
+                bed.col_chunk_iter(5, Some(range)).next().unwrap(),
+                stack(Axis(1), &[
+                    expected.slice(s![.., 1..3]),
+                    expected.slice(s![.., 5..8])
+                ])
+                .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_bed_from_dosages() {
+        let dosages = array![
+            [0., 0.5, f32::NAN],
+            [0.4999, 1.5, 2.],
+            [1., 1.5001, 0.3],
+            [1.9, f32::NAN, 0.5],
+            [0.2, 0.7, 1.6]
+        ];
+        let bed_path = NamedTempFile::new().unwrap().into_temp_path();
+        let bed_path_str = bed_path.to_str().unwrap();
+        PlinkBed::create_bed_from_dosages(&dosages, 0.5, 1.5, bed_path_str)
+            .unwrap();
+        let mut bim = NamedTempFile::new().unwrap();
+        let mut fam = NamedTempFile::new().unwrap();
+        create_dummy_bim_fam(&mut bim, &mut fam, 5, 3).unwrap();
+        let bed = PlinkBed::new(&[(
+            bed_path_str.to_string(),
+            bim.path().to_str().unwrap().to_string(),
+            fam.path().to_str().unwrap().to_string(),
+            PlinkSnpType::Additive,
+        )])
+        .unwrap();
+        assert_eq!(bed.get_genotype_matrix_u8(None, 255).unwrap(), array![
+            [0u8, 1, 255],
+            [0, 1, 2],
+            [1, 2, 0],
+            [2, 255, 1],
+            [0, 1, 2]
+        ]);
+        // the padding bits are zero
+        let bytes = std::fs::read(&bed_path).unwrap();
+        assert_eq!(bytes.len(), NUM_MAGIC_BYTES + 3 * 2);
+        for k in 0..3 {
+            assert_eq!(bytes[NUM_MAGIC_BYTES + 2 * k + 1] & 0b11_11_11_00, 0);
+        }
+
+        // equal thresholds call only the threshold itself heterozygous
+        PlinkBed::create_bed_from_dosages(&dosages, 1., 1., bed_path_str)
+            .unwrap();
+        assert_eq!(bed.get_genotype_matrix_u8(None, 255).unwrap(), array![
+            [0u8, 0, 255],
+            [0, 2, 2],
+            [1, 2, 0],
+            [2, 255, 0],
+            [0, 0, 2]
+        ]);
+        assert!(PlinkBed::create_bed_from_dosages(
+            &dosages,
+            1.5,
+            0.5,
+            bed_path_str
+        )
+        .is_err());
+        assert!(PlinkBed::create_bed_from_dosages(
+            &dosages,
+            f32::NAN,
+            1.5,
+            bed_path_str
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_get_line_count() {
+        for (content, expected) in [
+            ("", 0),
+            ("\n", 1),
+            ("a", 1),
+            ("a\n", 1),
+            ("a\nb\nc", 3),
+            ("a\nb\nc\n", 3),
+            ("a\n\nc\n", 3),
+        ] {
+            let mut file = NamedTempFile::new().unwrap();
+            file.write_all(content.as_bytes()).unwrap();
+            assert_eq!(
+                get_line_count(file.path().to_str().unwrap()).unwrap(),
+                expected
+            );
+        }
+        assert!(get_line_count("/nonexistent/file.fam").is_err());
+    }
+
+    #[test]
+    fn test_create_bed_padding() {
+        // 5 people, so that the last byte of each SNP has 3 padding slots
+        let geno = array![[0u8, 2], [1, 2], [2, 1], [0, 0], [1, 2]];
+        let expected_body = |last_byte_padding: u8| {
+            vec![
+                0b11_00_10_11,
+                0b10 | last_byte_padding,
+                0b11_10_00_00,
+                last_byte_padding,
+            ]
+        };
+        for (padding, last_byte_padding) in [
+            (None, 0b00_00_00_00),
+            (Some(PaddingBits::Zero), 0b00_00_00_00),
+            (Some(PaddingBits::Missing), 0b01_01_01_00),
+        ] {
+            let bed_path = NamedTempFile::new().unwrap().into_temp_path();
+            let bed_path_str = bed_path.to_str().unwrap();
+            match padding {
+                None => PlinkBed::create_bed(&geno, bed_path_str).unwrap(),
+                Some(padding) => PlinkBed::create_bed_with_padding(
+                    &geno,
+                    bed_path_str,
+                    padding,
+                )
+                .unwrap(),
+            }
+            let mut expected = MAGIC_BYTES.to_vec();
+            expected.append(&mut expected_body(last_byte_padding));
+            assert_eq!(std::fs::read(&bed_path).unwrap(), expected);
+
+            let mut bim = NamedTempFile::new().unwrap();
+            let mut fam = NamedTempFile::new().unwrap();
+            create_dummy_bim_fam(&mut bim, &mut fam, 5, 2).unwrap();
+            let bed = PlinkBed::new(&[(
+                bed_path_str.to_string(),
+                bim.path().to_str().unwrap().to_string(),
+                fam.path().to_str().unwrap().to_string(),
+                PlinkSnpType::Additive,
+            )])
+            .unwrap();
+            assert_eq!(
+                bed.get_genotype_matrix_u8(None, 3).unwrap(),
+                geno.clone()
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_genotype_matrix_f64() {
+        let geno = Array::random((11, 7), Uniform::from(0..3));
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        // mark the genotype of person 1 at SNP 2 as missing
+        set_missing(&bed_path, 11, &[(1, 2)]);
+        for snp_type in [PlinkSnpType::Additive, PlinkSnpType::Dominance] {
+            let mut bed = PlinkBed::new(&[(
+                bed_path.to_str().unwrap().to_string(),
+                bim_path.to_str().unwrap().to_string(),
+                fam_path.to_str().unwrap().to_string(),
+                snp_type,
+            )])
+            .unwrap();
+            for missing in [MissingStrategy::Zero, MissingStrategy::MeanImpute]
+            {
+                bed.set_missing_strategy(missing);
+                let range = OrderedIntegerSet::from_slice(&[[1, 2], [4, 6]]);
+                for range in [None, Some(range)] {
+                    let expected =
+                        bed.get_genotype_matrix(range.clone(), None).unwrap();
+                    let actual = bed.get_genotype_matrix_f64(range).unwrap();
+                    assert_eq!(actual.dim(), expected.dim());
+                    for (a, e) in actual.iter().zip(expected.iter()) {
+                        assert!((a - *e as f64).abs() < 1e-6);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_transposed_chunk_iter() {
+        let geno = Array::random((11, 7), Uniform::from(0..3));
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        // mark the genotypes of person 5 at SNP 1 and person 10 at SNP 3 as
+        // missing
+        set_missing(&bed_path, 11, &[(5, 1), (10, 3)]);
+        let bfile = (
+            bed_path.to_str().unwrap().to_string(),
+            bim_path.to_str().unwrap().to_string(),
+            fam_path.to_str().unwrap().to_string(),
+        );
+        let mut bed = PlinkBed::new(&[
+            (
+                bfile.0.clone(),
+                bfile.1.clone(),
+                bfile.2.clone(),
+                PlinkSnpType::Additive,
+            ),
+            (bfile.0, bfile.1, bfile.2, PlinkSnpType::Dominance),
+        ])
+        .unwrap();
+        for missing in [MissingStrategy::Zero, MissingStrategy::MeanImpute] {
+            bed.set_missing_strategy(missing);
+            let expected = bed.get_genotype_matrix(None, None).unwrap();
+            for precompute in [false, true] {
+                if precompute {
+                    bed.precompute_snp_stats().unwrap();
+                } else {
+                    bed.invalidate_snp_stats();
+                }
+                for people_per_chunk in 1..=12 {
+                    let mut iter =
+                        bed.transposed_chunk_iter(people_per_chunk).unwrap();
+                    let mut start = 0;
+                    for block in &mut iter {
+                        let end = min(start + people_per_chunk, 11);
+                        assert_eq!(block.dim(), (end - start, 14));
+                        for (a, e) in block
+                            .iter()
+                            .zip(expected.slice(s![start..end, ..]).iter())
+                        {
+                            assert!((a - e).abs() < 1e-6);
+                        }
+                        start = end;
+                    }
+                    assert_eq!(start, 11);
+                    assert!(iter.take_error().is_none());
+                }
+            }
+        }
+        assert!(bed.transposed_chunk_iter(0).is_err());
+    }
+
+    #[test]
+    fn test_snp_sums() {
+        let geno =
+            array![[0u8, 2, 1], [1, 1, 2], [2, 0, 1], [2, 2, 0], [1, 0, 0]];
+        let (bed, (bed_path, _bim_path, _fam_path)) = open_temp_bed(&geno);
+        // mark the genotype of the fourth person in the first SNP as missing
+        set_missing(&bed_path, 5, &[(3, 0)]);
+        let expected = vec![(4., 6., 4), (5., 9., 5), (4., 6., 5)];
+        assert_eq!(bed.snp_sums(None).unwrap(), expected);
+        assert_eq!(bed.snp_sums(Some(2)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_snp_byte_iter() {
+        let geno = Array::random((6, 5), Uniform::from(0..3));
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        let other_geno = Array::random((6, 4), Uniform::from(0..3));
+        let (other_bed_path, other_bim_path, other_fam_path) =
+            create_temp_geno_bfile(&other_geno);
+        let bed = PlinkBed::new(&[
+            (
+                bed_path.to_str().unwrap().to_string(),
+                bim_path.to_str().unwrap().to_string(),
+                fam_path.to_str().unwrap().to_string(),
+                PlinkSnpType::Additive,
+            ),
+            (
+                other_bed_path.to_str().unwrap().to_string(),
+                other_bim_path.to_str().unwrap().to_string(),
+                other_fam_path.to_str().unwrap().to_string(),
+                PlinkSnpType::Additive,
+            ),
+        ])
+        .unwrap();
+        let decode = |snp_bytes: &[u8]| decode_snp_bytes_u8(snp_bytes, 6, 255);
+
+        let mut iter = bed.snp_byte_iter(0, None).unwrap();
+        let snps: Vec<(usize, Vec<u8>)> = (&mut iter).collect();
+        assert!(iter.take_error().is_none());
+        assert_eq!(snps.iter().map(|(i, _)| *i).collect::<Vec<usize>>(), vec![
+            0, 1, 2, 3, 4
+        ]);
+        for (i, snp_bytes) in snps.iter() {
+            assert_eq!(snp_bytes.len(), 2);
+            assert_eq!(decode(snp_bytes), geno.column(*i).to_vec());
+        }
+
+        let range = OrderedIntegerSet::from_slice(&[[0, 0], [2, 3]]);
+        let snps: Vec<(usize, Vec<u8>)> =
+            bed.snp_byte_iter(1, Some(range)).unwrap().collect();
+        assert_eq!(snps.iter().map(|(i, _)| *i).collect::<Vec<usize>>(), vec![
+            5, 7, 8
+        ]);
+        for (i, snp_bytes) in snps.iter() {
+            assert_eq!(decode(snp_bytes), other_geno.column(*i - 5).to_vec());
+        }
+
+        assert!(bed.snp_byte_iter(2, None).is_err());
+        assert!(bed
+            .snp_byte_iter(1, Some(OrderedIntegerSet::from_slice(&[[3, 4]])))
+            .is_err());
+    }
+
+    #[test]
+    fn test_inbreeding_coefficients() {
+        let geno = Array::random((7, 23), Uniform::from(0..3));
+        let (mut bed, (bed_path, _bim_path, _fam_path)) = open_temp_bed(&geno);
+        // mark the genotypes of person 1 at SNP 2 and person 6 at SNP 3 as
+        // missing, and all the genotypes of SNP 4 as missing
+        let mut missing_genotypes = vec![(1, 2), (6, 3)];
+        missing_genotypes.extend((0..7).map(|i| (i, 4)));
+        set_missing(&bed_path, 7, &missing_genotypes);
+        let mut missing = Array::from_elem((7, 23), false);
+        for &(i, j) in missing_genotypes.iter() {
+            missing[[i, j]] = true;
+        }
+
+        let frequencies: Vec<f64> = (0..23)
+            .map(|i| {
+                let (sum, n) = (0..7)
+                    .filter(|&j| !missing[[j, i]])
+                    .fold((0., 0.), |(sum, n), j| {
+                        (sum + geno[[j, i]] as f64, n + 1.)
+                    });
+                sum / (2. * n)
+            })
+            .collect();
+        let expected: Vec<f64> = (0..7)
+            .map(|j| {
+                let (mut o, mut e, mut n) = (0., 0., 0.);
+                for i in (0..23).filter(|&i| !missing[[j, i]]) {
+                    let p = frequencies[i];
+                    n += 1.;
+                    e += 1. - 2. * p * (1. - p);
+                    if geno[[j, i]] != 1 {
+                        o += 1.;
+                    }
+                }
+                (o - e) / (n - e)
+            })
+            .collect();
+        for precompute in [false, true] {
+            if precompute {
+                bed.precompute_snp_stats().unwrap();
+            }
+            let f = bed.inbreeding_coefficients().unwrap();
+            assert_eq!(f.len(), 7);
+            for (a, e) in f.iter().zip(expected.iter()) {
+                assert!((*a as f64 - e).abs() < 1e-5);
+            }
+        }
+
+        // all the SNPs of a single homozygous person are monomorphic, so that
+        // the number of SNPs equals the expected homozygosity
+        let (bed, _bfile) = open_temp_bed(&array![[0u8, 2, 2]]);
+        let f = bed.inbreeding_coefficients().unwrap();
+        assert!(f[0].is_nan());
+    }
+
+    #[test]
+    fn test_pairwise_ibs() {
+        let geno = array![
+            [0u8, 1, 2, 2, 1],
+            [2, 1, 2, 0, 1],
+            [0, 0, 1, 2, 1],
+            [1, 1, 1, 1, 1],
+            [2, 2, 0, 0, 0]
+        ];
+        let (bed, (bed_path, _bim_path, _fam_path)) = open_temp_bed(&geno);
+        // mark the genotype of person 0 at SNP 4 as missing
+        set_missing(&bed_path, 5, &[(0, 4)]);
+        // the IBS per SNP of people 0 and 1 is 0, 1, 1, 0 and missing
+        assert_eq!(bed.pairwise_ibs(0, 1, None).unwrap(), 0.5);
+        assert_eq!(bed.pairwise_ibs(1, 0, None).unwrap(), 0.5);
+        // the IBS per SNP of people 2 and 4 is 0, 0, 0.5, 0 and 0.5
+        assert_eq!(bed.pairwise_ibs(2, 4, None).unwrap(), 0.2);
+        assert_eq!(bed.pairwise_ibs(3, 3, None).unwrap(), 1.);
+        let ibs = bed
+            .pairwise_ibs(
+                0,
+                2,
+                Some(OrderedIntegerSet::from_slice(&[[0, 1], [3, 4]])),
+            )
+            .unwrap();
+        assert!((ibs - 2.5 / 3.).abs() < 1e-6);
+        assert!(bed
+            .pairwise_ibs(0, 1, Some(OrderedIntegerSet::from_slice(&[[4, 4]])))
+            .unwrap()
+            .is_nan());
+        assert!(bed.pairwise_ibs(0, 5, None).is_err());
+        assert!(bed.pairwise_ibs(5, 0, None).is_err());
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = Fnv1a::new();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash(b"foobar"), 0x8594_4171_f739_67e8);
+        // hashing in pieces is the same as hashing at once
+        let mut hasher = Fnv1a::new();
+        hasher.write(b"foo");
+        hasher.write(b"bar");
+        assert_eq!(hasher.finish(), hash(b"foobar"));
+    }
+
+    #[test]
+    fn test_content_digest() {
+        let geno = Array::random((9, 5), Uniform::from(0..3));
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        let other_geno = Array::random((9, 4), Uniform::from(0..3));
+        let (other_bed_path, other_bim_path, other_fam_path) =
+            create_temp_geno_bfile(&other_geno);
+        let bfiles = [
+            (
+                bed_path.to_str().unwrap().to_string(),
+                bim_path.to_str().unwrap().to_string(),
+                fam_path.to_str().unwrap().to_string(),
+                PlinkSnpType::Additive,
+            ),
+            (
+                other_bed_path.to_str().unwrap().to_string(),
+                other_bim_path.to_str().unwrap().to_string(),
+                other_fam_path.to_str().unwrap().to_string(),
+                PlinkSnpType::Additive,
+            ),
+        ];
+        let bed = PlinkBed::new(&bfiles).unwrap();
+        let mut hasher = Fnv1a::new();
+        hasher.write(&std::fs::read(&bed_path).unwrap());
+        let digest = bed.content_digest(0).unwrap();
+        assert_eq!(digest, hasher.finish());
+        assert!(bed.content_digest(2).is_err());
+
+        let combined = bed.combined_content_digest().unwrap();
+        let mut hasher = Fnv1a::new();
+        hasher.write(&digest.to_le_bytes());
+        hasher.write(&bed.content_digest(1).unwrap().to_le_bytes());
+        assert_eq!(combined, hasher.finish());
+        let reversed =
+            PlinkBed::new(&[bfiles[1].clone(), bfiles[0].clone()]).unwrap();
+        assert_ne!(reversed.combined_content_digest().unwrap(), combined);
+
+        // flipping a single genotype changes the digest
+        let mut bytes = std::fs::read(&bed_path).unwrap();
+        bytes[NUM_MAGIC_BYTES] ^= 0b11;
+        std::fs::write(&bed_path, bytes).unwrap();
+        assert_ne!(bed.content_digest(0).unwrap(), digest);
+        assert_ne!(bed.combined_content_digest().unwrap(), combined);
+    }
+
+    #[test]
+    fn test_snp_types() {
+        let geno = Array::random((5, 3), Uniform::from(0..3));
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        let bfile = |snp_type| {
+            (
+                bed_path.to_str().unwrap().to_string(),
+                bim_path.to_str().unwrap().to_string(),
+                fam_path.to_str().unwrap().to_string(),
+                snp_type,
+            )
+        };
+        let bed = PlinkBed::new(&[
+            bfile(PlinkSnpType::Additive),
+            bfile(PlinkSnpType::Additive),
+        ])
+        .unwrap();
+        assert_eq!(bed.snp_types(), vec![PlinkSnpType::Additive; 6]);
+        assert!(!bed.has_mixed_snp_types());
+
+        let bed = PlinkBed::new(&[
+            bfile(PlinkSnpType::Dominance),
+            bfile(PlinkSnpType::Additive),
+        ])
+        .unwrap();
+        let mut expected = vec![PlinkSnpType::Dominance; 3];
+        expected.extend(vec![PlinkSnpType::Additive; 3]);
+        assert_eq!(bed.snp_types(), expected);
+        assert!(bed.has_mixed_snp_types());
+    }
+
+    #[test]
+    fn test_new_with_num_snps() {
+        let geno = Array::random((6, 5), Uniform::from(0..3));
+        let (bed_path, _bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        // the bim file is filtered down to 2 of the 5 SNPs
+        let mut bim = NamedTempFile::new().unwrap();
+        write_dummy_bim(&mut bim, 2).unwrap();
+        let bfiles = [(
+            bed_path.to_str().unwrap().to_string(),
+            bim.path().to_str().unwrap().to_string(),
+            fam_path.to_str().unwrap().to_string(),
+            PlinkSnpType::Additive,
+        )];
+        assert!(PlinkBed::new(&bfiles).is_err());
+        let bed = PlinkBed::new_with_num_snps(&bfiles, &[5]).unwrap();
+        assert_eq!(bed.total_num_snps(), 5);
+        assert_eq!(bed.get_genotype_matrix_u8(None, 255).unwrap(), geno);
+
+        // the size of the bed file is still checked
+        assert!(PlinkBed::new_with_num_snps(&bfiles, &[4]).is_err());
+        assert!(PlinkBed::new_with_num_snps(&bfiles, &[]).is_err());
+        assert!(PlinkBed::new_with_num_snps(&bfiles, &[0]).is_err());
+    }
+
+    #[test]
+    fn test_new_with_snp_type_file() {
+        let geno = Array::random((9, 6), Uniform::from(0..3));
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        let bfiles = [(
+            bed_path.to_str().unwrap().to_string(),
+            bim_path.to_str().unwrap().to_string(),
+            fam_path.to_str().unwrap().to_string(),
+        )];
+        let types = [
+            PlinkSnpType::Dominance,
+            PlinkSnpType::Additive,
+            PlinkSnpType::Additive,
+            PlinkSnpType::Dominance,
+            PlinkSnpType::Dominance,
+            PlinkSnpType::Additive,
+        ];
+        let mut type_file = NamedTempFile::new().unwrap();
+        writeln!(
+            type_file,
+            "dominance\nadditive\nAdditive\ndominance\ndominance\nadditive"
+        )
+        .unwrap();
+        let bed = PlinkBed::new_with_snp_type_file(&bfiles, &[type_file
+            .path()
+            .to_str()
+            .unwrap()
+            .to_string()])
+        .unwrap();
+
+        let (bed_path, bim_path, fam_path) = bfiles[0].clone();
+        let reference = PlinkBed::new(&[(
+            bed_path,
+            bim_path,
+            fam_path,
+            PlinkSnpType::Additive,
+        )])
+        .unwrap();
+        let expected: Vec<Array<f32, Ix2>> = reference
+            .col_chunk_iter_with_types(4, None, &types)
+            .unwrap()
+            .collect();
+        let chunks: Vec<Array<f32, Ix2>> =
+            bed.col_chunk_iter(4, None).collect();
+        assert_eq!(chunks, expected);
+        assert_eq!(bed.snp_types(), types.to_vec());
+        assert!(bed.has_mixed_snp_types());
+        assert!(!reference.has_mixed_snp_types());
+        let expected =
+            stack(Axis(1), &[expected[0].view(), expected[1].view()]).unwrap();
+        assert_eq!(bed.get_genotype_matrix(None, Some(4)).unwrap(), expected);
+        for i in 0..6 {
+            assert_eq!(bed.get_snp(i).unwrap(), expected.column(i));
+        }
+        let range = OrderedIntegerSet::from_slice(&[[0, 0], [3, 4]]);
+        assert_eq!(
+            bed.col_chunk_iter(3, Some(range)).next().unwrap(),
+            stack(Axis(1), &[
+                expected.slice(s![.., 0..1]),
+                expected.slice(s![.., 3..5])
+            ])
+            .unwrap()
+        );
+
+        let mut short_type_file = NamedTempFile::new().unwrap();
+        writeln!(short_type_file, "additive\ndominance").unwrap();
+        match PlinkBed::new_with_snp_type_file(&bfiles, &[short_type_file
+            .path()
+            .to_str()
+            .unwrap()
+            .to_string()])
+        {
+            Err(Error::BadFormat(why)) => assert!(why.contains("2 types")),
+            _ => panic!("expected a BadFormat error"),
+        }
+        assert!(PlinkBed::new_with_snp_type_file(&bfiles, &[]).is_err());
+    }
+
+    #[test]
+    fn test_progress() {
+        let reports = Mutex::new(Vec::new());
+        let record = |done: usize, total: usize| {
+            reports.lock().unwrap().push((done, total));
+        };
+        let reporter = ProgressReporter::new(&record, 123_457);
+        for _ in 0..123_457 {
+            reporter.add(1);
+        }
+        {
+            let reports = reports.lock().unwrap();
+            assert!(reports.len() <= MAX_PROGRESS_REPORTS);
+            assert_eq!(reports.last(), Some(&(123_457, 123_457)));
+            assert!(reports.windows(2).all(|w| w[0].0 < w[1].0));
+        }
+
+        let geno = Array::random((13, 10), Uniform::from(0..3));
+        let (mut bed, _bfile) = open_temp_bed(&geno);
+
+        reports.lock().unwrap().clear();
+        bed.get_genotype_matrix_with_progress(None, Some(3), &record)
+            .unwrap();
+        let mut done: Vec<usize> =
+            reports.lock().unwrap().iter().map(|r| r.0).collect();
+        done.sort_unstable();
+        assert_eq!(done, vec![3, 6, 9, 10]);
+
+        reports.lock().unwrap().clear();
+        let _ = bed.compute_grm_with_progress(Some(4), false, &record);
+        let mut done: Vec<usize> =
+            reports.lock().unwrap().iter().map(|r| r.0).collect();
+        done.sort_unstable();
+        assert_eq!(done, vec![4, 8, 10]);
+
+        reports.lock().unwrap().clear();
+        let bed_t_path = NamedTempFile::new().unwrap().into_temp_path();
+        bed.create_bed_t_with_progress(
+            0,
+            bed_t_path.to_str().unwrap(),
+            1,
+            &record,
+        )
+        .unwrap();
+        assert_eq!(*reports.lock().unwrap(), vec![
+            (4, 13),
+            (8, 13),
+            (12, 13),
+            (13, 13)
+        ]);
+    }
+
+    #[test]
+    fn test_random_snp_subset() {
+        let geno = Array::random((5, 40), Uniform::from(0..3));
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let subset = bed.random_snp_subset(10, 7).unwrap();
+        assert_eq!(subset.size(), 10);
+        assert!(subset.last().unwrap() < 40);
+        assert_eq!(bed.random_snp_subset(10, 7).unwrap(), subset);
+        assert_ne!(bed.random_snp_subset(10, 8).unwrap(), subset);
+        // pins the output so that changes to the generator are detected
+        assert_eq!(
+            bed.random_snp_subset(5, 2021)
+                .unwrap()
+                .to_iter()
+                .collect::<Vec<usize>>(),
+            vec![18, 22, 23, 31, 35]
+        );
+        assert_eq!(bed.col_chunk_iter(4, Some(subset)).count(), 3);
+
+        assert_eq!(bed.random_snp_subset(0, 7).unwrap().size(), 0);
+        assert_eq!(
+            bed.random_snp_subset(40, 7).unwrap(),
+            OrderedIntegerSet::from_slice(&[[0, 39]])
+        );
+        assert!(bed.random_snp_subset(41, 7).is_err());
+    }
+
+    #[test]
+    fn test_windowed_maf() {
+        let values = [1., 2., 3., 4., 5., 6., 7.];
+        assert_eq!(windowed_mean(&values, 3, 3), vec![2., 5., 7.]);
+        assert_eq!(windowed_mean(&values, 3, 2), vec![2., 4., 6.]);
+        assert_eq!(windowed_mean(&values, 2, 4), vec![1.5, 5.5]);
+        assert_eq!(windowed_mean(&values, 10, 1), vec![4.]);
+        assert!(windowed_mean(&[], 3, 1).is_empty());
+
+        let geno = array![[0, 1, 2], [2, 1, 0], [1, 0, 0]];
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let maf = bed.windowed_maf(2, 1).unwrap();
+        assert_eq!(maf.len(), 2);
+        assert!((maf[0] - 5. / 12.).abs() < 1e-6);
+        assert!((maf[1] - 1. / 3.).abs() < 1e-6);
+        assert!(bed.windowed_maf(0, 1).is_err());
+        assert!(bed.windowed_maf(2, 0).is_err());
+    }
+
+    #[test]
+    fn test_ld_matrix() {
+        // SNP 1 duplicates SNP 0, SNP 3 is SNP 2 flipped, and SNP 4 is
+        // monomorphic
+        let geno = array![
+            [0, 0, 2, 0, 1, 1],
+            [1, 1, 0, 2, 1, 2],
+            [2, 2, 1, 1, 1, 0],
+            [0, 0, 2, 0, 1, 2],
+            [1, 1, 1, 1, 1, 0]
+        ];
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let r2 = |x: usize, y: usize| -> f32 {
+            let x = geno.column(x).mapv(|g| g as f64);
+            let y = geno.column(y).mapv(|g| g as f64);
+            let x = &x - x.sum() / x.len() as f64;
+            let y = &y - y.sum() / y.len() as f64;
+            let cov = x.dot(&y);
+            (cov * cov / (x.dot(&x) * y.dot(&y))) as f32
+        };
+        let set = OrderedIntegerSet::from_slice(&[[0, 2], [4, 5]]);
+        let set_indices = [0, 1, 2, 4, 5];
+        // the column blocks are decoded once with the budget of 1000 bytes,
+        // and by each band on or above them with the budget of 0
+        for &memory_budget_bytes in [0, 1000].iter() {
+            for block_num_snps in 1..=6 {
+                let ld = bed
+                    .ld_matrix_in_blocks(
+                        &set,
+                        block_num_snps,
+                        memory_budget_bytes,
+                    )
+                    .unwrap();
+                assert_eq!(ld.dim(), (5, 5));
+                for (i, &x) in set_indices.iter().enumerate() {
+                    for (j, &y) in set_indices.iter().enumerate() {
+                        if x == 4 || y == 4 {
+                            assert!(ld[[i, j]].is_nan());
+                        } else {
+                            assert!((ld[[i, j]] - r2(x, y)).abs() < 1e-5);
+                            assert_eq!(ld[[i, j]], ld[[j, i]]);
+                        }
+                    }
+                }
+                assert!((ld[[0, 1]] - 1.).abs() < 1e-5);
+            }
+        }
+        let ld = bed
+            .ld_matrix(&OrderedIntegerSet::from_slice(&[[2, 3]]))
+            .unwrap();
+        assert_arr_almost_eq_f32(&ld, &array![[1., 1.], [1., 1.]], 1e-5);
+    }
+
+    #[test]
+    fn test_snp_covariance() {
+        let mut geno = Array::random((9, 5), Uniform::from(0..3));
+        geno.column_mut(4).fill(1);
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        // mark the genotypes of person 2 at SNP 0 and person 8 at SNP 4 as
+        // missing
+        set_missing(&bed_path, 9, &[(2, 0), (8, 4)]);
+        let bfile = (
+            bed_path.to_str().unwrap().to_string(),
+            bim_path.to_str().unwrap().to_string(),
+            fam_path.to_str().unwrap().to_string(),
+        );
+        let mut bed = PlinkBed::new(&[
+            (
+                bfile.0.clone(),
+                bfile.1.clone(),
+                bfile.2.clone(),
+                PlinkSnpType::Additive,
+            ),
+            (bfile.0, bfile.1, bfile.2, PlinkSnpType::Dominance),
+        ])
+        .unwrap();
+        let set = OrderedIntegerSet::from_slice(&[[0, 1], [3, 6], [9, 9]]);
+        bed.set_missing_strategy(MissingStrategy::MeanImpute);
+        let x = bed
+            .get_genotype_matrix(Some(set.clone()), None)
+            .unwrap()
+            .mapv(|g| g as f64);
+        bed.set_missing_strategy(MissingStrategy::Zero);
+        let n = 9.;
+        let mut centered = x.clone();
+        let mut stds = Vec::new();
+        for mut col in centered.gencolumns_mut() {
+            let mean = col.sum() / n;
+            col.mapv_inplace(|g| g - mean);
+            stds.push((col.dot(&col) / n).sqrt());
+        }
+        let expected_cov = centered.t().dot(&centered) / n;
+        for standardize in [false, true] {
+            for block_num_people in 1..=10 {
+                let cov = bed
+                    .snp_covariance_in_blocks(
+                        Some(set.clone()),
+                        standardize,
+                        block_num_people,
+                    )
+                    .unwrap();
+                assert_eq!(cov.dim(), (7, 7));
+                for i in 0..7 {
+                    for j in 0..7 {
+                        if !standardize {
+                            assert!(
+                                (cov[[i, j]] as f64 - expected_cov[[i, j]])
+                                    .abs()
+                                    < 1e-4
+                            );
+                        } else if stds[i] < 1e-9 || stds[j] < 1e-9 {
+                            assert!(cov[[i, j]].is_nan());
+                        } else {
+                            let expected =
+                                expected_cov[[i, j]] / (stds[i] * stds[j]);
+                            assert!(
+                                (cov[[i, j]] as f64 - expected).abs() < 1e-4
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        // the cached counts of all the SNPs give the same covariance
+        let cov = bed.snp_covariance(Some(set.clone()), true).unwrap();
+        bed.precompute_snp_stats().unwrap();
+        assert_eq!(
+            bed.snp_covariance(Some(set), true)
+                .unwrap()
+                .mapv(|x| x.to_bits()),
+            cov.mapv(|x| x.to_bits())
+        );
+        assert_eq!(bed.snp_covariance(None, false).unwrap().dim(), (10, 10));
+        assert!(bed
+            .snp_covariance(
+                Some(OrderedIntegerSet::from_slice(&[[8, 10]])),
+                false
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_ld_prune() {
+        // SNP 1 duplicates SNP 0, SNP 3 is SNP 2 flipped, and SNP 4 is
+        // monomorphic
+        let geno = array![
+            [0, 0, 2, 0, 1, 1],
+            [1, 1, 0, 2, 1, 2],
+            [2, 2, 1, 1, 1, 0],
+            [0, 0, 2, 0, 1, 2],
+            [1, 1, 1, 1, 1, 0]
+        ];
+        let (bed, _bfile) = open_temp_bed(&geno);
+        assert_eq!(
+            bed.ld_prune(6, 1, 0.99).unwrap(),
+            OrderedIntegerSet::from_slice(&[[0, 0], [2, 2], [4, 5]])
+        );
+        // the duplicated SNPs are never in the same window
+        assert_eq!(
+            bed.ld_prune(1, 1, 0.99).unwrap(),
+            OrderedIntegerSet::from_slice(&[[0, 5]])
+        );
+        assert_eq!(
+            bed.ld_prune(2, 2, 0.99).unwrap(),
+            OrderedIntegerSet::from_slice(&[[0, 0], [2, 2], [4, 5]])
+        );
+        assert_eq!(
+            bed.ld_prune(3, 3, 0.99).unwrap(),
+            OrderedIntegerSet::from_slice(&[[0, 0], [2, 5]])
+        );
+        // every polymorphic pair is removed with a zero threshold
+        assert_eq!(
+            bed.ld_prune(6, 6, 0.).unwrap(),
+            OrderedIntegerSet::from_slice(&[[0, 0], [4, 4]])
+        );
+        assert!(bed.ld_prune(0, 1, 0.99).is_err());
+        assert!(bed.ld_prune(2, 0, 0.99).is_err());
+    }
+
+    #[test]
+    fn test_get_snp() {
+        let geno = Array::random((13, 11), Uniform::from(0..3));
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        let bed_path = bed_path.to_str().unwrap().to_string();
+        let bim_path = bim_path.to_str().unwrap().to_string();
+        let fam_path = fam_path.to_str().unwrap().to_string();
+        let bed = PlinkBed::new(&[
+            (
+                bed_path.clone(),
+                bim_path.clone(),
+                fam_path.clone(),
+                PlinkSnpType::Additive,
+            ),
+            (bed_path, bim_path, fam_path, PlinkSnpType::Dominance),
+        ])
+        .unwrap();
+        let geno_f32 = geno.mapv(|x| x as f32);
+        let dominance =
+            convert_geno_arr_to_dominance_representation(geno_f32.clone());
+        for i in 0..11 {
+            assert_eq!(bed.get_snp(i).unwrap(), geno_f32.column(i));
+            assert_eq!(bed.get_snp(11 + i).unwrap(), dominance.column(i));
+        }
+        match bed.get_snp(22) {
+            Err(Error::SnpIndexOutOfRange {
+                index,
+                total,
+            }) => assert_eq!((index, total), (22, 22)),
+            _ => panic!("expected SnpIndexOutOfRange"),
+        }
+    }
+
+    #[test]
+    fn test_packed_snp() {
+        let geno = Array::random((13, 5), Uniform::from(0..3));
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        let bed_path = bed_path.to_str().unwrap().to_string();
+        let bim_path = bim_path.to_str().unwrap().to_string();
+        let fam_path = fam_path.to_str().unwrap().to_string();
+        let bed = PlinkBed::new(&[
+            (
+                bed_path.clone(),
+                bim_path.clone(),
+                fam_path.clone(),
+                PlinkSnpType::Additive,
+            ),
+            (
+                bed_path.clone(),
+                bim_path,
+                fam_path,
+                PlinkSnpType::Dominance,
+            ),
+        ])
+        .unwrap();
+        let bytes = std::fs::read(&bed_path).unwrap();
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(13);
+        for i in 0..10 {
+            let start = NUM_MAGIC_BYTES + (i % 5) * num_bytes_per_snp;
+            assert_eq!(
+                bed.packed_snp(i).unwrap(),
+                bytes[start..start + num_bytes_per_snp].to_vec()
+            );
+        }
+        assert!(bed.packed_snp(10).is_err());
+
+        let out_path = NamedTempFile::new().unwrap().into_temp_path();
+        let mut writer =
+            BedWriter::create(out_path.to_str().unwrap(), 13).unwrap();
+        for i in 0..5 {
+            writer.push_packed_snp(&bed.packed_snp(i).unwrap()).unwrap();
+        }
+        assert!(writer.push_packed_snp(&[0u8; 3]).is_err());
+        assert_eq!(writer.num_snps_written(), 5);
+        writer.finish().unwrap();
+        assert_eq!(std::fs::read(&out_path).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_sample_ids() {
+        let geno = array![[0, 1], [2, 1], [1, 0]];
+        let (bed_path, bim_path, _) = create_temp_geno_bfile(&geno);
+        let write_fam = |content: &str| {
+            let mut fam = NamedTempFile::new().unwrap();
+            fam.write_all(content.as_bytes()).unwrap();
+            fam.into_temp_path()
+        };
+        let fam_1 =
+            write_fam("f1 i1 0 0 1 -9\nf1 i2 0 0 2 -9\nf2 i3 0 0 1 -9\n");
+        let fam_2 =
+            write_fam("f1 i1 0 0 1 -9\nf1 i2 0 0 2 -9\nf2 i3 0 0 1 -9\n");
+        let fam_3 =
+            write_fam("f1 i1 0 0 1 -9\nf1 i4 0 0 2 -9\nf2 i3 0 0 1 -9\n");
+        let bfile = |fam: &TempPath| {
+            (
+                bed_path.to_str().unwrap().to_string(),
+                bim_path.to_str().unwrap().to_string(),
+                fam.to_str().unwrap().to_string(),
+                PlinkSnpType::Additive,
+            )
+        };
+        let expected = vec![
+            ("f1".to_string(), "i1".to_string()),
+            ("f1".to_string(), "i2".to_string()),
+            ("f2".to_string(), "i3".to_string()),
+        ];
+        assert_eq!(
+            PlinkBed::new(&[bfile(&fam_1)])
+                .unwrap()
+                .sample_ids()
+                .unwrap(),
+            expected
+        );
+        assert_eq!(
+            PlinkBed::new(&[bfile(&fam_1), bfile(&fam_2)])
+                .unwrap()
+                .sample_ids()
+                .unwrap(),
+            expected
+        );
+        assert!(PlinkBed::new(&[bfile(&fam_1), bfile(&fam_3)])
+            .unwrap()
+            .sample_ids()
+            .is_err());
+    }
+
+    #[test]
+    fn test_decode_snp_bytes() {
+        // the people are encoded from the lowest two bits, where 00 -> 2,
+        // 01 -> missing, 10 -> 1, 11 -> 0, and the last byte has two padding
+        // slots
+        let bytes = [0b11_01_10_00, 0b11_11_00_10];
+        assert_eq!(decode_snp_bytes(&bytes, 6), vec![2., 1., 0., 0., 1., 2.]);
+        assert_eq!(decode_snp_bytes_u8(&bytes, 6, 9), vec![2, 1, 9, 0, 1, 2]);
+        assert_eq!(decode_snp_bytes_u8(&bytes, 8, 9), vec![
+            2, 1, 9, 0, 1, 2, 0, 0
+        ]);
+        assert!(decode_snp_bytes(&[], 0).is_empty());
+
+        let geno = Array::random((9, 5), Uniform::from(0..3));
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let num_bytes_per_snp = 3;
+        let byte_iter = bed
+            .byte_chunk_iter(
+                0,
+                NUM_MAGIC_BYTES,
+                NUM_MAGIC_BYTES + num_bytes_per_snp * 5,
+                num_bytes_per_snp,
+            )
+            .unwrap();
+        for (i, bytes) in byte_iter.enumerate() {
+            assert_eq!(
+                decode_snp_bytes_u8(&bytes, 9, 9),
+                geno.column(i).to_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_num_people_last_byte() {
+        assert_eq!(get_num_people_last_byte(0), None);
+        assert_eq!(get_num_people_last_byte(1), Some(1));
+        assert_eq!(get_num_people_last_byte(3), Some(3));
+        assert_eq!(get_num_people_last_byte(4), Some(4));
+        assert_eq!(get_num_people_last_byte(9), Some(1));
+        assert_eq!(get_num_people_last_byte(12), Some(4));
+    }
+
+    #[test]
+    fn test_gzipped_bim_fam() {
+        let geno = Array::random((7, 5), Uniform::from(0..3));
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        let gzip = |path: &TempPath| {
+            let gz = NamedTempFile::new().unwrap();
+            let mut encoder = GzEncoder::new(gz, Compression::default());
+            encoder.write_all(&std::fs::read(path).unwrap()).unwrap();
+            encoder.finish().unwrap().into_temp_path()
+        };
+        let bim_gz_path = gzip(&bim_path);
+        let fam_gz_path = gzip(&fam_path);
+        let bed = PlinkBed::new(&[(
+            bed_path.to_str().unwrap().to_string(),
+            bim_gz_path.to_str().unwrap().to_string(),
+            fam_gz_path.to_str().unwrap().to_string(),
+            PlinkSnpType::Additive,
+        )])
+        .unwrap();
+        assert_eq!(bed.total_num_snps(), 5);
+        assert_eq!(bed.num_people, 7);
+        assert_eq!(
+            bed.get_genotype_matrix(None, None).unwrap(),
+            geno.mapv(|x| x as f32)
+        );
+    }
+
+    #[test]
+    fn test_range_across_files() {
+        let num_people = 9;
+        let (num_snps_1, num_snps_2) = (7, 5);
+        let geno_1 =
+            Array::random((num_people, num_snps_1), Uniform::from(0..3));
+        let geno_2 =
+            Array::random((num_people, num_snps_2), Uniform::from(0..3));
+        let (bed_1, bim_1, fam_1) = create_temp_geno_bfile(&geno_1);
+        let (bed_2, bim_2, fam_2) = create_temp_geno_bfile(&geno_2);
+        let bed = PlinkBed::new(&[
+            (
+                bed_1.to_str().unwrap().to_string(),
+                bim_1.to_str().unwrap().to_string(),
+                fam_1.to_str().unwrap().to_string(),
+                PlinkSnpType::Additive,
+            ),
+            (
+                bed_2.to_str().unwrap().to_string(),
+                bim_2.to_str().unwrap().to_string(),
+                fam_2.to_str().unwrap().to_string(),
+                PlinkSnpType::Additive,
+            ),
+        ])
+        .unwrap();
+        let geno = stack(Axis(1), &[geno_1.view(), geno_2.view()])
+            .unwrap()
+            .mapv(|x| x as f32);
+
+        let range =
+            OrderedIntegerSet::from_slice(&[[num_snps_1 - 2, num_snps_1 + 2]]);
+        let expected = geno.slice(s![.., num_snps_1 - 2..num_snps_1 + 3]);
+        assert_eq!(
+            bed.get_genotype_matrix(Some(range.clone()), None).unwrap(),
+            expected
+        );
+        for chunk_size in 1..=6 {
+            assert_eq!(
+                bed.get_genotype_matrix(Some(range.clone()), Some(chunk_size))
+                    .unwrap(),
+                expected
+            );
+            let chunks: Vec<Array<f32, Ix2>> = bed
+                .col_chunk_iter(chunk_size, Some(range.clone()))
+                .collect();
+            assert_eq!(
+                stack(
+                    Axis(1),
+                    &chunks.iter().map(|c| c.view()).collect::<Vec<_>>()
+                )
+                .unwrap(),
+                expected
+            );
+        }
+
+        // a range with gaps on both sides of the boundary
+        let range = OrderedIntegerSet::from_slice(&[
+            [1, 2],
+            [num_snps_1 - 1, num_snps_1],
+            [num_snps_1 + 3, num_snps_1 + 4],
+        ]);
+        let snp_indices = [1, 2, 6, 7, 10, 11];
+        let expected = stack(
+            Axis(1),
+            &snp_indices
+                .iter()
+                .map(|&i| geno.slice(s![.., i..i + 1]))
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        for chunk_size in 1..=7 {
+            assert_eq!(
+                bed.get_genotype_matrix(Some(range.clone()), Some(chunk_size))
+                    .unwrap(),
+                expected
+            );
         }
     }
 
-    fn read_snp_bytes(
-        &mut self,
-        snp_index: usize,
-        mut snp_bytes_buf: &mut Vec<u8>,
-    ) -> Result<PlinkSnpType, Error> {
-        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
-        match self.file_snp_indexer.get_file_snp_index(snp_index) {
-            Some((file_index, snp_index_within_file, snp_type)) => {
-                if let Some((last_file_index, last_snp_index_within_file)) =
-                    self.last_read_file_snp_index
-                {
-                    if file_index == last_file_index {
-                        let snp_index_gap =
-                            snp_index_within_file - last_snp_index_within_file;
-                        if snp_index_gap > 1 {
-                            self.buf[file_index]
-                                .seek_relative(
-                                    ((snp_index_gap - 1) * num_bytes_per_snp)
-                                        as i64,
-                                )
-                                .unwrap();
-                        }
-                        self.buf[file_index].read_exact(&mut snp_bytes_buf)?;
-                        self.last_read_file_snp_index =
-                            Some((file_index, snp_index_within_file));
-                        return Ok(snp_type);
-                    }
-                }
-                self.seek_to_snp(snp_index)?;
-                self.buf[file_index].read_exact(&mut snp_bytes_buf)?;
-                self.last_read_file_snp_index =
-                    Some((file_index, snp_index_within_file));
-                Ok(snp_type)
+    #[test]
+    fn test_create_bed_permuted() {
+        let geno = Array::random((9, 6), Uniform::from(0..3));
+        let (_, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        let row_perm = [3, 0, 8, 1, 2, 7, 5, 4, 6];
+        let col_perm = [5, 2, 0, 4, 1, 3];
+        let bed_path = NamedTempFile::new().unwrap().into_temp_path();
+        let bed_path_str = bed_path.to_str().unwrap().to_string();
+        PlinkBed::create_bed_permuted(
+            &geno,
+            &row_perm,
+            &col_perm,
+            &bed_path_str,
+        )
+        .unwrap();
+        let bed = PlinkBed::new(&[(
+            bed_path_str.clone(),
+            bim_path.to_str().unwrap().to_string(),
+            fam_path.to_str().unwrap().to_string(),
+            PlinkSnpType::Additive,
+        )])
+        .unwrap();
+        let actual = bed.get_genotype_matrix_u8(None, 255).unwrap();
+        for (r, &i) in row_perm.iter().enumerate() {
+            for (c, &j) in col_perm.iter().enumerate() {
+                assert_eq!(actual[[r, c]], geno[[i, j]]);
             }
-            None => Err(Error::Generic(format!(
-                "SNP index {} out of range",
-                snp_index
-            ))),
         }
-    }
 
-    /// indices are 0 based
-    #[inline]
-    fn clone_with_range(
-        &self,
-        range: OrderedIntegerSet<usize>,
-    ) -> PlinkColChunkIter {
-        PlinkColChunkIter::new(
-            self.file_num_snps.clone(),
-            range,
-            self.num_snps_per_iter,
-            self.num_people,
-            self.bed_path_list.clone(),
+        // invalid permutations are rejected before writing
+        let untouched_path = NamedTempFile::new().unwrap().into_temp_path();
+        let untouched_path_str = untouched_path.to_str().unwrap();
+        for (rows, cols) in [
+            (&row_perm[..8], &col_perm[..]),
+            (&row_perm[..], &[5, 2, 0, 4, 1, 1][..]),
+            (&row_perm[..], &[5, 2, 0, 4, 1, 6][..]),
+        ]
+        .iter()
+        {
+            assert!(PlinkBed::create_bed_permuted(
+                &geno,
+                rows,
+                cols,
+                untouched_path_str
+            )
+            .is_err());
+            assert!(std::fs::read(untouched_path_str).unwrap().is_empty());
+        }
+
+        // out-of-range genotypes are rejected
+        assert!(PlinkBed::create_bed_permuted(
+            &array![[3, 0], [1, 2]],
+            &[0, 1],
+            &[0, 1],
+            untouched_path_str
         )
+        .is_err());
     }
 
-    fn read_chunk(&mut self, chunk_size: usize) -> Array<f32, Ix2> {
-        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(self.num_people);
-        let num_people_last_byte =
-            get_num_people_last_byte(self.num_people).unwrap_or(0);
-
-        let snp_indices = self
-            .range
-            .slice(self.range_cursor..self.range_cursor + chunk_size);
-        let actual_chunk_size = snp_indices.size();
-        self.range_cursor += actual_chunk_size;
-
-        let mut v = Vec::with_capacity(self.num_people * actual_chunk_size);
-        let mut snp_bytes = vec![0u8; num_bytes_per_snp];
-        for index in snp_indices.to_iter() {
-            let snp_type = self.read_snp_bytes(index, &mut snp_bytes).unwrap();
-            let mut snp_vec = Vec::with_capacity(self.num_people);
-            for i in 0..num_bytes_per_snp - 1 {
-                snp_vec.push(lowest_two_bits_to_geno(snp_bytes[i]) as f32);
-                snp_vec.push(lowest_two_bits_to_geno(snp_bytes[i] >> 2) as f32);
-                snp_vec.push(lowest_two_bits_to_geno(snp_bytes[i] >> 4) as f32);
-                snp_vec.push(lowest_two_bits_to_geno(snp_bytes[i] >> 6) as f32);
-            }
-            // last byte
-            for k in 0..num_people_last_byte {
-                snp_vec.push(lowest_two_bits_to_geno(
-                    snp_bytes[num_bytes_per_snp - 1] >> (k << 1),
-                ) as f32);
-            }
-            v.append(&mut match snp_type {
-                PlinkSnpType::Additive => snp_vec,
-                PlinkSnpType::Dominance => {
-                    convert_geno_vec_to_dominance_representation(snp_vec)
-                }
-            });
+    #[test]
+    fn test_allele_frequency_order() {
+        let geno = Array::random((11, 53), Uniform::from(0..3));
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let range = OrderedIntegerSet::from_slice(&[
+            [0, 2],
+            [7, 7],
+            [10, 24],
+            [30, 31],
+            [40, 52],
+        ]);
+        let frequency = |i: usize| {
+            geno.column(i).iter().map(|&x| x as f32).sum::<f32>() / 22.
+        };
+        let expected: Vec<f32> = range.to_iter().map(frequency).collect();
+        for chunk_size in [1, 2, 3, 5, 8, 40, 100].iter() {
+            assert_eq!(
+                bed.get_allele_frequencies_in_range(
+                    Some(range.clone()),
+                    *chunk_size
+                )
+                .unwrap(),
+                expected
+            );
+            assert_eq!(
+                bed.get_allele_frequencies(Some(*chunk_size)).unwrap(),
+                (0..53).map(frequency).collect::<Vec<f32>>()
+            );
         }
-        Array::from_shape_vec(
-            (self.num_people, actual_chunk_size).strides((1, self.num_people)),
-            v,
-        )
-        .unwrap()
     }
-}
 
-fn convert_geno_vec_to_dominance_representation(
-    mut geno_vec: Vec<f32>,
-) -> Vec<f32> {
-    let num_people = geno_vec.len();
-    let double_num_people = (2 * num_people) as f32;
-    let p = sum_f32(geno_vec.iter()) / double_num_people;
-    let hetero = 2. * p;
-    let homo_minor = 4. * p - 2.;
-    for i in 0..num_people {
-        geno_vec[i] = match geno_vec[i] as u8 {
-            2 => homo_minor,
-            1 => hetero,
-            _ => 0.,
+    #[test]
+    fn test_snp_phenotype_correlations() {
+        // the third SNP is monomorphic
+        let geno =
+            array![[0u8, 2, 1, 1], [1, 1, 1, 2], [2, 0, 1, 0], [1, 2, 1, 1], [
+                0, 1, 1, 2
+            ]];
+        let (bed, (bed_path, _bim_path, _fam_path)) = open_temp_bed(&geno);
+        // mark the genotype of the fourth person in the first SNP as missing
+        set_missing(&bed_path, 5, &[(3, 0)]);
+        let phenotype = [1.5, 0.2, -1., 7., 2.];
+        let pearson = |x: &[f32], y: &[f32]| {
+            let n = x.len() as f32;
+            let mean_x = x.iter().sum::<f32>() / n;
+            let mean_y = y.iter().sum::<f32>() / n;
+            let cov: f32 = x
+                .iter()
+                .zip(y.iter())
+                .map(|(a, b)| (a - mean_x) * (b - mean_y))
+                .sum();
+            let var_x: f32 = x.iter().map(|a| (a - mean_x).powi(2)).sum();
+            let var_y: f32 = y.iter().map(|b| (b - mean_y).powi(2)).sum();
+            cov / (var_x * var_y).sqrt()
         };
+        let expected = [
+            pearson(&[0., 1., 2., 0.], &[1.5, 0.2, -1., 2.]),
+            pearson(&[2., 1., 0., 2., 1.], &phenotype),
+            f32::NAN,
+            pearson(&[1., 2., 0., 1., 2.], &phenotype),
+        ];
+        for chunk_size in [None, Some(1), Some(3)].iter() {
+            let correlations = bed
+                .snp_phenotype_correlations(&phenotype, *chunk_size)
+                .unwrap();
+            assert_eq!(correlations.len(), 4);
+            for (r, e) in correlations.iter().zip(expected.iter()) {
+                if e.is_nan() {
+                    assert!(r.is_nan());
+                } else {
+                    assert!((r - e).abs() < 1e-5);
+                }
+            }
+        }
+        assert!(bed
+            .snp_phenotype_correlations(&phenotype[1..], None)
+            .is_err());
     }
-    geno_vec
-}
 
-pub fn convert_geno_arr_to_dominance_representation(
-    mut geno_arr: Array<f32, Ix2>,
-) -> Array<f32, Ix2> {
-    let num_people = geno_arr.dim().0;
-    let double_num_people = (2 * num_people) as f32;
-    for mut col in geno_arr.axis_iter_mut(Axis(1)) {
-        let p = sum_f32(col.iter()) / double_num_people;
-        let hetero = 2. * p;
-        let homo_minor = 4. * p - 2.;
-        for i in 0..num_people {
-            col[i] = match col[i] as u8 {
-                2 => homo_minor,
-                1 => hetero,
-                _ => 0.,
-            };
+    #[test]
+    fn test_annotated_col_chunk_iter() {
+        let geno = Array::random((7, 13), Uniform::from(0..3));
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let bim: Vec<BimRecord> = (0..13)
+            .map(|i| BimRecord {
+                chrom: "1".to_string(),
+                variant_id: format!("rs{}", i),
+                cm_position: 0.,
+                coordinate: i as u64 * 10,
+                allele_1: "A".to_string(),
+                allele_2: "C".to_string(),
+            })
+            .collect();
+        let geno_f32 = geno.mapv(|x| x as f32);
+        let range = OrderedIntegerSet::from_slice(&[[0, 1], [4, 8], [11, 12]]);
+        for chunk_size in 1..=10 {
+            let mut snp_indices = range.to_iter();
+            for (chunk, records) in bed
+                .annotated_col_chunk_iter(&bim, chunk_size, Some(range.clone()))
+                .unwrap()
+            {
+                assert_eq!(chunk.dim().1, records.len());
+                for (col, record) in chunk.gencolumns().into_iter().zip(records)
+                {
+                    let i = snp_indices.next().unwrap();
+                    assert_eq!(record.variant_id, format!("rs{}", i));
+                    assert_eq!(col, geno_f32.column(i));
+                }
+            }
+            assert_eq!(snp_indices.next(), None);
         }
+        let num_records: usize = bed
+            .annotated_col_chunk_iter(&bim, 4, None)
+            .unwrap()
+            .map(|(_, records)| records.len())
+            .sum();
+        assert_eq!(num_records, 13);
+        assert!(bed.annotated_col_chunk_iter(&bim[1..], 4, None).is_err());
     }
-    geno_arr
-}
-
-impl IntoParallelIterator for PlinkColChunkIter {
-    type Item = <PlinkColChunkParallelIter as ParallelIterator>::Item;
-    type Iter = PlinkColChunkParallelIter;
 
-    fn into_par_iter(self) -> Self::Iter {
-        PlinkColChunkParallelIter {
-            iter: self,
+    #[test]
+    fn test_snp_flags() {
+        // the third SNP is all heterozygous and hence polymorphic, and the
+        // fourth SNP will be all missing
+        let geno =
+            array![[0u8, 2, 1, 1], [1, 1, 1, 2], [2, 0, 1, 0], [1, 2, 1, 1], [
+                0, 0, 1, 2
+            ]];
+        let (bed, (bed_path, _bim_path, _fam_path)) = open_temp_bed(&geno);
+        // mark the genotype of the fourth person in the first SNP and all the
+        // genotypes in the fourth SNP as missing
+        let mut missing_genotypes = vec![(3, 0)];
+        missing_genotypes.extend((0..5).map(|i| (i, 3)));
+        set_missing(&bed_path, 5, &missing_genotypes);
+        let counts = |num_hom_first, num_het, num_hom_second, num_missing| {
+            GenotypeCounts {
+                num_hom_first,
+                num_het,
+                num_hom_second,
+                num_missing,
+            }
+        };
+        let expected_counts = vec![
+            counts(1, 1, 2, 1),
+            counts(2, 1, 2, 0),
+            counts(0, 5, 0, 0),
+            counts(0, 0, 0, 5),
+        ];
+        for chunk_size in [None, Some(1), Some(3)].iter() {
+            assert_eq!(
+                bed.genotype_counts(*chunk_size).unwrap(),
+                expected_counts
+            );
+            let flags = bed.snp_flags(*chunk_size).unwrap();
+            assert_eq!(
+                flags
+                    .iter()
+                    .map(|f| (f.all_missing, f.monomorphic))
+                    .collect::<Vec<(bool, bool)>>(),
+                vec![
+                    (false, false),
+                    (false, false),
+                    (false, false),
+                    (true, true)
+                ]
+            );
+            assert!((flags[0].maf - 3. / 8.).abs() < 1e-6);
+            assert!((flags[1].maf - 0.5).abs() < 1e-6);
+            assert!((flags[2].maf - 0.5).abs() < 1e-6);
+            assert!(flags[3].maf.is_nan());
         }
+        let summary = bed.dataset_summary().unwrap();
+        assert_eq!(
+            (summary.num_people, summary.num_snps, summary.num_files),
+            (5, 4, 1)
+        );
+        assert!((summary.overall_call_rate - 14. / 20.).abs() < 1e-6);
+        assert!((summary.mean_maf - (3. / 8. + 1.) / 3.).abs() < 1e-6);
     }
-}
 
-impl Iterator for PlinkColChunkIter {
-    type Item = Array<f32, Ix2>;
+    #[test]
+    fn test_concat_beds() {
+        let geno = Array::random((7, 10), Uniform::from(0..3));
+        let dir = tempdir().unwrap();
+        let out_prefix = dir.path().join("shard");
+        let bed_paths = PlinkBed::create_sharded_bed(
+            &geno,
+            out_prefix.to_str().unwrap(),
+            4,
+        )
+        .unwrap();
+        let inputs: Vec<&str> = bed_paths.iter().map(|p| p.as_str()).collect();
+        let concat_path = dir.path().join("concat.bed");
+        let concat_path = concat_path.to_str().unwrap();
+        PlinkBed::concat_beds(&inputs, 7, concat_path).unwrap();
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.range_cursor >= self.num_snps_in_range {
-            return None;
-        }
-        let chunk_size = min(
-            self.num_snps_per_iter,
-            self.num_snps_in_range - self.range_cursor,
+        let expected_path = dir.path().join("expected.bed");
+        let expected_path = expected_path.to_str().unwrap();
+        PlinkBed::create_bed(&geno, expected_path).unwrap();
+        assert_eq!(
+            std::fs::read(concat_path).unwrap(),
+            std::fs::read(expected_path).unwrap()
         );
-        Some(self.read_chunk(chunk_size))
-    }
-}
 
-impl ExactSizeIterator for PlinkColChunkIter {
-    fn len(&self) -> usize {
-        usize_div_ceil(
-            self.num_snps_in_range - self.range_cursor,
-            self.num_snps_per_iter,
+        // a bed file of a different number of bytes per SNP
+        let other_path = dir.path().join("other.bed");
+        let other_path = other_path.to_str().unwrap();
+        PlinkBed::create_bed(
+            &Array::random((9, 3), Uniform::from(0..3)),
+            other_path,
+        )
+        .unwrap();
+        assert!(PlinkBed::concat_beds(
+            &[inputs[0], other_path],
+            7,
+            concat_path
         )
+        .is_err());
+        assert!(PlinkBed::concat_beds(&inputs, 0, concat_path).is_err());
     }
-}
 
-impl DoubleEndedIterator for PlinkColChunkIter {
-    fn next_back(&mut self) -> Option<Self::Item> {
-        if self.range_cursor >= self.num_snps_in_range {
-            return None;
+    #[test]
+    fn test_split_by_chromosome() {
+        let geno = Array::random((6, 7), Uniform::from(0..3));
+        let (bed, _bfile) = open_temp_bed(&geno);
+        // chromosome 2 is not contiguous
+        let bim: Vec<BimRecord> = ["1", "1", "2", "2", "X", "2", "X"]
+            .iter()
+            .enumerate()
+            .map(|(i, chrom)| {
+                BimRecord::from_line(&format!(
+                    "{} rs{} 0 {} A G",
+                    chrom,
+                    i,
+                    100 * i
+                ))
+                .unwrap()
+            })
+            .collect();
+        let dir = tempdir().unwrap();
+        let out_prefix = dir.path().join("split");
+        let out_prefix = out_prefix.to_str().unwrap();
+        let prefixes = bed.split_by_chromosome(&bim, out_prefix).unwrap();
+        assert_eq!(
+            prefixes,
+            ["1", "2", "X"]
+                .iter()
+                .map(|c| format!("{}.{}", out_prefix, c))
+                .collect::<Vec<String>>()
+        );
+        for (prefix, snp_indices) in prefixes
+            .iter()
+            .zip([vec![0, 1], vec![2, 3, 5], vec![4, 6]].iter())
+        {
+            let chrom_bed =
+                PlinkBed::from_prefix(prefix, PlinkSnpType::Additive).unwrap();
+            let actual = chrom_bed.get_genotype_matrix_u8(None, 255).unwrap();
+            assert_eq!(actual.dim(), (6, snp_indices.len()));
+            for (k, &i) in snp_indices.iter().enumerate() {
+                assert_eq!(actual.column(k), geno.column(i));
+            }
+            let records = Bim::read(&[format!("{}.bim", prefix)])
+                .unwrap()
+                .into_records();
+            assert_eq!(
+                records,
+                snp_indices
+                    .iter()
+                    .map(|&i| bim[i].clone())
+                    .collect::<Vec<BimRecord>>()
+            );
         }
-        let chunk_size = min(
-            self.num_snps_per_iter,
-            self.num_snps_in_range - self.range_cursor,
+        let all_prefixes: Vec<(&str, PlinkSnpType)> = prefixes
+            .iter()
+            .map(|p| (p.as_str(), PlinkSnpType::Additive))
+            .collect();
+        assert_eq!(
+            PlinkBed::from_prefixes(&all_prefixes)
+                .unwrap()
+                .total_num_snps(),
+            7
         );
-        // reading from the back is equivalent to reducing the number of SNPs in
-        // range
-        self.num_snps_in_range -= chunk_size;
+        assert!(bed.split_by_chromosome(&bim[1..], out_prefix).is_err());
+    }
 
-        // save and restore self.last_read_snp_index after the call to
-        // self.read_chunk we set the self.last_read_snp_index to None
-        // to prevent self.read_chunk from performing seek_relative on
-        // the buffer
-        let last_read_snp_index = self.last_read_file_snp_index;
-        self.last_read_file_snp_index = None;
+    #[test]
+    fn test_create_sharded_bed() {
+        let geno = Array::random((7, 10), Uniform::from(0..3));
+        let dir = tempdir().unwrap();
+        let out_prefix = dir.path().join("shard");
+        let out_prefix = out_prefix.to_str().unwrap();
+        let bed_paths =
+            PlinkBed::create_sharded_bed(&geno, out_prefix, 4).unwrap();
+        assert_eq!(
+            bed_paths,
+            (0..3)
+                .map(|i| format!("{}.{}.bed", out_prefix, i))
+                .collect::<Vec<String>>()
+        );
 
-        let snp = self
-            .range
-            .slice(self.num_snps_in_range..self.num_snps_in_range + 1)
-            .first()
-            .unwrap();
-        self.seek_to_snp(snp).unwrap();
-        let chunk = self.read_chunk(chunk_size);
-        match last_read_snp_index {
-            Some((file_i, snp_i)) => {
-                let snp_index = self
-                    .file_num_snps
-                    .iter()
-                    .take(file_i)
-                    .map(|pair| pair.0)
-                    .sum::<usize>()
-                    + snp_i;
-                self.seek_to_snp(snp_index).unwrap();
-            }
-            None => self.seek_to_snp(0).unwrap(),
-        };
-        self.last_read_file_snp_index = last_read_snp_index;
-        Some(chunk)
+        let mut temp_files = Vec::new();
+        let bfile_path_list: Vec<(String, String, String, PlinkSnpType)> =
+            bed_paths
+                .iter()
+                .zip([4, 4, 2].iter())
+                .map(|(bed_path, &num_snps)| {
+                    let mut bim = NamedTempFile::new().unwrap();
+                    let mut fam = NamedTempFile::new().unwrap();
+                    create_dummy_bim_fam(&mut bim, &mut fam, 7, num_snps)
+                        .unwrap();
+                    let bim = bim.into_temp_path();
+                    let fam = fam.into_temp_path();
+                    let paths = (
+                        bed_path.to_string(),
+                        bim.to_str().unwrap().to_string(),
+                        fam.to_str().unwrap().to_string(),
+                        PlinkSnpType::Additive,
+                    );
+                    temp_files.push((bim, fam));
+                    paths
+                })
+                .collect();
+        let bed = PlinkBed::new(&bfile_path_list).unwrap();
+        assert_eq!(bed.get_genotype_matrix_u8(None, 255).unwrap(), geno);
+        assert!(PlinkBed::create_sharded_bed(&geno, out_prefix, 0).is_err());
     }
-}
-
-struct ColChunkIterProducer {
-    iter: PlinkColChunkIter,
-}
 
-impl Producer for ColChunkIterProducer {
-    type IntoIter = PlinkColChunkIter;
-    type Item = <PlinkColChunkIter as Iterator>::Item;
+    #[test]
+    fn test_checked_geno_to_lowest_two_bits() {
+        for geno in 0..3 {
+            assert_eq!(
+                checked_geno_to_lowest_two_bits(geno).unwrap(),
+                geno_to_lowest_two_bits(geno)
+            );
+            assert_eq!(
+                lowest_two_bits_to_geno(geno_to_lowest_two_bits(geno)),
+                geno
+            );
+        }
+        assert!(checked_geno_to_lowest_two_bits(3).is_err());
+        assert!(checked_geno_to_lowest_two_bits(255).is_err());
 
-    #[inline]
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter
+        let path = NamedTempFile::new().unwrap().into_temp_path();
+        let path = path.to_str().unwrap();
+        assert!(PlinkBed::create_bed(&array![[3]], path).is_err());
+        assert!(PlinkBed::create_bed(&array![[0, 1], [2, 0], [1, 4]], path)
+            .is_err());
+        assert!(
+            PlinkBed::create_bed(&array![[0, 1], [2, 0], [1, 2]], path).is_ok()
+        );
     }
 
-    fn split_at(self, index: usize) -> (Self, Self) {
-        let mid_range_index =
-            min(self.iter.num_snps_per_iter * index, self.iter.range.size());
-        (
-            ColChunkIterProducer {
-                iter: self.iter.clone_with_range(
-                    self.iter.range.slice(0..mid_range_index),
-                ),
-            },
-            ColChunkIterProducer {
-                iter: self.iter.clone_with_range(
-                    self.iter
-                        .range
-                        .slice(mid_range_index..self.iter.range.size()),
-                ),
-            },
-        )
-    }
-}
+    #[test]
+    fn test_from_prefix_auto() {
+        let dir = tempdir().unwrap();
+        let prefix = dir.path().join("geno");
+        let prefix = prefix.to_str().unwrap();
+        let geno = array![[0u8, 1], [2, 1], [1, 0]];
+        PlinkBed::create_bed(&geno, &format!("{}.bed", prefix)).unwrap();
+        let mut bim = NamedTempFile::new().unwrap();
+        let mut fam = NamedTempFile::new().unwrap();
+        create_dummy_bim_fam(&mut bim, &mut fam, 3, 2).unwrap();
+        std::fs::copy(bim.path(), format!("{}.bim", prefix)).unwrap();
+        std::fs::copy(fam.path(), format!("{}.fam", prefix)).unwrap();
+        let geno_f32 = geno.mapv(|x| x as f32);
 
-impl IntoIterator for ColChunkIterProducer {
-    type IntoIter = PlinkColChunkIter;
-    type Item = <PlinkColChunkIter as Iterator>::Item;
+        let bed = PlinkBed::from_prefix_auto(prefix).unwrap();
+        assert_eq!(bed.get_file_num_snps(), &vec![(2, PlinkSnpType::Additive)]);
+        assert_eq!(bed.get_genotype_matrix(None, None).unwrap(), geno_f32);
 
-    #[inline]
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter
+        let encoding_path = format!("{}.encoding", prefix);
+        std::fs::write(&encoding_path, "Dominance\n").unwrap();
+        let bed = PlinkBed::from_prefix_auto(prefix).unwrap();
+        assert_eq!(bed.get_file_num_snps(), &vec![(
+            2,
+            PlinkSnpType::Dominance
+        )]);
+        assert_eq!(
+            bed.get_genotype_matrix(None, None).unwrap(),
+            convert_geno_arr_to_dominance_representation(geno_f32)
+        );
+
+        std::fs::write(&encoding_path, "recessive\n").unwrap();
+        assert!(PlinkBed::from_prefix_auto(prefix).is_err());
     }
-}
 
-pub struct PlinkColChunkParallelIter {
-    iter: PlinkColChunkIter,
-}
+    #[test]
+    fn test_write_matrix_tsv() {
+        let geno = array![[0u8, 1, 2, 1], [2, 1, 0, 0], [1, 0, 0, 2]];
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let sample_ids: Vec<String> =
+            ["s1", "s2", "s3"].iter().map(|s| s.to_string()).collect();
+        let snp_ids: Vec<String> = ["rs1", "rs2", "rs3", "rs4"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let range = OrderedIntegerSet::from_slice(&[[0, 0], [2, 3]]);
+        for num_people_per_block in 1..=4 {
+            let mut out = Vec::new();
+            bed.write_matrix_tsv_in_blocks(
+                &mut out,
+                Some(range.clone()),
+                Some(&sample_ids),
+                Some(&snp_ids),
+                num_people_per_block,
+            )
+            .unwrap();
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                "sample_id\trs1\trs3\trs4\n\
+                s1\t0\t2\t1\n\
+                s2\t2\t0\t0\n\
+                s3\t1\t0\t2\n"
+            );
+        }
+        let mut out = Vec::new();
+        bed.write_matrix_tsv(&mut out, None, None, None).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "0\t1\t2\t3\n0\t1\t2\t1\n2\t1\t0\t0\n1\t0\t0\t2\n"
+        );
+        assert!(bed
+            .write_matrix_tsv(Vec::new(), None, Some(&sample_ids[1..]), None)
+            .is_err());
+        assert!(bed
+            .write_matrix_tsv(Vec::new(), None, None, Some(&snp_ids[1..]))
+            .is_err());
+        assert!(bed
+            .write_matrix_tsv(
+                Vec::new(),
+                Some(OrderedIntegerSet::from_slice(&[[2, 4]])),
+                None,
+                None
+            )
+            .is_err());
+    }
 
-impl ParallelIterator for PlinkColChunkParallelIter {
-    type Item = <PlinkColChunkIter as Iterator>::Item;
+    #[test]
+    fn test_write_vcf() {
+        let geno = array![[0u8, 1, 2], [2, 1, 0], [1, 0, 0]];
+        let (bed, (bed_path, _bim_path, _fam_path)) = open_temp_bed(&geno);
+        // mark the genotype of the second person in the third SNP as missing
+        set_missing(&bed_path, 3, &[(1, 2)]);
+        let bim: Vec<BimRecord> = [("1", 100), ("1", 200), ("2", 50)]
+            .iter()
+            .enumerate()
+            .map(|(i, &(chrom, coordinate))| BimRecord {
+                chrom: chrom.to_string(),
+                variant_id: format!("rs{}", i),
+                cm_position: 0.,
+                coordinate,
+                allele_1: "A".to_string(),
+                allele_2: "G".to_string(),
+            })
+            .collect();
+        let fam: Vec<FamRecord> = (0..3)
+            .map(|i| FamRecord {
+                family_id: format!("f{}", i),
+                individual_id: format!("i{}", i),
+                paternal_id: "0".to_string(),
+                maternal_id: "0".to_string(),
+                sex: "0".to_string(),
+                phenotype: "-9".to_string(),
+            })
+            .collect();
+        let header = "##fileformat=VCFv4.2\n\
+            ##contig=<ID=1>\n\
+            ##contig=<ID=2>\n\
+            ##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n\
+            #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\ti0\ti1\ti2\n";
 
-    fn drive_unindexed<C>(self, consumer: C) -> C::Result
-    where
-        C: UnindexedConsumer<Self::Item>, {
-        bridge(self, consumer)
-    }
+        let mut out = Vec::new();
+        bed.write_vcf(&mut out, &bim, &fam, None).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "{}\
+                1\t100\trs0\tG\tA\t.\t.\t.\tGT\t0/0\t1/1\t0/1\n\
+                1\t200\trs1\tG\tA\t.\t.\t.\tGT\t0/1\t0/1\t0/0\n\
+                2\t50\trs2\tG\tA\t.\t.\t.\tGT\t1/1\t./.\t0/0\n",
+                header
+            )
+        );
 
-    fn opt_len(&self) -> Option<usize> {
-        Some(self.iter.len())
+        let mut out = Vec::new();
+        bed.write_vcf(
+            &mut out,
+            &bim,
+            &fam,
+            Some(OrderedIntegerSet::from_slice(&[[2, 2]])),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{}2\t50\trs2\tG\tA\t.\t.\t.\tGT\t1/1\t./.\t0/0\n", header)
+        );
+        assert!(bed.write_vcf(Vec::new(), &bim[1..], &fam, None).is_err());
+        assert!(bed.write_vcf(Vec::new(), &bim, &fam[1..], None).is_err());
     }
-}
 
-impl IndexedParallelIterator for PlinkColChunkParallelIter {
-    fn len(&self) -> usize {
-        self.iter.len()
+    #[test]
+    fn test_write_raw() {
+        let geno = array![[0u8, 1, 2], [2, 1, 0], [1, 0, 0]];
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        // mark the genotype of the second person in the third SNP as missing
+        set_missing(&bed_path, 3, &[(1, 2)]);
+        // the dominance type does not change the counts of the first allele
+        let bed = PlinkBed::new(&[(
+            bed_path.to_str().unwrap().to_string(),
+            bim_path.to_str().unwrap().to_string(),
+            fam_path.to_str().unwrap().to_string(),
+            PlinkSnpType::Dominance,
+        )])
+        .unwrap();
+        let bim: Vec<BimRecord> = (0..3)
+            .map(|i| BimRecord {
+                chrom: "1".to_string(),
+                variant_id: format!("rs{}", i),
+                cm_position: 0.,
+                coordinate: 100 * i as u64,
+                allele_1: "A".to_string(),
+                allele_2: "G".to_string(),
+            })
+            .collect();
+        let fam: Vec<FamRecord> = (0..3)
+            .map(|i| FamRecord {
+                family_id: format!("f{}", i),
+                individual_id: format!("i{}", i),
+                paternal_id: "0".to_string(),
+                maternal_id: "0".to_string(),
+                sex: "1".to_string(),
+                phenotype: "-9".to_string(),
+            })
+            .collect();
+        for num_people_per_block in 1..=4 {
+            let mut out = Vec::new();
+            bed.write_raw_in_blocks(
+                &mut out,
+                &fam,
+                &bim,
+                None,
+                num_people_per_block,
+            )
+            .unwrap();
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                "FID IID PAT MAT SEX PHENOTYPE rs0_A rs1_A rs2_A\n\
+                f0 i0 0 0 1 -9 0 1 2\n\
+                f1 i1 0 0 1 -9 2 1 NA\n\
+                f2 i2 0 0 1 -9 1 0 0\n"
+            );
+        }
+
+        let mut out = Vec::new();
+        bed.write_raw(
+            &mut out,
+            &fam,
+            &bim,
+            Some(OrderedIntegerSet::from_slice(&[[0, 0], [2, 2]])),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "FID IID PAT MAT SEX PHENOTYPE rs0_A rs2_A\n\
+            f0 i0 0 0 1 -9 0 2\n\
+            f1 i1 0 0 1 -9 2 NA\n\
+            f2 i2 0 0 1 -9 1 0\n"
+        );
+        assert!(bed.write_raw(Vec::new(), &fam[1..], &bim, None).is_err());
+        assert!(bed.write_raw(Vec::new(), &fam, &bim[1..], None).is_err());
+        assert!(bed
+            .write_raw(
+                Vec::new(),
+                &fam,
+                &bim,
+                Some(OrderedIntegerSet::from_slice(&[[2, 3]]))
+            )
+            .is_err());
     }
 
-    fn drive<C>(self, consumer: C) -> C::Result
-    where
-        C: Consumer<Self::Item>, {
-        bridge(self, consumer)
+    #[test]
+    fn test_polygenic_score() {
+        let geno = array![[0u8, 1, 2, 1], [2, 1, 0, 0], [1, 0, 2, 2]];
+        let (bed, (bed_path, _bim_path, _fam_path)) = open_temp_bed(&geno);
+        // mark the genotype of the second person in the third SNP as missing
+        set_missing(&bed_path, 3, &[(1, 2)]);
+        let range = OrderedIntegerSet::from_slice(&[[0, 0], [2, 3]]);
+        let weights = [0.5, -1., 2.];
+        assert_eq!(
+            bed.polygenic_score(
+                &weights,
+                Some(range.clone()),
+                MissingStrategy::Zero
+            )
+            .unwrap(),
+            vec![0., 1., 2.5]
+        );
+        assert_eq!(
+            bed.polygenic_score(
+                &weights,
+                Some(range.clone()),
+                MissingStrategy::MeanImpute
+            )
+            .unwrap(),
+            vec![0., -1., 2.5]
+        );
+        assert_eq!(
+            bed.polygenic_score(&[1., 1., 1., 1.], None, MissingStrategy::Zero)
+                .unwrap(),
+            vec![4., 3., 5.]
+        );
+        assert!(bed
+            .polygenic_score(&weights[1..], Some(range), MissingStrategy::Zero)
+            .is_err());
     }
 
-    fn with_producer<CB>(self, callback: CB) -> CB::Output
-    where
-        CB: ProducerCallback<Self::Item>, {
-        callback.callback(ColChunkIterProducer {
-            iter: self.iter,
-        })
-    }
-}
+    #[test]
+    fn test_polygenic_score_with_effect_alleles() {
+        let geno = array![[0u8, 1, 2, 1], [2, 1, 0, 0], [1, 0, 2, 2]];
+        let (bed, (bed_path, bim_path, fam_path)) = open_temp_bed(&geno);
+        // mark the genotype of the second person in the third SNP as missing
+        set_missing(&bed_path, 3, &[(1, 2)]);
+        let bim: Vec<BimRecord> = (0..4)
+            .map(|i| BimRecord {
+                chrom: "1".to_string(),
+                variant_id: format!("rs{}", i),
+                cm_position: 0.,
+                coordinate: i as u64,
+                allele_1: "A".to_string(),
+                allele_2: "G".to_string(),
+            })
+            .collect();
+        let range = OrderedIntegerSet::from_slice(&[[0, 0], [2, 3]]);
+        let weights = [0.5, -1., 2.];
+        let effect_alleles: Vec<String> =
+            ["A", "g", "T"].iter().map(|s| s.to_string()).collect();
 
-#[cfg(test)]
-mod tests {
-    use std::{cmp::min, io, io::Write};
+        // the genotypes of the third SNP become 0, 2 (missing), 0
+        let (scores, num_skipped) = bed
+            .polygenic_score_with_effect_alleles(
+                &weights,
+                &effect_alleles,
+                &bim,
+                Some(range.clone()),
+                MissingStrategy::Zero,
+                true,
+            )
+            .unwrap();
+        assert_eq!(scores, vec![0., 1., 0.5]);
+        assert_eq!(num_skipped, 1);
 
-    use math::{
-        set::ordered_integer_set::OrderedIntegerSet, traits::ToIterator,
-    };
-    use ndarray::{array, s, stack, Array, Axis, Ix2};
-    use ndarray_rand::RandomExt;
-    use rand::distributions::Uniform;
-    use tempfile::{NamedTempFile, TempPath};
+        // the missing genotype is imputed as 2 - 2 = 0
+        let (scores, num_skipped) = bed
+            .polygenic_score_with_effect_alleles(
+                &weights,
+                &effect_alleles,
+                &bim,
+                Some(range.clone()),
+                MissingStrategy::MeanImpute,
+                true,
+            )
+            .unwrap();
+        assert_eq!(scores, vec![0., 1., 0.5]);
+        assert_eq!(num_skipped, 1);
 
-    use crate::plink_bed::{
-        convert_geno_arr_to_dominance_representation, PlinkBed, PlinkSnpType,
-    };
+        assert!(bed
+            .polygenic_score_with_effect_alleles(
+                &weights,
+                &effect_alleles,
+                &bim,
+                Some(range.clone()),
+                MissingStrategy::Zero,
+                false,
+            )
+            .is_err());
 
-    fn create_dummy_bim_fam(
-        mut bim: &mut NamedTempFile,
-        mut fam: &mut NamedTempFile,
-        num_people: usize,
-        num_snps: usize,
-    ) -> Result<(), io::Error> {
-        write_dummy_bim(&mut bim, num_snps)?;
-        write_dummy_fam(&mut fam, num_people)?;
-        Ok(())
-    }
+        let effect_alleles: Vec<String> =
+            ["A", "A", "A"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            bed.polygenic_score_with_effect_alleles(
+                &weights,
+                &effect_alleles,
+                &bim,
+                Some(range.clone()),
+                MissingStrategy::Zero,
+                false,
+            )
+            .unwrap(),
+            (
+                bed.polygenic_score(
+                    &weights,
+                    Some(range.clone()),
+                    MissingStrategy::Zero
+                )
+                .unwrap(),
+                0
+            )
+        );
 
-    fn write_dummy_bim(
-        bim: &mut NamedTempFile,
-        num_snps: usize,
-    ) -> Result<(), io::Error> {
-        for i in 1..=num_snps {
-            bim.write_fmt(format_args!("{}\n", i))?;
-        }
-        Ok(())
+        let bed = PlinkBed::new(&[(
+            bed_path.to_str().unwrap().to_string(),
+            bim_path.to_str().unwrap().to_string(),
+            fam_path.to_str().unwrap().to_string(),
+            PlinkSnpType::Dominance,
+        )])
+        .unwrap();
+        assert!(bed
+            .polygenic_score_with_effect_alleles(
+                &weights,
+                &effect_alleles,
+                &bim,
+                Some(range.clone()),
+                MissingStrategy::Zero,
+                false,
+            )
+            .is_ok());
+        let effect_alleles: Vec<String> =
+            ["A", "G", "A"].iter().map(|s| s.to_string()).collect();
+        assert!(bed
+            .polygenic_score_with_effect_alleles(
+                &weights,
+                &effect_alleles,
+                &bim,
+                Some(range),
+                MissingStrategy::Zero,
+                true,
+            )
+            .is_err());
     }
 
-    fn write_dummy_fam(
-        fam: &mut NamedTempFile,
-        num_people: usize,
-    ) -> Result<(), io::Error> {
-        for i in 1..=num_people {
-            fam.write_fmt(format_args!("{}\n", i))?;
+    #[test]
+    fn test_bed_size_mismatch() {
+        let geno = array![[0, 1, 2, 1], [2, 0, 1, 1], [1, 1, 0, 2]];
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(&geno);
+        let bfile = [(
+            bed_path.to_str().unwrap().to_string(),
+            bim_path.to_str().unwrap().to_string(),
+            fam_path.to_str().unwrap().to_string(),
+            PlinkSnpType::Additive,
+        )];
+        assert!(PlinkBed::new(&bfile).is_ok());
+
+        // drop the last SNP
+        OpenOptions::new()
+            .write(true)
+            .open(&bed_path)
+            .unwrap()
+            .set_len((NUM_MAGIC_BYTES + 3) as u64)
+            .unwrap();
+        match PlinkBed::new(&bfile) {
+            Err(Error::BadFormat(why)) => assert!(why.contains("bytes")),
+            _ => panic!("expected a BadFormat error"),
         }
-        Ok(())
     }
 
     #[test]
-    fn test_create_bed() {
-        fn test(geno: &Array<u8, Ix2>) {
-            let mut bim = NamedTempFile::new().unwrap();
-            let mut fam = NamedTempFile::new().unwrap();
-            create_dummy_bim_fam(
-                &mut bim,
-                &mut fam,
-                geno.dim().0,
-                geno.dim().1,
+    fn test_chunk_iter_errors() {
+        let geno = array![[0, 1, 2, 1], [2, 0, 1, 1], [1, 1, 0, 2]];
+        let (bed, (bed_path, _bim_path, _fam_path)) = open_temp_bed(&geno);
+
+        let mut iter = bed
+            .col_chunk_iter(1, Some(OrderedIntegerSet::from_slice(&[[2, 4]])));
+        match iter.take_error() {
+            Some(Error::SnpIndexOutOfRange {
+                index: 4,
+                total: 4,
+            }) => {}
+            _ => panic!("expected a SnpIndexOutOfRange error"),
+        }
+        assert_eq!(iter.len(), 0);
+        assert!(iter.next().is_none());
+        assert!(bed
+            .get_genotype_matrix(
+                Some(OrderedIntegerSet::from_slice(&[[3, 4]])),
+                None
             )
+            .is_err());
+        // the frequencies of the unread SNPs are not filled with zeros
+        assert!(bed
+            .get_allele_frequencies_in_range(
+                Some(OrderedIntegerSet::from_slice(&[[0, 1], [3, 4]])),
+                1
+            )
+            .is_err());
+
+        // keep only the magic bytes and the first two SNPs
+        OpenOptions::new()
+            .write(true)
+            .open(&bed_path)
+            .unwrap()
+            .set_len((NUM_MAGIC_BYTES + 2) as u64)
             .unwrap();
-            let path = NamedTempFile::new().unwrap().into_temp_path();
-            let path_str = path.to_str().unwrap().to_string();
-            PlinkBed::create_bed(&geno, &path_str).unwrap();
-            let geno_bed = PlinkBed::new(&[(
-                path_str,
-                bim.into_temp_path().to_str().unwrap().to_string(),
-                fam.into_temp_path().to_str().unwrap().to_string(),
-                PlinkSnpType::Additive,
-            )])
-            .unwrap();
-            assert_eq!(
-                geno.mapv(|x| x as f32),
-                geno_bed.get_genotype_matrix(None).unwrap()
-            );
+        let mut iter = bed.col_chunk_iter(1, None);
+        assert_eq!(iter.next().unwrap(), array![[0.], [2.], [1.]]);
+        assert_eq!(iter.next().unwrap(), array![[1.], [0.], [1.]]);
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+        match iter.take_error() {
+            Some(Error::IO {
+                ..
+            }) => {}
+            _ => panic!("expected an IO error"),
         }
-        test(&array![[0],]);
-        test(&array![[1],]);
-        test(&array![[2],]);
-        test(&array![[0, 1, 2],]);
-        test(&array![[0], [1], [2],]);
-        test(&array![[0, 0, 1], [1, 1, 2], [0, 2, 1],]);
-        test(&array![
-            [0, 0, 1, 2],
-            [1, 1, 2, 1],
-            [2, 0, 0, 0],
-            [1, 0, 0, 2],
-            [0, 2, 1, 0],
-        ]);
-        test(&array![
-            [0, 0, 1, 2, 1],
-            [1, 1, 2, 1, 2],
-            [2, 0, 0, 0, 0],
-            [1, 0, 0, 2, 2],
-            [0, 2, 1, 0, 1],
-        ]);
-        test(&array![
-            [0, 0, 1, 2, 1],
-            [1, 0, 0, 2, 1],
-            [2, 0, 2, 0, 0],
-            [1, 1, 0, 2, 2],
-            [0, 2, 2, 1, 1],
-            [2, 1, 2, 0, 0],
-            [1, 2, 0, 1, 2],
-            [2, 0, 1, 0, 1],
-        ]);
-        test(&array![
-            [0, 0, 1, 2, 1, 2, 2, 0],
-            [1, 0, 0, 2, 1, 2, 1, 1],
-            [2, 0, 2, 0, 0, 0, 2, 1],
-            [1, 1, 0, 2, 2, 1, 1, 1],
-            [0, 2, 2, 1, 1, 2, 0, 2],
-            [2, 1, 2, 0, 0, 0, 2, 2],
-            [1, 2, 0, 1, 2, 1, 1, 0],
-            [2, 0, 1, 0, 1, 0, 0, 2],
-        ]);
-    }
+        assert!(bed.get_genotype_matrix(None, None).is_err());
+        assert!(bed.get_allele_frequencies(Some(1)).is_err());
+        assert!(bed.get_minor_allele_frequencies(None).is_err());
+        assert!(bed.windowed_maf(2, 1).is_err());
+        assert!(bed.ld_prune(4, 1, 0.99).is_err());
+        assert!(bed.snp_flags(None).is_err());
+        assert!(bed.snp_sums(None).is_err());
 
-    #[test]
-    fn test_multiple_bfiles() {
-        let (num_people, num_snps_1, num_snps_2) = (137usize, 71usize, 37usize);
-        let geno_1 =
-            Array::random((num_people, num_snps_1), Uniform::from(0..3));
-        let geno_2 =
-            Array::random((num_people, num_snps_2), Uniform::from(0..3));
-        let mut bim_1 = NamedTempFile::new().unwrap();
-        let mut bim_2 = NamedTempFile::new().unwrap();
-        let mut fam = NamedTempFile::new().unwrap();
-        write_dummy_fam(&mut fam, num_people).unwrap();
-        write_dummy_bim(&mut bim_1, num_snps_1).unwrap();
-        write_dummy_bim(&mut bim_2, num_snps_2).unwrap();
-        let bed_path_1 = NamedTempFile::new().unwrap().into_temp_path();
-        let bed_path_2 = NamedTempFile::new().unwrap().into_temp_path();
-        let bim_path_1 = bim_1.into_temp_path();
-        let bim_path_2 = bim_2.into_temp_path();
-        let fam_path = fam.into_temp_path();
-        PlinkBed::create_bed(&geno_1, bed_path_1.to_str().unwrap()).unwrap();
-        PlinkBed::create_bed(&geno_2, bed_path_2.to_str().unwrap()).unwrap();
+        let iter = bed.col_chunk_iter(1, None);
+        let error_handle = iter.get_error_handle();
+        iter.into_par_iter().for_each(|_| {});
+        assert!(error_handle.lock().unwrap().is_some());
+        assert!(bed.compute_grm(Some(1), false).is_err());
 
-        let bed = PlinkBed::new(&[
-            (
-                bed_path_1.to_str().unwrap().to_string(),
-                bim_path_1.to_str().unwrap().to_string(),
-                fam_path.to_str().unwrap().to_string(),
-                PlinkSnpType::Additive,
-            ),
-            (
-                bed_path_2.to_str().unwrap().to_string(),
-                bim_path_2.to_str().unwrap().to_string(),
-                fam_path.to_str().unwrap().to_string(),
-                PlinkSnpType::Additive,
-            ),
-        ])
-        .unwrap();
-        let true_geno_arr = stack![Axis(1), geno_1, geno_2].mapv(|x| x as f32);
-        assert_eq!(true_geno_arr, bed.get_genotype_matrix(None).unwrap());
+        bed_path.close().unwrap();
+        let mut iter = bed.col_chunk_iter(1, None);
+        assert!(iter.next().is_none());
+        assert!(iter.take_error().is_some());
     }
 
     #[test]
@@ -1065,7 +7044,14 @@ mod tests {
         let true_geno_arr = geno.mapv(|x| x as f32);
 
         // test get_genotype_matrix
-        assert_eq!(bed.get_genotype_matrix(None).unwrap(), true_geno_arr);
+        assert_eq!(bed.get_genotype_matrix(None, None).unwrap(), true_geno_arr);
+        for chunk_size in [1, 3, 70, 71, 200].iter() {
+            assert_eq!(
+                bed.get_genotype_matrix(None, Some(*chunk_size)).unwrap(),
+                true_geno_arr
+            );
+        }
+        assert!(bed.get_genotype_matrix(None, Some(0)).is_err());
 
         let chunk_size = 5;
         for (i, snps) in bed.col_chunk_iter(chunk_size, None).enumerate() {
@@ -1095,7 +7081,7 @@ mod tests {
 
         // test get_genotype_matrix
         let geno = bed
-            .get_genotype_matrix(Some(snp_index_slices.clone()))
+            .get_genotype_matrix(Some(snp_index_slices.clone()), None)
             .unwrap();
         let mut arr = Array::zeros((num_people, 35));
         for (jj, j) in snp_index_slices.to_iter().enumerate() {
@@ -1106,6 +7092,71 @@ mod tests {
         assert_eq!(arr, geno);
     }
 
+    #[test]
+    fn test_polymorphic_col_chunk_iter() {
+        let geno = array![
+            [0, 2, 1, 0, 2, 2, 0, 1],
+            [0, 2, 0, 0, 2, 1, 0, 1],
+            [0, 2, 2, 0, 2, 0, 0, 1],
+            [0, 2, 1, 0, 2, 2, 0, 1],
+            [0, 2, 0, 0, 2, 1, 0, 0],
+        ];
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let true_geno_arr = geno.mapv(|x| x as f32);
+
+        let collect_indices = |range: Option<OrderedIntegerSet<usize>>| {
+            let mut indices = Vec::new();
+            for (snps, snp_indices) in bed.polymorphic_col_chunk_iter(2, range)
+            {
+                assert!(snp_indices.len() <= 2);
+                assert_eq!(snps.dim(), (5, snp_indices.len()));
+                for (k, &j) in snp_indices.iter().enumerate() {
+                    assert_eq!(
+                        true_geno_arr.slice(s![.., j]),
+                        snps.slice(s![.., k])
+                    );
+                }
+                indices.extend(snp_indices);
+            }
+            indices
+        };
+        assert_eq!(collect_indices(None), vec![2, 5, 7]);
+        assert_eq!(
+            collect_indices(Some(OrderedIntegerSet::from_slice(&[[1, 1], [
+                4, 7
+            ]]))),
+            vec![5, 7]
+        );
+        assert_eq!(
+            collect_indices(Some(OrderedIntegerSet::from_slice(&[[3, 4]]))),
+            Vec::<usize>::new()
+        );
+    }
+
+    #[test]
+    fn test_compute_grm() {
+        // the last SNP is monomorphic and should be excluded
+        let geno = array![[0, 1, 0], [1, 1, 0], [2, 0, 0]];
+        let (bed, _bfile) = open_temp_bed(&geno);
+        let eps = 1e-6;
+        let raw_grm = array![[1.125, 0.125, -1.25], [0.125, 0.125, -0.25], [
+            -1.25, -0.25, 1.5
+        ],];
+        assert_arr_almost_eq_f32(
+            &bed.compute_grm(Some(2), false).unwrap(),
+            &raw_grm,
+            eps,
+        );
+        let mut adjusted_grm = raw_grm.clone();
+        adjusted_grm.diag_mut().assign(&array![1., 0., 1.75]);
+        assert_arr_almost_eq_f32(
+            &bed.compute_grm(Some(1), true).unwrap(),
+            &adjusted_grm,
+            eps,
+        );
+        assert!(bed.compute_grm(Some(0), false).is_err());
+    }
+
     fn create_temp_geno_bfile(
         geno: &Array<u8, Ix2>,
     ) -> (TempPath, TempPath, TempPath) {
@@ -1121,6 +7172,38 @@ mod tests {
         (bed_path, bim_path, fam_path)
     }
 
+    fn open_temp_bed(
+        geno: &Array<u8, Ix2>,
+    ) -> (PlinkBed, (TempPath, TempPath, TempPath)) {
+        let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(geno);
+        let bed = PlinkBed::new(&[(
+            bed_path.to_str().unwrap().to_string(),
+            bim_path.to_str().unwrap().to_string(),
+            fam_path.to_str().unwrap().to_string(),
+            PlinkSnpType::Additive,
+        )])
+        .unwrap();
+        (bed, (bed_path, bim_path, fam_path))
+    }
+
+    /// Marks the genotype of each `(person, snp)` in `genotypes` as missing in
+    /// the bed file of `num_people` people at `bed_path`.
+    fn set_missing(
+        bed_path: &Path,
+        num_people: usize,
+        genotypes: &[(usize, usize)],
+    ) {
+        let mut bytes = std::fs::read(bed_path).unwrap();
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(num_people);
+        for &(person, snp) in genotypes.iter() {
+            let i = NUM_MAGIC_BYTES + snp * num_bytes_per_snp + person / 4;
+            let shift = 2 * (person % 4);
+            bytes[i] = (bytes[i] & !(0b11 << shift))
+                | (MISSING_GENOTYPE_BITS << shift);
+        }
+        std::fs::write(bed_path, bytes).unwrap();
+    }
+
     fn assert_arr_almost_eq_f32(
         arr1: &Array<f32, Ix2>,
         arr2: &Array<f32, Ix2>,
@@ -1147,14 +7230,8 @@ mod tests {
     #[test]
     fn test_create_dominance_geno_bed() {
         fn test(geno: &Array<u8, Ix2>) {
-            let (bed_path, bim_path, fam_path) = create_temp_geno_bfile(geno);
-            let geno_bed = PlinkBed::new(&[(
-                bed_path.to_str().unwrap().to_string(),
-                bim_path.to_str().unwrap().to_string(),
-                fam_path.to_str().unwrap().to_string(),
-                PlinkSnpType::Additive,
-            )])
-            .unwrap();
+            let (geno_bed, (_bed_path, bim_path, fam_path)) =
+                open_temp_bed(geno);
             let dominance_path = NamedTempFile::new().unwrap().into_temp_path();
             geno_bed
                 .create_dominance_geno_bed(0, dominance_path.to_str().unwrap())
@@ -1167,14 +7244,14 @@ mod tests {
             )])
             .unwrap();
             assert_eq!(
-                geno_bed.get_genotype_matrix(None).unwrap().mapv(|s| {
+                geno_bed.get_genotype_matrix(None, None).unwrap().mapv(|s| {
                     match s as u8 {
                         2 => 1u8,
                         s => s,
                     }
                 }),
                 dominance_geno
-                    .get_genotype_matrix(None)
+                    .get_genotype_matrix(None, None)
                     .unwrap()
                     .mapv(|s| s as u8)
             );
@@ -1248,7 +7325,8 @@ mod tests {
                 PlinkSnpType::Dominance,
             )])
             .unwrap();
-            let dominance_snps = geno_bed.get_genotype_matrix(None).unwrap();
+            let dominance_snps =
+                geno_bed.get_genotype_matrix(None, None).unwrap();
             assert_arr_almost_eq_f32(&dominance_snps, &expected, eps)
         }
 
@@ -1269,4 +7347,151 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn test_snp_byte_offset() {
+        // the SNPs of 1_000_003 people take 250_001 bytes each, so that the
+        // offset of the SNP 20_000 overflows u32
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(1_000_003);
+        assert_eq!(num_bytes_per_snp, 250_001);
+        let expected: u64 = NUM_MAGIC_BYTES as u64 + 20_000 * 250_001 + 3;
+        assert!(expected > u32::MAX as u64);
+        assert_eq!(
+            num_snp_bytes(20_000, num_bytes_per_snp).unwrap(),
+            20_000 * 250_001
+        );
+        assert_eq!(
+            snp_byte_offset(20_000, num_bytes_per_snp, 3).unwrap(),
+            expected
+        );
+        assert!(cast_byte_offset::<u32>(expected).is_err());
+        assert_eq!(cast_byte_offset::<i64>(expected).unwrap(), expected as i64);
+        assert!(cast_byte_offset::<i64>(u64::MAX).is_err());
+
+        // the person 13 is in the byte 3 of the SNP
+        let mut cursor = Cursor::new(Vec::<u8>::new());
+        PlinkBed::seek_to_byte_containing_snp_i_person_j(
+            &mut cursor,
+            20_000,
+            13,
+            num_bytes_per_snp,
+        )
+        .unwrap();
+        assert_eq!(cursor.position(), expected);
+
+        #[cfg(target_pointer_width = "64")]
+        {
+            assert!(num_snp_bytes(usize::MAX, 2).is_err());
+            assert!(snp_byte_offset(usize::MAX / 2, 2, 0).is_err());
+            assert!(PlinkBed::seek_to_byte_containing_snp_i_person_j(
+                &mut cursor,
+                usize::MAX,
+                0,
+                num_bytes_per_snp,
+            )
+            .is_err());
+            assert_eq!(cursor.position(), expected);
+        }
+    }
+
+    #[test]
+    fn test_dominance_orientation() {
+        // the first allele frequencies are 3 / 8 and 1 / 8
+        let geno = array![[2., 0.], [1., 0.], [0., 1.], [0., 0.]];
+        assert_arr_almost_eq_f32(
+            &convert_geno_arr_to_dominance_representation_with_orientation(
+                geno.clone(),
+                true,
+            ),
+            &array![[-0.5, 0.], [0.75, 0.], [0., 0.25], [0., 0.]],
+            1e-6,
+        );
+        // the second allele frequencies are 5 / 8 and 7 / 8
+        assert_arr_almost_eq_f32(
+            &convert_geno_arr_to_dominance_representation_with_orientation(
+                geno.clone(),
+                false,
+            ),
+            &array![[0., 1.5], [1.25, 1.5], [0.5, 1.75], [0.5, 1.5]],
+            1e-6,
+        );
+        for &assume_minor_is_first in [true, false].iter() {
+            let expected =
+                convert_geno_arr_to_dominance_representation_with_orientation(
+                    geno.clone(),
+                    assume_minor_is_first,
+                );
+            for j in 0..2 {
+                let mut geno_vec = geno.column(j).to_vec();
+                convert_geno_vec_to_dominance_representation(
+                    &mut geno_vec,
+                    assume_minor_is_first,
+                );
+                assert_eq!(geno_vec, expected.column(j).to_vec());
+            }
+            // the values other than the genotypes are encoded as 0
+            assert_eq!(
+                convert_geno_arr_to_dominance_representation_with_orientation(
+                    array![[f32::NAN], [3.], [2.], [0.]],
+                    assume_minor_is_first,
+                )
+                .column(0)
+                .iter()
+                .filter(|&&x| x == 0.)
+                .count(),
+                3
+            );
+        }
+
+        // the first allele frequencies are 3 / 8 and 5 / 8, so the first
+        // allele is the minor allele of SNP 0 but not of SNP 1
+        let (bed_path, bim_path, fam_path) =
+            create_temp_geno_bfile(&array![[2, 2], [1, 2], [0, 1], [0, 0]]);
+        let mut bed = PlinkBed::new(&[(
+            bed_path.to_str().unwrap().to_string(),
+            bim_path.to_str().unwrap().to_string(),
+            fam_path.to_str().unwrap().to_string(),
+            PlinkSnpType::Dominance,
+        )])
+        .unwrap();
+        let check = |bed: &PlinkBed, expected: Array<f32, Ix2>| {
+            assert_arr_almost_eq_f32(
+                &bed.get_genotype_matrix(None, None).unwrap(),
+                &expected,
+                1e-6,
+            );
+            assert_arr_almost_eq_f32(
+                &bed.get_genotype_matrix_f64(None)
+                    .unwrap()
+                    .mapv(|x| x as f32),
+                &expected,
+                1e-6,
+            );
+            assert_arr_almost_eq_f32(
+                &bed.transposed_chunk_iter(4).unwrap().next().unwrap(),
+                &expected,
+                1e-6,
+            );
+        };
+        assert_eq!(
+            bed.get_dominance_orientation(),
+            DominanceOrientation::FirstAllele
+        );
+        check(&bed, array![[-0.5, 0.5], [0.75, 0.5], [0., 1.25], [0., 0.]]);
+        bed.set_dominance_orientation(DominanceOrientation::SecondAllele);
+        check(&bed, array![[0., 0.], [1.25, 0.], [0.5, 0.75], [0.5, -0.5]]);
+        bed.set_dominance_orientation(DominanceOrientation::MinorAllele);
+        check(&bed, array![[-0.5, 0.], [0.75, 0.], [0., 0.75], [0., -0.5]]);
+
+        // mark the genotype of person 3 at SNP 0 as missing, which is decoded
+        // as 0 rather than as the genotype 0
+        set_missing(&bed_path, 4, &[(3, 0)]);
+        bed.set_dominance_orientation(DominanceOrientation::SecondAllele);
+        check(&bed, array![[0., 0.], [1.25, 0.], [0.5, 0.75], [0., -0.5]]);
+        bed.set_missing_strategy(MissingStrategy::MeanImpute);
+        check(&bed, array![[0., 0.], [1.25, 0.], [0.5, 0.75], [
+            7. / 12.,
+            -0.5
+        ]]);
+    }
 }
@@ -0,0 +1,53 @@
+use math::{set::ordered_integer_set::OrderedIntegerSet, traits::ToIterator};
+use ndarray::{Array, Ix2};
+
+use crate::{error::Error, plink_bed::PlinkColChunkIter, plink_bim::BimRecord};
+
+/// Iterates over the chunks of SNPs of a `PlinkColChunkIter`, where each chunk
+/// is paired with the bim records of its columns in the same order.
+pub struct AnnotatedColChunkIter<'a> {
+    chunk_iter: PlinkColChunkIter,
+    bim: &'a [BimRecord],
+    range: OrderedIntegerSet<usize>,
+    range_cursor: usize,
+}
+
+impl<'a> AnnotatedColChunkIter<'a> {
+    /// `range` has to be the range of the `chunk_iter`, and `bim` has to
+    /// contain the records of all the SNPs.
+    pub(crate) fn new(
+        chunk_iter: PlinkColChunkIter,
+        bim: &'a [BimRecord],
+        range: OrderedIntegerSet<usize>,
+    ) -> AnnotatedColChunkIter<'a> {
+        AnnotatedColChunkIter {
+            chunk_iter,
+            bim,
+            range,
+            range_cursor: 0,
+        }
+    }
+
+    /// Takes the error that stopped the iteration, if any.
+    pub fn take_error(&self) -> Option<Error> {
+        self.chunk_iter.take_error()
+    }
+}
+
+impl<'a> Iterator for AnnotatedColChunkIter<'a> {
+    type Item = (Array<f32, Ix2>, Vec<&'a BimRecord>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = self.chunk_iter.next()?;
+        let num_snps = chunk.dim().1;
+        let bim = self.bim;
+        let records = self
+            .range
+            .slice(self.range_cursor..self.range_cursor + num_snps)
+            .to_iter()
+            .map(|i| &bim[i])
+            .collect();
+        self.range_cursor += num_snps;
+        Some((chunk, records))
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Read, Write},
+};
+
+use crate::{
+    error::Error,
+    plink_bed::{
+        checked_geno_to_lowest_two_bits, pack_snp_bytes, PlinkBed, MAGIC_BYTES,
+        NUM_MAGIC_BYTES,
+    },
+};
+
+/// Writes a bed file one SNP at a time, so that the SNPs can be generated in
+/// batches without holding the whole genotype matrix in memory.
+pub struct BedWriter {
+    writer: BufWriter<File>,
+    num_people: usize,
+    num_snps_written: usize,
+    snp_bytes: Vec<u8>,
+}
+
+impl BedWriter {
+    /// Creates the bed file at `path` for `num_people` people, truncating any
+    /// existing file, and writes the magic bytes. Returns an error if
+    /// `num_people` is 0.
+    pub fn create(path: &str, num_people: usize) -> Result<BedWriter, Error> {
+        check_num_people(num_people)?;
+        let mut writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(path)?,
+        );
+        writer.write_all(&MAGIC_BYTES)?;
+        Ok(BedWriter::from_writer(writer, num_people, 0))
+    }
+
+    /// Opens the existing bed file at `path` for `num_people` people, so that
+    /// the pushed SNPs are appended after the SNPs already in the file.
+    /// Returns an error if `num_people` is 0.
+    pub fn append(path: &str, num_people: usize) -> Result<BedWriter, Error> {
+        check_num_people(num_people)?;
+        let mut file = OpenOptions::new().read(true).append(true).open(path)?;
+        let mut magic_bytes = [0u8; NUM_MAGIC_BYTES];
+        file.read_exact(&mut magic_bytes)?;
+        if magic_bytes != MAGIC_BYTES {
+            return Err(Error::BadFormat(format!(
+                "{} does not start with the bed magic bytes",
+                path
+            )));
+        }
+        let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(num_people);
+        let num_snp_bytes = file.metadata()?.len() - NUM_MAGIC_BYTES as u64;
+        if !num_snp_bytes.is_multiple_of(num_bytes_per_snp as u64) {
+            return Err(Error::BadFormat(format!(
+                "the {} bytes of SNPs in {} are not a multiple of {} bytes \
+                per SNP for {} people",
+                num_snp_bytes, path, num_bytes_per_snp, num_people
+            )));
+        }
+        Ok(BedWriter::from_writer(
+            BufWriter::new(file),
+            num_people,
+            (num_snp_bytes / num_bytes_per_snp as u64) as usize,
+        ))
+    }
+
+    fn from_writer(
+        writer: BufWriter<File>,
+        num_people: usize,
+        num_snps_written: usize,
+    ) -> BedWriter {
+        BedWriter {
+            writer,
+            num_people,
+            num_snps_written,
+            snp_bytes: vec![0u8; PlinkBed::num_bytes_per_snp(num_people)],
+        }
+    }
+
+    #[inline]
+    pub fn num_people(&self) -> usize {
+        self.num_people
+    }
+
+    /// The number of SNPs in the file, including those already in the file
+    /// when opened by `append`.
+    #[inline]
+    pub fn num_snps_written(&self) -> usize {
+        self.num_snps_written
+    }
+
+    /// Encodes and writes the SNP whose genotypes of the people are
+    /// `dosages`, each of which must be 0, 1, or 2.
+    pub fn push_snp(&mut self, dosages: &[u8]) -> Result<(), Error> {
+        if dosages.len() != self.num_people {
+            return Err(Error::Generic(format!(
+                "the SNP has {} dosages but there are {} people",
+                dosages.len(),
+                self.num_people
+            )));
+        }
+        pack_snp_bytes(dosages, &mut self.snp_bytes, 0b00, |&geno| {
+            checked_geno_to_lowest_two_bits(geno)
+        })?;
+        self.writer.write_all(&self.snp_bytes)?;
+        self.num_snps_written += 1;
+        Ok(())
+    }
+
+    /// Writes the SNP whose `snp_bytes` are already encoded as in a bed file,
+    /// e.g. as read by `PlinkBed::packed_snp`, which must be
+    /// `num_bytes_per_snp` bytes long.
+    pub fn push_packed_snp(&mut self, snp_bytes: &[u8]) -> Result<(), Error> {
+        if snp_bytes.len() != self.snp_bytes.len() {
+            return Err(Error::Generic(format!(
+                "the SNP has {} bytes but {} people take {} bytes",
+                snp_bytes.len(),
+                self.num_people,
+                self.snp_bytes.len()
+            )));
+        }
+        self.writer.write_all(snp_bytes)?;
+        self.num_snps_written += 1;
+        Ok(())
+    }
+
+    /// Flushes the SNPs written so far to the file.
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn check_num_people(num_people: usize) -> Result<(), Error> {
+    if num_people == 0 {
+        return Err(Error::Generic(
+            "cannot write a bed file with 0 people".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    use super::BedWriter;
+    use crate::plink_bed::PlinkBed;
+
+    #[test]
+    fn test_bed_writer() {
+        let geno =
+            array![[0u8, 1, 2], [2, 1, 0], [1, 0, 0], [2, 2, 1], [0, 1, 1]];
+        let expected_path = NamedTempFile::new().unwrap().into_temp_path();
+        PlinkBed::create_bed(&geno, expected_path.to_str().unwrap()).unwrap();
+
+        let path = NamedTempFile::new().unwrap().into_temp_path();
+        let path_str = path.to_str().unwrap();
+        let mut writer = BedWriter::create(path_str, 5).unwrap();
+        for j in 0..2 {
+            writer.push_snp(&geno.column(j).to_vec()).unwrap();
+        }
+        assert!(writer.push_snp(&[0, 1, 2, 1]).is_err());
+        assert!(writer.push_snp(&[0, 1, 2, 1, 3]).is_err());
+        assert_eq!(writer.num_snps_written(), 2);
+        writer.finish().unwrap();
+
+        let mut writer = BedWriter::append(path_str, 5).unwrap();
+        assert_eq!(writer.num_snps_written(), 2);
+        writer.push_snp(&geno.column(2).to_vec()).unwrap();
+        assert_eq!(writer.num_snps_written(), 3);
+        writer.finish().unwrap();
+
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            std::fs::read(&expected_path).unwrap()
+        );
+        // 13 people take 4 bytes per SNP, which do not divide the 6 bytes
+        assert!(BedWriter::append(path_str, 13).is_err());
+        assert!(BedWriter::append(path_str, 0).is_err());
+        assert!(BedWriter::create(path_str, 0).is_err());
+    }
+
+    #[test]
+    fn test_bed_writer_append_bad_magic_bytes() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&[0x6c, 0x1b, 0x0, 0b11_10_00_01]).unwrap();
+        assert!(BedWriter::append(file.path().to_str().unwrap(), 4).is_err());
+    }
+}
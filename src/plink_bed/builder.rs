@@ -0,0 +1,157 @@
+use crate::{
+    error::Error,
+    plink_bed::{
+        DominanceOrientation, MissingStrategy, PlinkBed, PlinkSnpType,
+    },
+    util::bfile_paths,
+};
+
+/// A builder for `PlinkBed` as an alternative to passing the tuples of paths
+/// to `PlinkBed::new`.
+#[derive(Default)]
+pub struct PlinkBedBuilder {
+    bfile_path_list: Vec<(String, String, String, PlinkSnpType)>,
+    missing_strategy: MissingStrategy,
+    dominance_orientation: DominanceOrientation,
+    quiet: bool,
+    /// The first error in adding a bfile, which is returned by `build`.
+    error: Option<Error>,
+}
+
+impl PlinkBedBuilder {
+    pub fn new() -> PlinkBedBuilder {
+        PlinkBedBuilder::default()
+    }
+
+    /// Adds the bfile whose bed, bim and fam files are `{prefix}.bed`,
+    /// `{prefix}.bim` and `{prefix}.fam` respectively. An invalid `prefix`
+    /// makes `build` return an error.
+    pub fn add_bfile(
+        mut self,
+        prefix: &str,
+        snp_type: PlinkSnpType,
+    ) -> PlinkBedBuilder {
+        match bfile_paths(prefix) {
+            Ok((bed, bim, fam)) => {
+                self.bfile_path_list.push((bed, bim, fam, snp_type))
+            }
+            Err(error) => {
+                self.error.get_or_insert(error);
+            }
+        }
+        self
+    }
+
+    /// Adds the bfile with explicit paths to its bed, bim and fam files.
+    pub fn add_bfile_paths(
+        mut self,
+        bed_path: &str,
+        bim_path: &str,
+        fam_path: &str,
+        snp_type: PlinkSnpType,
+    ) -> PlinkBedBuilder {
+        self.bfile_path_list.push((
+            bed_path.to_string(),
+            bim_path.to_string(),
+            fam_path.to_string(),
+            snp_type,
+        ));
+        self
+    }
+
+    pub fn missing_strategy(
+        mut self,
+        missing_strategy: MissingStrategy,
+    ) -> PlinkBedBuilder {
+        self.missing_strategy = missing_strategy;
+        self
+    }
+
+    pub fn dominance_orientation(
+        mut self,
+        dominance_orientation: DominanceOrientation,
+    ) -> PlinkBedBuilder {
+        self.dominance_orientation = dominance_orientation;
+        self
+    }
+
+    /// If `quiet` is true, the summary of the files will not be logged.
+    pub fn quiet(mut self, quiet: bool) -> PlinkBedBuilder {
+        self.quiet = quiet;
+        self
+    }
+
+    pub fn build(self) -> Result<PlinkBed, Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let mut bed = if self.quiet {
+            PlinkBed::new_quiet(&self.bfile_path_list)?
+        } else {
+            PlinkBed::new(&self.bfile_path_list)?
+        };
+        bed.set_missing_strategy(self.missing_strategy);
+        bed.set_dominance_orientation(self.dominance_orientation);
+        Ok(bed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+    use tempfile::tempdir;
+
+    use super::PlinkBedBuilder;
+    use crate::plink_bed::{MissingStrategy, PlinkBed, PlinkSnpType};
+
+    #[test]
+    fn test_builder() {
+        let dir = tempdir().unwrap();
+        let prefix = dir.path().join("geno");
+        let prefix = prefix.to_str().unwrap();
+        let geno = array![[0u8, 1], [2, 1], [1, 1]];
+        PlinkBed::create_bed(&geno, &format!("{}.bed", prefix)).unwrap();
+        // mark the genotype of the third person in the first SNP as missing
+        {
+            let mut bytes = std::fs::read(format!("{}.bed", prefix)).unwrap();
+            bytes[3] = (bytes[3] & 0b11_00_11_11) | 0b00_01_00_00;
+            std::fs::write(format!("{}.bed", prefix), bytes).unwrap();
+        }
+        std::fs::write(format!("{}.bim", prefix), "1\n2\n").unwrap();
+        std::fs::write(format!("{}.fam", prefix), "1\n2\n3\n").unwrap();
+
+        let bed = PlinkBedBuilder::new()
+            .add_bfile(prefix, PlinkSnpType::Additive)
+            .quiet(true)
+            .build()
+            .unwrap();
+        assert_eq!(bed.get_missing_strategy(), MissingStrategy::Zero);
+        assert_eq!(bed.get_genotype_matrix(None, None).unwrap(), array![
+            [0., 1.],
+            [2., 1.],
+            [0., 1.]
+        ]);
+
+        let bed = PlinkBedBuilder::new()
+            .add_bfile(prefix, PlinkSnpType::Additive)
+            .missing_strategy(MissingStrategy::MeanImpute)
+            .build()
+            .unwrap();
+        assert_eq!(bed.get_genotype_matrix(None, None).unwrap(), array![
+            [0., 1.],
+            [2., 1.],
+            [1., 1.]
+        ]);
+
+        assert!(PlinkBedBuilder::new()
+            .add_bfile(&format!("{}_missing", prefix), PlinkSnpType::Additive)
+            .build()
+            .is_err());
+        assert!(PlinkBedBuilder::new().build().is_err());
+        assert!(PlinkBedBuilder::new()
+            .add_bfile("/", PlinkSnpType::Additive)
+            .add_bfile(prefix, PlinkSnpType::Additive)
+            .build()
+            .is_err());
+    }
+}
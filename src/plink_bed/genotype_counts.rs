@@ -0,0 +1,197 @@
+use crate::plink_bed::{
+    decode_snp_bytes_with, HETEROZYGOUS_GENOTYPE_BITS,
+    HOMOZYGOUS_FIRST_GENOTYPE_BITS, HOMOZYGOUS_SECOND_GENOTYPE_BITS,
+    MISSING_GENOTYPE_BITS,
+};
+
+/// The number of people with each genotype at a SNP, where the first allele is
+/// the first allele in the .bim file.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct GenotypeCounts {
+    /// The number of people homozygous for the first allele, i.e. genotype 2.
+    pub num_hom_first: usize,
+    /// The number of heterozygous people, i.e. genotype 1.
+    pub num_het: usize,
+    /// The number of people homozygous for the second allele, i.e. genotype 0.
+    pub num_hom_second: usize,
+    pub num_missing: usize,
+}
+
+impl GenotypeCounts {
+    /// Counts the genotypes of the `num_people` people in the `snp_bytes` of a
+    /// SNP, skipping the padding bits in the last byte.
+    pub fn from_snp_bytes(
+        snp_bytes: &[u8],
+        num_people: usize,
+    ) -> GenotypeCounts {
+        let mut counts = GenotypeCounts::default();
+        for bits in decode_snp_bytes_with(snp_bytes, num_people, |bits| bits) {
+            match bits {
+                HOMOZYGOUS_FIRST_GENOTYPE_BITS => counts.num_hom_first += 1,
+                HETEROZYGOUS_GENOTYPE_BITS => counts.num_het += 1,
+                HOMOZYGOUS_SECOND_GENOTYPE_BITS => counts.num_hom_second += 1,
+                MISSING_GENOTYPE_BITS => counts.num_missing += 1,
+                _ => unreachable!(),
+            }
+        }
+        counts
+    }
+
+    #[inline]
+    pub fn num_non_missing(&self) -> usize {
+        self.num_hom_first + self.num_het + self.num_hom_second
+    }
+
+    /// The frequency of the less frequent allele among the non-missing
+    /// genotypes, which is NaN if all the genotypes are missing.
+    pub fn minor_allele_frequency(&self) -> f32 {
+        let num_alleles = 2 * self.num_non_missing();
+        let first_allele_frequency =
+            (2 * self.num_hom_first + self.num_het) as f32 / num_alleles as f32;
+        first_allele_frequency.min(1. - first_allele_frequency)
+    }
+}
+
+/// The quality-control flags of a SNP.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SnpFlag {
+    /// Whether the genotypes of all the people are missing.
+    pub all_missing: bool,
+    /// Whether there is no minor allele among the non-missing genotypes,
+    /// which is also the case if all the genotypes are missing.
+    pub monomorphic: bool,
+    /// The minor allele frequency among the non-missing genotypes, which is
+    /// NaN if all the genotypes are missing.
+    pub maf: f32,
+}
+
+impl From<GenotypeCounts> for SnpFlag {
+    fn from(counts: GenotypeCounts) -> SnpFlag {
+        let num_non_missing = counts.num_non_missing();
+        SnpFlag {
+            all_missing: num_non_missing == 0,
+            monomorphic: counts.num_hom_first == num_non_missing
+                || counts.num_hom_second == num_non_missing,
+            maf: counts.minor_allele_frequency(),
+        }
+    }
+}
+
+/// A summary of the whole dataset of a `PlinkBed`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DatasetSummary {
+    pub num_people: usize,
+    pub num_snps: usize,
+    pub num_files: usize,
+    /// The fraction of the genotypes over all the people and SNPs that are
+    /// not missing.
+    pub overall_call_rate: f32,
+    /// The mean of the minor allele frequencies of the SNPs, excluding the
+    /// SNPs whose genotypes are all missing, which is NaN if there is no
+    /// other SNP.
+    pub mean_maf: f32,
+}
+
+impl DatasetSummary {
+    /// Aggregates the genotype `counts` of all the SNPs of the `num_files`
+    /// files.
+    pub fn from_counts(
+        counts: &[GenotypeCounts],
+        num_people: usize,
+        num_files: usize,
+    ) -> DatasetSummary {
+        let num_snps = counts.len();
+        let num_non_missing: usize =
+            counts.iter().map(|c| c.num_non_missing()).sum();
+        let (maf_sum, num_called_snps) = counts
+            .iter()
+            .filter(|c| c.num_non_missing() > 0)
+            .fold((0f64, 0usize), |(sum, n), c| {
+                (sum + c.minor_allele_frequency() as f64, n + 1)
+            });
+        DatasetSummary {
+            num_people,
+            num_snps,
+            num_files,
+            overall_call_rate: (num_non_missing as f64
+                / (num_people * num_snps) as f64)
+                as f32,
+            mean_maf: (maf_sum / num_called_snps as f64) as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DatasetSummary, GenotypeCounts, SnpFlag};
+
+    #[test]
+    fn test_genotype_counts() {
+        // the people are encoded from the lowest two bits, where 00 -> 2,
+        // 01 -> missing, 10 -> 1, 11 -> 0, and the last byte has two padding
+        // slots filled with 00
+        let counts =
+            GenotypeCounts::from_snp_bytes(&[0b11_01_10_00, 0b00_00_10_00], 6);
+        assert_eq!(counts, GenotypeCounts {
+            num_hom_first: 2,
+            num_het: 2,
+            num_hom_second: 1,
+            num_missing: 1,
+        });
+        assert_eq!(counts.num_non_missing(), 5);
+        assert!((counts.minor_allele_frequency() - 0.4).abs() < 1e-6);
+
+        let flag = SnpFlag::from(counts);
+        assert!(!flag.all_missing);
+        assert!(!flag.monomorphic);
+        assert!((flag.maf - 0.4).abs() < 1e-6);
+
+        let flag =
+            SnpFlag::from(GenotypeCounts::from_snp_bytes(&[0b11_01_11_11], 4));
+        assert!(!flag.all_missing);
+        assert!(flag.monomorphic);
+        assert_eq!(flag.maf, 0.);
+
+        let flag = SnpFlag::from(GenotypeCounts::from_snp_bytes(
+            &[0b01_01_01_01, 0b01],
+            5,
+        ));
+        assert!(flag.all_missing);
+        assert!(flag.monomorphic);
+        assert!(flag.maf.is_nan());
+    }
+
+    #[test]
+    fn test_dataset_summary() {
+        let counts = [
+            GenotypeCounts {
+                num_hom_first: 1,
+                num_het: 1,
+                num_hom_second: 2,
+                num_missing: 0,
+            },
+            GenotypeCounts {
+                num_hom_first: 0,
+                num_het: 0,
+                num_hom_second: 0,
+                num_missing: 4,
+            },
+            GenotypeCounts {
+                num_hom_first: 3,
+                num_het: 0,
+                num_hom_second: 0,
+                num_missing: 1,
+            },
+        ];
+        let summary = DatasetSummary::from_counts(&counts, 4, 2);
+        assert_eq!(summary.num_people, 4);
+        assert_eq!(summary.num_snps, 3);
+        assert_eq!(summary.num_files, 2);
+        assert!((summary.overall_call_rate - 7. / 12.).abs() < 1e-6);
+        assert!((summary.mean_maf - 3. / 16.).abs() < 1e-6);
+
+        let summary = DatasetSummary::from_counts(&counts[1..2], 4, 1);
+        assert_eq!(summary.overall_call_rate, 0.);
+        assert!(summary.mean_maf.is_nan());
+    }
+}
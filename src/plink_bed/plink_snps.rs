@@ -108,7 +108,8 @@ impl Iterator for PlinkSnpsIter {
             None
         } else {
             let snp = lowest_two_bits_to_geno(
-                self.bytes[self.byte_cursor] >> (2 * (self.cursor % 4) as u8),
+                (self.bytes[self.byte_cursor] >> (2 * (self.cursor % 4) as u8))
+                    & 0b11,
             );
             self.cursor += 1;
             if self.cursor % 4 == 0 {
@@ -0,0 +1,114 @@
+use std::{
+    fs::File,
+    io,
+    io::{Read, Seek, SeekFrom},
+    sync::Arc,
+};
+
+/// A reader of one of the files shared behind an `Arc`, which keeps its own
+/// position and reads at that position without moving the cursor shared by
+/// the file descriptor, so that any number of readers on different threads
+/// can read the same file through a single descriptor.
+pub(crate) struct SharedFile {
+    files: Arc<Vec<File>>,
+    file_index: usize,
+    pos: u64,
+}
+
+impl SharedFile {
+    pub(crate) fn new(files: Arc<Vec<File>>, file_index: usize) -> SharedFile {
+        SharedFile {
+            files,
+            file_index,
+            pos: 0,
+        }
+    }
+
+    #[inline]
+    fn file(&self) -> &File {
+        &self.files[self.file_index]
+    }
+
+    #[cfg(unix)]
+    fn read_at(&self, buf: &mut [u8]) -> io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self.file(), buf, self.pos)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, buf: &mut [u8]) -> io::Result<usize> {
+        std::os::windows::fs::FileExt::seek_read(self.file(), buf, self.pos)
+    }
+}
+
+impl Read for SharedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let num_bytes_read = self.read_at(buf)?;
+        self.pos += num_bytes_read as u64;
+        Ok(num_bytes_read)
+    }
+}
+
+impl Seek for SharedFile {
+    fn seek(&mut self, seek_from: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match seek_from {
+            SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return Ok(pos);
+            }
+            SeekFrom::Current(offset) => (self.pos, offset),
+            SeekFrom::End(offset) => (self.file().metadata()?.len(), offset),
+        };
+        match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufReader, Read, Seek, SeekFrom, Write},
+        sync::Arc,
+    };
+
+    use tempfile::NamedTempFile;
+
+    use super::SharedFile;
+
+    #[test]
+    fn test_shared_file() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&(0u8..100).collect::<Vec<u8>>()).unwrap();
+        let files = Arc::new(vec![file.reopen().unwrap()]);
+        let mut a =
+            BufReader::with_capacity(8, SharedFile::new(files.clone(), 0));
+        let mut b = BufReader::with_capacity(8, SharedFile::new(files, 0));
+        let mut bytes = [0u8; 3];
+
+        // the readers keep their own positions
+        a.seek(SeekFrom::Start(10)).unwrap();
+        a.read_exact(&mut bytes).unwrap();
+        assert_eq!(bytes, [10, 11, 12]);
+        b.read_exact(&mut bytes).unwrap();
+        assert_eq!(bytes, [0, 1, 2]);
+        a.seek_relative(20).unwrap();
+        a.read_exact(&mut bytes).unwrap();
+        assert_eq!(bytes, [33, 34, 35]);
+        b.seek(SeekFrom::Current(-2)).unwrap();
+        b.read_exact(&mut bytes).unwrap();
+        assert_eq!(bytes, [1, 2, 3]);
+
+        assert_eq!(a.seek(SeekFrom::End(-3)).unwrap(), 97);
+        a.read_exact(&mut bytes).unwrap();
+        assert_eq!(bytes, [97, 98, 99]);
+        assert!(a.read_exact(&mut bytes).is_err());
+        assert!(b.seek(SeekFrom::Current(-10)).is_err());
+    }
+}
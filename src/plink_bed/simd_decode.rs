@@ -0,0 +1,89 @@
+use std::simd::{num::SimdUint, u8x16};
+
+use crate::plink_bed::PlinkBed;
+
+const NUM_LANES: usize = 16;
+const NUM_PEOPLE_PER_VECTOR: usize = NUM_LANES * 4;
+
+/// Same as the scalar `decode_snp_bytes_into`, but decodes 16 bytes, i.e. 64
+/// people, per vector operation. The bytes after the last one are treated as
+/// zeros, and the genotypes decoded from them and from the padding bits in the
+/// last byte are truncated.
+pub(crate) fn decode_snp_bytes_into(bytes: &[u8], snp_vec: &mut [f32]) {
+    let num_bytes_per_snp = PlinkBed::num_bytes_per_snp(snp_vec.len());
+    for (people, chunk) in snp_vec
+        .chunks_mut(NUM_PEOPLE_PER_VECTOR)
+        .zip(bytes[..num_bytes_per_snp].chunks(NUM_LANES))
+    {
+        let genotypes = decode_vector(u8x16::load_or_default(chunk));
+        people.copy_from_slice(&genotypes[..people.len()]);
+    }
+}
+
+/// Decodes the genotypes of the 64 people in the 16 `bytes`, where the first
+/// person is in the lowest two bits of the first byte.
+#[inline]
+fn decode_vector(bytes: u8x16) -> [f32; NUM_PEOPLE_PER_VECTOR] {
+    let one = u8x16::splat(1);
+    let two_bits = u8x16::splat(0b11);
+    // the same bit arithmetic as lowest_two_bits_to_geno, applied per lane
+    let decode = |bits: u8x16| {
+        let a = bits >> one;
+        let b = bits & one;
+        (((a | b) ^ one) << one) | (a & !b & one)
+    };
+    // genotypes[k][i] is the genotype of person 4 * i + k
+    let genotypes = [
+        decode(bytes & two_bits),
+        decode((bytes >> u8x16::splat(2)) & two_bits),
+        decode((bytes >> u8x16::splat(4)) & two_bits),
+        decode((bytes >> u8x16::splat(6)) & two_bits),
+    ];
+    let (even_lo, even_hi) = genotypes[0].interleave(genotypes[2]);
+    let (odd_lo, odd_hi) = genotypes[1].interleave(genotypes[3]);
+    let (p0, p1) = even_lo.interleave(odd_lo);
+    let (p2, p3) = even_hi.interleave(odd_hi);
+
+    let mut out = [0f32; NUM_PEOPLE_PER_VECTOR];
+    for (out_chunk, people) in
+        out.chunks_exact_mut(NUM_LANES).zip(&[p0, p1, p2, p3])
+    {
+        people.cast::<f32>().copy_to_slice(out_chunk);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array;
+    use ndarray_rand::RandomExt;
+    use rand::distributions::Uniform;
+
+    use super::decode_snp_bytes_into;
+    use crate::plink_bed::{
+        decode_snp_bytes_with, lowest_two_bits_to_geno, PlinkBed,
+    };
+
+    #[test]
+    fn test_decode_snp_bytes_matches_scalar() {
+        for num_people in (0..300).chain(vec![4093, 4094, 4095, 4096]) {
+            let num_bytes = PlinkBed::num_bytes_per_snp(num_people);
+            for _ in 0..10 {
+                // the random bits include the padding bits in the last byte
+                let bytes = Array::random(
+                    num_bytes,
+                    Uniform::new_inclusive(0u8, 255u8),
+                )
+                .into_raw_vec();
+                let mut decoded = vec![0f32; num_people];
+                decode_snp_bytes_into(&bytes, &mut decoded);
+                assert_eq!(
+                    decoded,
+                    decode_snp_bytes_with(&bytes, num_people, |bits| {
+                        lowest_two_bits_to_geno(bits) as f32
+                    })
+                );
+            }
+        }
+    }
+}
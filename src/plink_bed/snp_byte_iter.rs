@@ -0,0 +1,93 @@
+use math::set::ordered_integer_set::IntegerSetIter;
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+};
+
+use crate::{
+    error::Error,
+    plink_bed::{cast_byte_offset, num_snp_bytes, snp_byte_offset},
+};
+
+/// Iterates over the packed bytes of the SNPs in a range of a single bed file,
+/// yielding each SNP as `(global_snp_index, snp_bytes)`, where the global SNP
+/// index counts the SNPs of the preceding files.
+///
+/// The iterator stops once an IO error occurs, and the error can be retrieved
+/// by `take_error` after the iteration.
+pub struct SnpByteIter {
+    buf: BufReader<File>,
+    snp_index_iter: IntegerSetIter<usize>,
+    num_bytes_per_snp: usize,
+    /// The global index of the first SNP of the file.
+    first_snp_index: usize,
+    /// The index within the file of the SNP following the last SNP read.
+    next_file_snp_index: Option<usize>,
+    error: Option<Error>,
+}
+
+impl SnpByteIter {
+    pub(crate) fn new(
+        buf: BufReader<File>,
+        snp_index_iter: IntegerSetIter<usize>,
+        num_bytes_per_snp: usize,
+        first_snp_index: usize,
+    ) -> SnpByteIter {
+        SnpByteIter {
+            buf,
+            snp_index_iter,
+            num_bytes_per_snp,
+            first_snp_index,
+            next_file_snp_index: None,
+            error: None,
+        }
+    }
+
+    /// Returns the error that stopped the iteration, if any, leaving `None`
+    /// in its place.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    fn read_snp(&mut self, file_snp_index: usize) -> Result<Vec<u8>, Error> {
+        match self.next_file_snp_index {
+            Some(next) if next <= file_snp_index => {
+                self.buf.seek_relative(cast_byte_offset(num_snp_bytes(
+                    file_snp_index - next,
+                    self.num_bytes_per_snp,
+                )?)?)?;
+            }
+            _ => {
+                self.buf.seek(SeekFrom::Start(snp_byte_offset(
+                    file_snp_index,
+                    self.num_bytes_per_snp,
+                    0,
+                )?))?;
+            }
+        }
+        let mut snp_bytes = vec![0u8; self.num_bytes_per_snp];
+        self.buf.read_exact(&mut snp_bytes)?;
+        self.next_file_snp_index = Some(file_snp_index + 1);
+        Ok(snp_bytes)
+    }
+}
+
+impl Iterator for SnpByteIter {
+    type Item = (usize, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+        let file_snp_index = self.snp_index_iter.next()?;
+        match self.read_snp(file_snp_index) {
+            Ok(snp_bytes) => {
+                Some((self.first_snp_index + file_snp_index, snp_bytes))
+            }
+            Err(error) => {
+                self.error = Some(error);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use ndarray::{Array, Ix2};
+use std::{
+    cmp::min,
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+};
+
+use crate::{
+    error::Error,
+    plink_bed::{
+        cast_byte_offset, dominance_code_values, num_snp_bytes,
+        snp_byte_offset, usize_div_ceil, DominanceOrientation, GenotypeCounts,
+        MissingStrategy, PlinkSnpType, HETEROZYGOUS_GENOTYPE_BITS,
+        HOMOZYGOUS_FIRST_GENOTYPE_BITS, HOMOZYGOUS_SECOND_GENOTYPE_BITS,
+        MISSING_GENOTYPE_BITS, NUM_PEOPLE_PER_BYTE,
+    },
+    util::get_file_buf,
+};
+
+/// The SNPs to read within a bed file.
+pub(crate) enum SnpSelection {
+    /// All the SNPs of a file with the given number of SNPs.
+    All(usize),
+    /// The ascending indices of the SNPs within the file.
+    Indices(Vec<usize>),
+}
+
+impl SnpSelection {
+    fn iter(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        match self {
+            SnpSelection::All(num_snps) => Box::new(0..*num_snps),
+            SnpSelection::Indices(indices) => Box::new(indices.iter().cloned()),
+        }
+    }
+}
+
+/// Iterates over the blocks of people of the bed files, where each block is a
+/// `num_people_in_block x num_snps` matrix of the selected SNPs, without
+/// transposing the bed files on disk. Each block is read SNP by SNP, taking
+/// only the bytes of the people in the block, so that the memory is bounded by
+/// the block.
+///
+/// The iterator stops yielding blocks once an IO error occurs, and the error
+/// can be retrieved by `take_error` after the iteration.
+pub struct PlinkTransposedChunkIter {
+    buf: Vec<BufReader<File>>,
+    /// The SNPs to read within each file.
+    file_snp_indices: Vec<SnpSelection>,
+    num_people: usize,
+    people_per_chunk: usize,
+    person_cursor: usize,
+    /// The value of each of the four 2-bit codes for each SNP, which accounts
+    /// for the SNP type and the missing strategy.
+    snp_values: Vec<[f32; 4]>,
+    error: Option<Error>,
+}
+
+impl PlinkTransposedChunkIter {
+    pub(crate) fn new(
+        bed_path_list: &[String],
+        file_snp_indices: Vec<SnpSelection>,
+        num_people: usize,
+        people_per_chunk: usize,
+        snp_values: Vec<[f32; 4]>,
+    ) -> Result<PlinkTransposedChunkIter, Error> {
+        if people_per_chunk == 0 {
+            return Err(Error::Generic(
+                "people_per_chunk has to be positive".to_string(),
+            ));
+        }
+        let buf = bed_path_list
+            .iter()
+            .map(|p| get_file_buf(p))
+            .collect::<Result<Vec<BufReader<File>>, Error>>()?;
+        Ok(PlinkTransposedChunkIter {
+            buf,
+            file_snp_indices,
+            num_people,
+            people_per_chunk,
+            person_cursor: 0,
+            snp_values,
+            error: None,
+        })
+    }
+
+    /// Returns the error that stopped the iteration, if any, leaving `None`
+    /// in its place.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    /// Reads the genotypes of the people `start..start + num_people_in_block`
+    /// at the selected SNPs in a row-major matrix.
+    fn read_block(
+        &mut self,
+        start: usize,
+        num_people_in_block: usize,
+    ) -> Result<Array<f32, Ix2>, Error> {
+        let num_bytes_per_snp =
+            usize_div_ceil(self.num_people, NUM_PEOPLE_PER_BYTE);
+        let first_byte = start / NUM_PEOPLE_PER_BYTE;
+        let end_byte =
+            usize_div_ceil(start + num_people_in_block, NUM_PEOPLE_PER_BYTE);
+        let mut block_bytes = vec![0u8; end_byte - first_byte];
+        let num_snps = self.snp_values.len();
+        let mut v = vec![0f32; num_people_in_block * num_snps];
+        let mut snp_index = 0;
+        for (buf, file_snp_indices) in
+            self.buf.iter_mut().zip(self.file_snp_indices.iter())
+        {
+            let mut prev_k: Option<usize> = None;
+            for k in file_snp_indices.iter() {
+                match prev_k {
+                    // the block bytes of the previous SNP have been read
+                    Some(prev_k) => buf.seek_relative(cast_byte_offset(
+                        num_snp_bytes(k - prev_k, num_bytes_per_snp)?
+                            - block_bytes.len() as u64,
+                    )?)?,
+                    None => {
+                        buf.seek(SeekFrom::Start(snp_byte_offset(
+                            k,
+                            num_bytes_per_snp,
+                            first_byte,
+                        )?))?;
+                    }
+                }
+                prev_k = Some(k);
+                buf.read_exact(&mut block_bytes)?;
+                let values = &self.snp_values[snp_index];
+                for r in 0..num_people_in_block {
+                    let j = start + r;
+                    let byte =
+                        block_bytes[j / NUM_PEOPLE_PER_BYTE - first_byte];
+                    let bits = (byte >> (2 * (j % NUM_PEOPLE_PER_BYTE))) & 0b11;
+                    v[r * num_snps + snp_index] = values[bits as usize];
+                }
+                snp_index += 1;
+            }
+        }
+        Ok(Array::from_shape_vec((num_people_in_block, num_snps), v).unwrap())
+    }
+}
+
+impl Iterator for PlinkTransposedChunkIter {
+    type Item = Array<f32, Ix2>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.person_cursor >= self.num_people || self.error.is_some() {
+            return None;
+        }
+        let num_people_in_block =
+            min(self.people_per_chunk, self.num_people - self.person_cursor);
+        let start = self.person_cursor;
+        self.person_cursor += num_people_in_block;
+        match self.read_block(start, num_people_in_block) {
+            Ok(block) => Some(block),
+            Err(error) => {
+                self.error = Some(error);
+                None
+            }
+        }
+    }
+}
+
+/// Computes the value of each of the four 2-bit codes of a SNP with the
+/// genotype `counts` as decoded by `get_genotype_matrix`, i.e. with the
+/// `snp_type` encoding oriented according to the `dominance_orientation`, and
+/// the missing genotypes handled according to the `missing_strategy`.
+pub(crate) fn snp_code_values(
+    counts: &GenotypeCounts,
+    num_people: usize,
+    snp_type: PlinkSnpType,
+    missing_strategy: MissingStrategy,
+    dominance_orientation: DominanceOrientation,
+) -> [f32; 4] {
+    // the values of the genotypes 0, 1 and 2
+    let genotype_values = match snp_type {
+        PlinkSnpType::Additive => [0., 1., 2.],
+        PlinkSnpType::Dominance => {
+            // the frequency counts the missing genotypes as 0 as in
+            // convert_geno_vec_to_dominance_representation
+            let p = (2 * counts.num_hom_first + counts.num_het) as f32
+                / (2 * num_people) as f32;
+            dominance_code_values(
+                p,
+                dominance_orientation.assume_minor_is_first(|| *counts),
+            )
+        }
+    };
+    let num_non_missing = counts.num_non_missing();
+    let missing = match missing_strategy {
+        MissingStrategy::MeanImpute if num_non_missing > 0 => {
+            (counts.num_hom_second as f32 * genotype_values[0]
+                + counts.num_het as f32 * genotype_values[1]
+                + counts.num_hom_first as f32 * genotype_values[2])
+                / num_non_missing as f32
+        }
+        _ => 0.,
+    };
+    let mut values = [0f32; 4];
+    values[HOMOZYGOUS_SECOND_GENOTYPE_BITS as usize] = genotype_values[0];
+    values[HETEROZYGOUS_GENOTYPE_BITS as usize] = genotype_values[1];
+    values[HOMOZYGOUS_FIRST_GENOTYPE_BITS as usize] = genotype_values[2];
+    values[MISSING_GENOTYPE_BITS as usize] = missing;
+    values
+}
@@ -1,27 +1,382 @@
 use std::{
     collections::{HashMap, HashSet},
-    fs::{File, OpenOptions},
-    io::{BufRead, BufReader},
+    fs::OpenOptions,
+    io::{BufRead, BufReader, BufWriter, Write},
     iter::FromIterator,
     slice::Iter,
 };
 
 use math::{
     partition::integer_partitions::Partition,
-    set::ordered_integer_set::OrderedIntegerSet, traits::Collecting,
+    set::ordered_integer_set::OrderedIntegerSet,
+    traits::{Collecting, ToIterator},
 };
 
-use crate::error::Error;
+use crate::{error::Error, util::get_buf};
 use num::{FromPrimitive, Integer, ToPrimitive};
 
 pub const CHROM_FIELD_INDEX: usize = 0;
 pub const VARIANT_ID_FIELD_INDEX: usize = 1;
+pub const CM_POSITION_FIELD_INDEX: usize = 2;
 pub const COORDINATE_FIELD_INDEX: usize = 3;
 pub const FIRST_ALLELE_FIELD_INDEX: usize = 4;
 pub const SECOND_ALLELE_FIELD_INDEX: usize = 5;
 
 pub type PartitionKey = String;
 
+/// A line in a bim file, i.e. the metadata of a variant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BimRecord {
+    pub chrom: String,
+    pub variant_id: String,
+    /// The position in centimorgans, where 0 means unknown.
+    pub cm_position: f64,
+    /// The base-pair coordinate.
+    pub coordinate: u64,
+    /// The first allele, usually the minor allele.
+    pub allele_1: String,
+    /// The second allele, usually the major allele.
+    pub allele_2: String,
+}
+
+impl BimRecord {
+    /// Parses a line of the six whitespace-separated fields
+    /// `chrom variant_id cm_position coordinate allele_1 allele_2`.
+    pub fn from_line(line: &str) -> Result<BimRecord, Error> {
+        let toks: Vec<&str> = line.split_whitespace().collect();
+        if toks.len() < 6 {
+            return Err(Error::BadFormat(format!(
+                "a bim line should have 6 fields, but found {}: {}",
+                toks.len(),
+                line
+            )));
+        }
+        let cm_position =
+            toks[CM_POSITION_FIELD_INDEX].parse::<f64>().map_err(|e| {
+                Error::BadFormat(format!(
+                    "failed to parse the cM position {}: {}",
+                    toks[CM_POSITION_FIELD_INDEX], e
+                ))
+            })?;
+        let coordinate =
+            toks[COORDINATE_FIELD_INDEX].parse::<u64>().map_err(|e| {
+                Error::BadFormat(format!(
+                    "failed to parse the coordinate {}: {}",
+                    toks[COORDINATE_FIELD_INDEX], e
+                ))
+            })?;
+        Ok(BimRecord {
+            chrom: toks[CHROM_FIELD_INDEX].to_string(),
+            variant_id: toks[VARIANT_ID_FIELD_INDEX].to_string(),
+            cm_position,
+            coordinate,
+            allele_1: toks[FIRST_ALLELE_FIELD_INDEX].to_string(),
+            allele_2: toks[SECOND_ALLELE_FIELD_INDEX].to_string(),
+        })
+    }
+}
+
+/// The records of bim files held in memory, where the i-th record corresponds
+/// to the i-th SNP of the `PlinkBed` of the same files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bim {
+    records: Vec<BimRecord>,
+}
+
+impl Bim {
+    pub fn new(records: Vec<BimRecord>) -> Bim {
+        Bim {
+            records,
+        }
+    }
+
+    /// Reads the records of all the bim files in order.
+    pub fn read(bim_path_list: &[String]) -> Result<Bim, Error> {
+        let mut records = Vec::new();
+        for path in bim_path_list.iter() {
+            for (i, line) in get_buf(path)?.lines().enumerate() {
+                records.push(BimRecord::from_line(&line?).map_err(|e| {
+                    Error::BadFormat(format!(
+                        "line {} in bim file {}: {}",
+                        i + 1,
+                        path,
+                        e
+                    ))
+                })?);
+            }
+        }
+        Ok(Bim::new(records))
+    }
+
+    #[inline]
+    pub fn records(&self) -> &[BimRecord] {
+        &self.records
+    }
+
+    #[inline]
+    pub fn into_records(self) -> Vec<BimRecord> {
+        self.records
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns the number of SNPs on each chromosome, including the
+    /// mitochondrial and any other contigs, where the chromosomes are in the
+    /// order of their first appearance in the records.
+    pub fn snps_per_chromosome(&self) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        let mut chrom_to_count_index: HashMap<&str, usize> = HashMap::new();
+        for record in self.records.iter() {
+            match chrom_to_count_index.get(record.chrom.as_str()) {
+                Some(&i) => counts[i].1 += 1,
+                None => {
+                    chrom_to_count_index.insert(&record.chrom, counts.len());
+                    counts.push((record.chrom.to_string(), 1));
+                }
+            }
+        }
+        counts
+    }
+
+    /// Returns the indices of the SNPs whose variant IDs are in `ids`, in the
+    /// order of the SNPs rather than that of the `ids`, along with the `ids`
+    /// that are not found. If a variant ID appears more than once, only its
+    /// first occurrence is matched.
+    pub fn indices_for_ids(
+        &self,
+        ids: &[String],
+    ) -> (OrderedIntegerSet<usize>, Vec<String>) {
+        let mut id_to_index: HashMap<&str, usize> = HashMap::new();
+        for (i, record) in self.records.iter().enumerate() {
+            id_to_index.entry(record.variant_id.as_str()).or_insert(i);
+        }
+        let mut indices = Vec::new();
+        let mut missing_ids = Vec::new();
+        for id in ids.iter() {
+            match id_to_index.get(id.as_str()) {
+                Some(&i) => indices.push(i),
+                None => missing_ids.push(id.to_string()),
+            }
+        }
+        indices.sort_unstable();
+        let mut set = OrderedIntegerSet::new();
+        for i in indices {
+            set.collect(i);
+        }
+        (set, missing_ids)
+    }
+
+    /// Flips the strand of the SNPs at the `indices` by replacing A <-> T and
+    /// C <-> G in both alleles. The SNPs with an allele that is not a single
+    /// base, e.g. an indel, are left untouched, and their indices are returned.
+    pub fn complement_alleles(
+        &mut self,
+        indices: &OrderedIntegerSet<usize>,
+    ) -> Result<Vec<usize>, Error> {
+        if let Some(last) = indices.last() {
+            if last >= self.records.len() {
+                return Err(Error::SnpIndexOutOfRange {
+                    index: last,
+                    total: self.records.len(),
+                });
+            }
+        }
+        let mut skipped = Vec::new();
+        for i in indices.to_iter() {
+            let record = &mut self.records[i];
+            match (
+                complement_allele(&record.allele_1),
+                complement_allele(&record.allele_2),
+            ) {
+                (Some(allele_1), Some(allele_2)) => {
+                    record.allele_1 = allele_1;
+                    record.allele_2 = allele_2;
+                }
+                _ => skipped.push(i),
+            }
+        }
+        Ok(skipped)
+    }
+
+    /// Returns the indices of the strand-ambiguous SNPs, i.e. those whose
+    /// alleles are A/T or C/G in either order and case, which read the same on
+    /// both strands. The SNPs with an allele that is not a single base, e.g.
+    /// an indel, are never ambiguous.
+    pub fn ambiguous_snps(&self) -> OrderedIntegerSet<usize> {
+        let mut set = OrderedIntegerSet::new();
+        for (i, record) in self.records.iter().enumerate() {
+            if let Some(complement) = complement_allele(&record.allele_1) {
+                if complement.eq_ignore_ascii_case(&record.allele_2) {
+                    set.collect(i);
+                }
+            }
+        }
+        set
+    }
+
+    /// Returns a builder of the set of SNP indices selected by chromosomes
+    /// and regions.
+    pub fn range_builder(&self) -> BimRangeBuilder<'_> {
+        BimRangeBuilder::new(self)
+    }
+
+    /// Writes the records as a tab-separated bim file at `out_path`.
+    pub fn write(&self, out_path: &str) -> Result<(), Error> {
+        let mut writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(out_path)?,
+        );
+        for r in self.records.iter() {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                r.chrom,
+                r.variant_id,
+                r.cm_position,
+                r.coordinate,
+                r.allele_1,
+                r.allele_2
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Builds the set of the indices of the SNPs of a `Bim` by adding and
+/// excluding chromosomes and regions in the order of the calls, where a
+/// region `(chrom, start, end)` contains the SNPs on the chromosome `chrom`
+/// whose coordinates are in `[start, end]`. Adding SNPs that have already been
+/// added has no effect, and a chromosome that is absent from the `Bim` adds
+/// or excludes nothing.
+pub struct BimRangeBuilder<'a> {
+    records: &'a [BimRecord],
+    /// The indices of the SNPs on each chromosome in increasing order.
+    chrom_to_indices: HashMap<&'a str, Vec<usize>>,
+    set: OrderedIntegerSet<usize>,
+}
+
+impl<'a> BimRangeBuilder<'a> {
+    fn new(bim: &'a Bim) -> BimRangeBuilder<'a> {
+        let mut chrom_to_indices: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, record) in bim.records.iter().enumerate() {
+            chrom_to_indices
+                .entry(record.chrom.as_str())
+                .or_default()
+                .push(i);
+        }
+        BimRangeBuilder {
+            records: &bim.records,
+            chrom_to_indices,
+            set: OrderedIntegerSet::new(),
+        }
+    }
+
+    /// Adds all the SNPs on the chromosome `chrom`.
+    pub fn add_chromosome(mut self, chrom: &str) -> BimRangeBuilder<'a> {
+        let indices = self.region_indices(chrom, None);
+        self.set = union(self.set, indices);
+        self
+    }
+
+    /// Adds the SNPs on the chromosome `chrom` with coordinates in
+    /// `[start, end]`.
+    pub fn add_region(
+        mut self,
+        chrom: &str,
+        start: u64,
+        end: u64,
+    ) -> BimRangeBuilder<'a> {
+        let indices = self.region_indices(chrom, Some((start, end)));
+        self.set = union(self.set, indices);
+        self
+    }
+
+    /// Removes the SNPs on the chromosome `chrom` from the set.
+    pub fn exclude_chromosome(mut self, chrom: &str) -> BimRangeBuilder<'a> {
+        let indices = self.region_indices(chrom, None);
+        self.set -= &indices;
+        self
+    }
+
+    /// Removes the SNPs on the chromosome `chrom` with coordinates in
+    /// `[start, end]` from the set.
+    pub fn exclude_region(
+        mut self,
+        chrom: &str,
+        start: u64,
+        end: u64,
+    ) -> BimRangeBuilder<'a> {
+        let indices = self.region_indices(chrom, Some((start, end)));
+        self.set -= &indices;
+        self
+    }
+
+    pub fn build(self) -> OrderedIntegerSet<usize> {
+        self.set
+    }
+
+    /// Returns the indices of the SNPs on the chromosome `chrom` with
+    /// coordinates in the inclusive `region`, or all the SNPs on the
+    /// chromosome if `None`.
+    fn region_indices(
+        &self,
+        chrom: &str,
+        region: Option<(u64, u64)>,
+    ) -> OrderedIntegerSet<usize> {
+        let mut set = OrderedIntegerSet::new();
+        if let Some(indices) = self.chrom_to_indices.get(chrom) {
+            for &i in indices.iter() {
+                let coordinate = self.records[i].coordinate;
+                match region {
+                    Some((start, end))
+                        if coordinate < start || coordinate > end => {}
+                    _ => set.collect(i),
+                }
+            }
+        }
+        set
+    }
+}
+
+/// Returns the union of the sets `a` and `b`.
+fn union(
+    a: OrderedIntegerSet<usize>,
+    b: OrderedIntegerSet<usize>,
+) -> OrderedIntegerSet<usize> {
+    let mut intervals = a.into_intervals();
+    intervals.append(&mut b.into_intervals());
+    OrderedIntegerSet::from_contiguous_integer_sets(intervals)
+}
+
+/// Returns the complementary base of a single-base `allele`, preserving the
+/// case, or `None` if the `allele` is not one of A, C, G, and T.
+fn complement_allele(allele: &str) -> Option<String> {
+    let complement = match allele {
+        "A" => "T",
+        "T" => "A",
+        "C" => "G",
+        "G" => "C",
+        "a" => "t",
+        "t" => "a",
+        "c" => "g",
+        "g" => "c",
+        _ => return None,
+    };
+    Some(complement.to_string())
+}
+
 pub struct PlinkBim<T: Copy + FromPrimitive + Integer + ToPrimitive> {
     bim_path_list: Vec<String>,
     // maps partition_id to the file line indices
@@ -53,11 +408,11 @@ impl<T: Copy + FromPrimitive + Integer + ToPrimitive> PlinkBim<T> {
         bim.into_partitioned_by_file(partition_filepath)
     }
 
-    fn get_buf_list(&self) -> Result<Vec<BufReader<File>>, Error> {
+    fn get_buf_list(&self) -> Result<Vec<Box<dyn BufRead>>, Error> {
         self.bim_path_list
             .iter()
-            .map(|p| Ok(BufReader::new(OpenOptions::new().read(true).open(p)?)))
-            .collect::<Result<Vec<BufReader<File>>, Error>>()
+            .map(|p| get_buf(p))
+            .collect::<Result<Vec<Box<dyn BufRead>>, Error>>()
     }
 
     pub fn into_partitioned_by_file(
@@ -229,6 +584,12 @@ impl<T: Copy + FromPrimitive + Integer + ToPrimitive> PlinkBim<T> {
         }
     }
 
+    /// Reads the records of all the bim files in order, so that the i-th
+    /// record corresponds to the i-th SNP of the `PlinkBed` of the same files.
+    pub fn get_bim_records(&self) -> Result<Vec<BimRecord>, Error> {
+        Ok(Bim::read(&self.bim_path_list)?.into_records())
+    }
+
     #[inline]
     pub fn get_bim_path_list(&self) -> &Vec<String> {
         &self.bim_path_list
@@ -379,7 +740,7 @@ impl<'a, T: Copy + Integer + ToPrimitive> Iterator
 
 #[cfg(test)]
 mod tests {
-    use crate::plink_bim::PlinkBim;
+    use crate::plink_bim::{Bim, BimRecord, PlinkBim};
     use math::set::{
         contiguous_integer_set::ContiguousIntegerSet,
         ordered_integer_set::OrderedIntegerSet,
@@ -545,6 +906,243 @@ mod tests {
         assert_eq!(chrom_set, expected);
     }
 
+    #[test]
+    fn test_get_bim_records() {
+        let bim_file_1 = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&bim_file_1);
+            write_bim_line(&mut writer, "1", "rs1", 12, 'A', 'C');
+            write_bim_line(&mut writer, "X", "rs2", 100, 'G', 'T');
+        }
+        let bim_file_2 = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&bim_file_2);
+            writer.write_all(b"2\trs3\t0.25\t7\tAT\tA\n").unwrap();
+        }
+        let bim_temp_path_1 = bim_file_1.into_temp_path();
+        let bim_temp_path_2 = bim_file_2.into_temp_path();
+        let bim = PlinkBim::<usize>::new(vec![
+            bim_temp_path_1.to_str().unwrap().to_string(),
+            bim_temp_path_2.to_str().unwrap().to_string(),
+        ])
+        .unwrap();
+        let record =
+            |chrom: &str, id: &str, cm, coordinate, a1: &str, a2: &str| {
+                BimRecord {
+                    chrom: chrom.to_string(),
+                    variant_id: id.to_string(),
+                    cm_position: cm,
+                    coordinate,
+                    allele_1: a1.to_string(),
+                    allele_2: a2.to_string(),
+                }
+            };
+        assert_eq!(bim.get_bim_records().unwrap(), vec![
+            record("1", "rs1", 0., 12, "A", "C"),
+            record("X", "rs2", 0., 100, "G", "T"),
+            record("2", "rs3", 0.25, 7, "AT", "A"),
+        ]);
+        assert!(BimRecord::from_line("1 rs1 0 12 A").is_err());
+        assert!(BimRecord::from_line("1 rs1 0 -12 A C").is_err());
+    }
+
+    #[test]
+    fn test_snps_per_chromosome() {
+        let bim = Bim::new(
+            ["1", "1", "2", "X", "1", "MT", "X", "X"]
+                .iter()
+                .enumerate()
+                .map(|(i, chrom)| BimRecord {
+                    chrom: chrom.to_string(),
+                    variant_id: format!("rs{}", i),
+                    cm_position: 0.,
+                    coordinate: i as u64,
+                    allele_1: "A".to_string(),
+                    allele_2: "C".to_string(),
+                })
+                .collect(),
+        );
+        assert_eq!(bim.len(), 8);
+        assert_eq!(bim.snps_per_chromosome(), vec![
+            ("1".to_string(), 3),
+            ("2".to_string(), 1),
+            ("X".to_string(), 3),
+            ("MT".to_string(), 1),
+        ]);
+        assert!(Bim::new(Vec::new()).snps_per_chromosome().is_empty());
+    }
+
+    #[test]
+    fn test_indices_for_ids() {
+        let bim = Bim::new(
+            (0..6)
+                .map(|i| BimRecord {
+                    chrom: "1".to_string(),
+                    variant_id: format!("rs{}", i),
+                    cm_position: 0.,
+                    coordinate: i as u64,
+                    allele_1: "A".to_string(),
+                    allele_2: "C".to_string(),
+                })
+                .collect(),
+        );
+        let ids: Vec<String> = ["rs4", "rs9", "rs0", "rs1", "rs4", "chr1:5"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let (indices, missing_ids) = bim.indices_for_ids(&ids);
+        assert_eq!(indices, OrderedIntegerSet::from_slice(&[[0, 1], [4, 4]]));
+        assert_eq!(missing_ids, vec!["rs9".to_string(), "chr1:5".to_string()]);
+
+        let (indices, missing_ids) = bim.indices_for_ids(&[]);
+        assert!(indices.is_empty());
+        assert!(missing_ids.is_empty());
+    }
+
+    #[test]
+    fn test_range_builder() {
+        let bim = Bim::new(
+            [
+                ("1", 100),
+                ("1", 200),
+                ("1", 300),
+                ("2", 150),
+                ("2", 250),
+                ("1", 400),
+                ("X", 100),
+                ("X", 200),
+                ("X", 50),
+            ]
+            .iter()
+            .enumerate()
+            .map(|(i, (chrom, coordinate))| BimRecord {
+                chrom: chrom.to_string(),
+                variant_id: format!("rs{}", i),
+                cm_position: 0.,
+                coordinate: *coordinate,
+                allele_1: "A".to_string(),
+                allele_2: "C".to_string(),
+            })
+            .collect(),
+        );
+        assert!(bim.range_builder().build().is_empty());
+        assert_eq!(
+            bim.range_builder().add_chromosome("1").build(),
+            OrderedIntegerSet::from_slice(&[[0, 2], [5, 5]])
+        );
+        // overlapping regions, where the region bounds are inclusive
+        assert_eq!(
+            bim.range_builder()
+                .add_region("1", 200, 400)
+                .add_region("1", 100, 300)
+                .add_region("X", 50, 100)
+                .add_region("Y", 0, 1000)
+                .build(),
+            OrderedIntegerSet::from_slice(&[[0, 2], [5, 6], [8, 8]])
+        );
+        assert_eq!(
+            bim.range_builder()
+                .add_chromosome("1")
+                .add_chromosome("2")
+                .add_chromosome("1")
+                .exclude_region("1", 150, 300)
+                .exclude_region("2", 250, 250)
+                .add_region("X", 0, 150)
+                .build(),
+            OrderedIntegerSet::from_slice(&[[0, 0], [3, 3], [5, 6], [8, 8]])
+        );
+        // the calls are applied in order
+        assert_eq!(
+            bim.range_builder()
+                .exclude_chromosome("X")
+                .add_chromosome("X")
+                .exclude_region("X", 200, 200)
+                .build(),
+            OrderedIntegerSet::from_slice(&[[6, 6], [8, 8]])
+        );
+        assert_eq!(
+            bim.range_builder()
+                .add_chromosome("2")
+                .add_chromosome("X")
+                .exclude_chromosome("2")
+                .build(),
+            OrderedIntegerSet::from_slice(&[[6, 8]])
+        );
+    }
+
+    #[test]
+    fn test_complement_alleles() {
+        let bim_file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&bim_file);
+            for line in &[
+                "1\trs0\t0\t10\tA\tC",
+                "1\trs1\t0.5\t20\tG\tT",
+                "1\trs2\t0\t30\tAT\tA",
+                "2\trs3\t0\t40\tc\tg",
+                "2\trs4\t0\t50\tA\tN",
+            ] {
+                writeln!(writer, "{}", line).unwrap();
+            }
+        }
+        let bim_path = bim_file.path().to_str().unwrap().to_string();
+        let mut bim = Bim::read(&[bim_path]).unwrap();
+        let skipped = bim
+            .complement_alleles(&OrderedIntegerSet::from_slice(&[[0, 0], [
+                2, 4,
+            ]]))
+            .unwrap();
+        assert_eq!(skipped, vec![2, 4]);
+        let alleles: Vec<(&str, &str)> = bim
+            .records()
+            .iter()
+            .map(|r| (r.allele_1.as_str(), r.allele_2.as_str()))
+            .collect();
+        assert_eq!(alleles, vec![
+            ("T", "G"),
+            ("G", "T"),
+            ("AT", "A"),
+            ("g", "c"),
+            ("A", "N")
+        ]);
+        assert!(bim
+            .complement_alleles(&OrderedIntegerSet::from_slice(&[[4, 5]]))
+            .is_err());
+        assert_eq!(bim.records()[4].allele_1, "A");
+
+        let out_path = NamedTempFile::new().unwrap().into_temp_path();
+        let out_path_str = out_path.to_str().unwrap().to_string();
+        bim.write(&out_path_str).unwrap();
+        assert_eq!(Bim::read(&[out_path_str]).unwrap(), bim);
+    }
+
+    #[test]
+    fn test_ambiguous_snps() {
+        let bim_file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = BufWriter::new(&bim_file);
+            for line in &[
+                "1\trs0\t0\t10\tA\tT",
+                "1\trs1\t0\t20\tT\tA",
+                "1\trs2\t0\t30\tA\tC",
+                "1\trs3\t0\t40\tg\tC",
+                "2\trs4\t0\t50\tAT\tTA",
+                "2\trs5\t0\t60\tG\tT",
+                "2\trs6\t0\t70\tC\tG",
+                "2\trs7\t0\t80\tA\tA",
+            ] {
+                writeln!(writer, "{}", line).unwrap();
+            }
+        }
+        let bim_path = bim_file.path().to_str().unwrap().to_string();
+        let bim = Bim::read(&[bim_path]).unwrap();
+        assert_eq!(
+            bim.ambiguous_snps(),
+            OrderedIntegerSet::from_slice(&[[0, 1], [3, 3], [6, 6]])
+        );
+        assert!(Bim::new(Vec::new()).ambiguous_snps().is_empty());
+    }
+
     fn create_dummy_bim() -> (NamedTempFile, NamedTempFile) {
         let bim_temp_file = NamedTempFile::new().unwrap();
         {
@@ -0,0 +1,101 @@
+use std::io::BufRead;
+
+use crate::{error::Error, util::get_buf};
+
+pub const FAMILY_ID_FIELD_INDEX: usize = 0;
+pub const INDIVIDUAL_ID_FIELD_INDEX: usize = 1;
+pub const PATERNAL_ID_FIELD_INDEX: usize = 2;
+pub const MATERNAL_ID_FIELD_INDEX: usize = 3;
+pub const SEX_FIELD_INDEX: usize = 4;
+pub const PHENOTYPE_FIELD_INDEX: usize = 5;
+
+/// A line in a fam file, i.e. the metadata of a person.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FamRecord {
+    pub family_id: String,
+    pub individual_id: String,
+    /// The individual ID of the father, where 0 means not in the dataset.
+    pub paternal_id: String,
+    /// The individual ID of the mother, where 0 means not in the dataset.
+    pub maternal_id: String,
+    /// 1 for male, 2 for female, and 0 for unknown.
+    pub sex: String,
+    pub phenotype: String,
+}
+
+impl FamRecord {
+    /// Parses a line of the six whitespace-separated fields
+    /// `family_id individual_id paternal_id maternal_id sex phenotype`.
+    pub fn from_line(line: &str) -> Result<FamRecord, Error> {
+        let toks: Vec<&str> = line.split_whitespace().collect();
+        if toks.len() < 6 {
+            return Err(Error::BadFormat(format!(
+                "a fam line should have 6 fields, but found {}: {}",
+                toks.len(),
+                line
+            )));
+        }
+        Ok(FamRecord {
+            family_id: toks[FAMILY_ID_FIELD_INDEX].to_string(),
+            individual_id: toks[INDIVIDUAL_ID_FIELD_INDEX].to_string(),
+            paternal_id: toks[PATERNAL_ID_FIELD_INDEX].to_string(),
+            maternal_id: toks[MATERNAL_ID_FIELD_INDEX].to_string(),
+            sex: toks[SEX_FIELD_INDEX].to_string(),
+            phenotype: toks[PHENOTYPE_FIELD_INDEX].to_string(),
+        })
+    }
+}
+
+/// Reads the records of the fam file at `fam_path`.
+pub fn read_fam_records(fam_path: &str) -> Result<Vec<FamRecord>, Error> {
+    get_buf(fam_path)?
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            FamRecord::from_line(&line?).map_err(|e| {
+                Error::BadFormat(format!(
+                    "line {} in fam file {}: {}",
+                    i + 1,
+                    fam_path,
+                    e
+                ))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::NamedTempFile;
+
+    use super::{read_fam_records, FamRecord};
+
+    #[test]
+    fn test_read_fam_records() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "fam1 ind1 0 0 1 -9").unwrap();
+        writeln!(file, "fam1\tind2\tind1\t0\t2\t1.5").unwrap();
+        let records = read_fam_records(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(records, vec![
+            FamRecord {
+                family_id: "fam1".to_string(),
+                individual_id: "ind1".to_string(),
+                paternal_id: "0".to_string(),
+                maternal_id: "0".to_string(),
+                sex: "1".to_string(),
+                phenotype: "-9".to_string(),
+            },
+            FamRecord {
+                family_id: "fam1".to_string(),
+                individual_id: "ind2".to_string(),
+                paternal_id: "ind1".to_string(),
+                maternal_id: "0".to_string(),
+                sex: "2".to_string(),
+                phenotype: "1.5".to_string(),
+            },
+        ]);
+        assert!(FamRecord::from_line("fam1 ind1 0 0 1").is_err());
+    }
+}
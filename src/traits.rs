@@ -2,6 +2,11 @@
 
 pub mod trait_impl;
 
+use math::set::ordered_integer_set::OrderedIntegerSet;
+use ndarray::{Array, Ix1, Ix2};
+
+use crate::error::Error;
+
 /// Data type of the start and end coordinates
 pub type Coordinate = i64;
 
@@ -15,3 +20,65 @@ pub trait ToChromStartEndVal<V> {
         &self,
     ) -> (Chrom, Coordinate, Coordinate, Option<V>);
 }
+
+/// A source of the genotypes of `num_people` people at `num_snps` SNPs, so
+/// that the analyses can be written generically over the genotype formats.
+pub trait GenotypeSource {
+    fn num_people(&self) -> usize;
+
+    fn num_snps(&self) -> usize;
+
+    /// Returns the genotypes of all the people at the SNP with index `i`.
+    fn snp_column(&self, i: usize) -> Result<Array<f32, Ix1>, Error>;
+
+    /// Iterates over the SNPs in the `range`, or all the SNPs if `None`, in
+    /// `num_people x size` chunks, where the last chunk can be smaller. The
+    /// iteration ends after the first error.
+    fn col_chunks(
+        &self,
+        range: Option<OrderedIntegerSet<usize>>,
+        size: usize,
+    ) -> Box<dyn Iterator<Item = Result<Array<f32, Ix2>, Error>> + '_>;
+}
+
+/// An interval [start, end) on a chromosome, with zero-based left-closed
+/// right-open coordinates.
+pub trait GenomicInterval {
+    fn chrom(&self) -> &str;
+
+    fn start(&self) -> u64;
+
+    /// The exclusive end of the interval.
+    fn end(&self) -> u64;
+}
+
+/// Returns whether the two intervals share at least one position.
+pub fn overlaps<A: GenomicInterval, B: GenomicInterval>(a: &A, b: &B) -> bool {
+    a.chrom() == b.chrom() && a.start() < b.end() && b.start() < a.end()
+}
+
+/// Returns whether every position in the `inner` interval is in the `outer`
+/// interval.
+pub fn contains<A: GenomicInterval, B: GenomicInterval>(
+    outer: &A,
+    inner: &B,
+) -> bool {
+    outer.chrom() == inner.chrom()
+        && outer.start() <= inner.start()
+        && inner.end() <= outer.end()
+}
+
+/// Returns the number of positions in the `interval`.
+pub fn length<I: GenomicInterval>(interval: &I) -> u64 {
+    interval.end().saturating_sub(interval.start())
+}
+
+/// A called peak, such as a narrowPeak or broadPeak record, so that the peak
+/// filters can be shared across the peak formats.
+pub trait Peak: GenomicInterval {
+    fn signal_value(&self) -> f64;
+
+    /// The q-value column of the peak as in the file, which is `-log10(q)` in
+    /// the ENCODE formats, or `None` if it was not set.
+    fn q_value(&self) -> Option<f64>;
+}
@@ -1,7 +1,13 @@
+use math::set::ordered_integer_set::OrderedIntegerSet;
+use ndarray::{Array, Ix1, Ix2};
+
 use crate::{
     bed::{BedDataLine, Chrom, Coordinate},
     bedgraph::BedGraphDataLine,
-    traits::ToChromStartEndVal,
+    error::Error,
+    peak_file::{BroadPeak, NarrowPeak, PeakFileDataLine},
+    plink_bed::PlinkBed,
+    traits::{GenomicInterval, GenotypeSource, Peak, ToChromStartEndVal},
 };
 
 impl<V: Clone> ToChromStartEndVal<V> for BedDataLine<V> {
@@ -25,9 +31,265 @@ impl<V: Clone> ToChromStartEndVal<V> for BedGraphDataLine<V> {
     }
 }
 
+impl<D> GenomicInterval for BedDataLine<D> {
+    #[inline]
+    fn chrom(&self) -> &str {
+        &self.chrom
+    }
+
+    #[inline]
+    fn start(&self) -> u64 {
+        self.start as u64
+    }
+
+    #[inline]
+    fn end(&self) -> u64 {
+        self.end as u64
+    }
+}
+
+impl<D> GenomicInterval for BedGraphDataLine<D> {
+    #[inline]
+    fn chrom(&self) -> &str {
+        &self.chrom
+    }
+
+    #[inline]
+    fn start(&self) -> u64 {
+        self.start as u64
+    }
+
+    #[inline]
+    fn end(&self) -> u64 {
+        self.end_exclusive as u64
+    }
+}
+
+impl GenomicInterval for NarrowPeak {
+    #[inline]
+    fn chrom(&self) -> &str {
+        &self.chrom
+    }
+
+    #[inline]
+    fn start(&self) -> u64 {
+        self.start as u64
+    }
+
+    #[inline]
+    fn end(&self) -> u64 {
+        self.end as u64
+    }
+}
+
+impl GenomicInterval for BroadPeak {
+    #[inline]
+    fn chrom(&self) -> &str {
+        &self.chrom
+    }
+
+    #[inline]
+    fn start(&self) -> u64 {
+        self.start as u64
+    }
+
+    #[inline]
+    fn end(&self) -> u64 {
+        self.end as u64
+    }
+}
+
+impl Peak for NarrowPeak {
+    #[inline]
+    fn signal_value(&self) -> f64 {
+        self.signal_value
+    }
+
+    #[inline]
+    fn q_value(&self) -> Option<f64> {
+        self.q_value
+    }
+}
+
+impl Peak for BroadPeak {
+    #[inline]
+    fn signal_value(&self) -> f64 {
+        self.signal_value
+    }
+
+    #[inline]
+    fn q_value(&self) -> Option<f64> {
+        self.q_value
+    }
+}
+
+impl GenomicInterval for PeakFileDataLine {
+    #[inline]
+    fn chrom(&self) -> &str {
+        &self.chrom
+    }
+
+    #[inline]
+    fn start(&self) -> u64 {
+        self.start as u64
+    }
+
+    #[inline]
+    fn end(&self) -> u64 {
+        self.end as u64
+    }
+}
+
+impl GenotypeSource for PlinkBed {
+    #[inline]
+    fn num_people(&self) -> usize {
+        self.num_people
+    }
+
+    #[inline]
+    fn num_snps(&self) -> usize {
+        self.total_num_snps()
+    }
+
+    fn snp_column(&self, i: usize) -> Result<Array<f32, Ix1>, Error> {
+        self.get_snp(i)
+    }
+
+    fn col_chunks(
+        &self,
+        range: Option<OrderedIntegerSet<usize>>,
+        size: usize,
+    ) -> Box<dyn Iterator<Item = Result<Array<f32, Ix2>, Error>> + '_> {
+        let mut chunk_iter = self.col_chunk_iter(size, range);
+        let mut has_failed = false;
+        Box::new(std::iter::from_fn(move || {
+            if has_failed {
+                return None;
+            }
+            match chunk_iter.next() {
+                Some(chunk) => Some(Ok(chunk)),
+                None => chunk_iter.take_error().map(|error| {
+                    has_failed = true;
+                    Err(error)
+                }),
+            }
+        }))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{bed::BedDataLine, traits::ToChromStartEndVal};
+    use math::set::ordered_integer_set::OrderedIntegerSet;
+    use ndarray::{array, s, Array, Axis, Ix2};
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    use crate::{
+        bed::BedDataLine,
+        bedgraph::BedGraphDataLine,
+        peak_file::PeakFileDataLine,
+        plink_bed::{PlinkBed, PlinkSnpType},
+        traits::{
+            contains, length, overlaps, GenomicInterval, GenotypeSource,
+            ToChromStartEndVal,
+        },
+    };
+
+    #[test]
+    fn test_genomic_interval() {
+        let bed_line = BedDataLine::<f32> {
+            chrom: "chr1".to_string(),
+            start: 100,
+            end: 200,
+            name: None,
+            score: None,
+            strand: None,
+        };
+        let bedgraph_line = BedGraphDataLine {
+            chrom: "chr1".to_string(),
+            start: 150,
+            end_exclusive: 180,
+            value: 2.5,
+        };
+        let peak_line = PeakFileDataLine {
+            chrom: "chr1".to_string(),
+            start: 200,
+            end: 260,
+            name: "peak".to_string(),
+            score: 0.,
+            strand: None,
+            signal_value: 1.,
+            p_value: 0.,
+            q_value: 0.,
+            peak: None,
+        };
+        assert_eq!(bedgraph_line.chrom(), "chr1");
+        assert_eq!((peak_line.start(), peak_line.end()), (200, 260));
+        assert_eq!(length(&bed_line), 100);
+        assert_eq!(length(&bedgraph_line), 30);
+
+        assert!(overlaps(&bed_line, &bedgraph_line));
+        assert!(overlaps(&bedgraph_line, &bed_line));
+        // the end is exclusive
+        assert!(!overlaps(&bed_line, &peak_line));
+        assert!(contains(&bed_line, &bedgraph_line));
+        assert!(!contains(&bedgraph_line, &bed_line));
+        assert!(contains(&bed_line, &bed_line));
+
+        let other_chrom = BedDataLine::<f32> {
+            chrom: "chr2".to_string(),
+            ..bed_line.clone()
+        };
+        assert!(!overlaps(&bed_line, &other_chrom));
+        assert!(!contains(&bed_line, &other_chrom));
+    }
+
+    #[test]
+    fn test_plink_bed_genotype_source() {
+        let geno = array![[0u8, 1, 2], [2, 1, 0], [1, 0, 0], [2, 2, 1]];
+        let bed_path = NamedTempFile::new().unwrap().into_temp_path();
+        PlinkBed::create_bed(&geno, bed_path.to_str().unwrap()).unwrap();
+        let mut bim = NamedTempFile::new().unwrap();
+        let mut fam = NamedTempFile::new().unwrap();
+        for i in 0..3 {
+            writeln!(bim, "1 rs{} 0 {} A C", i, i).unwrap();
+        }
+        for i in 0..4 {
+            writeln!(fam, "f{} i{} 0 0 0 -9", i, i).unwrap();
+        }
+        let bed = PlinkBed::new(&[(
+            bed_path.to_str().unwrap().to_string(),
+            bim.path().to_str().unwrap().to_string(),
+            fam.path().to_str().unwrap().to_string(),
+            PlinkSnpType::Additive,
+        )])
+        .unwrap();
+
+        fn column_sums<G: GenotypeSource>(source: &G) -> Vec<f32> {
+            source
+                .col_chunks(None, 2)
+                .flat_map(|chunk| chunk.unwrap().sum_axis(Axis(0)).to_vec())
+                .collect()
+        }
+        assert_eq!(bed.num_people(), 4);
+        assert_eq!(bed.num_snps(), 3);
+        assert_eq!(column_sums(&bed), vec![5., 4., 3.]);
+        assert_eq!(bed.snp_column(1).unwrap(), array![1., 1., 0., 2.]);
+        assert!(bed.snp_column(3).is_err());
+
+        let chunks: Vec<Array<f32, Ix2>> = bed
+            .col_chunks(Some(OrderedIntegerSet::from_slice(&[[1, 2]])), 1)
+            .map(|chunk| chunk.unwrap())
+            .collect();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1], geno.slice(s![.., 2..3]).mapv(|x| x as f32));
+
+        let mut chunks =
+            bed.col_chunks(Some(OrderedIntegerSet::from_slice(&[[2, 3]])), 1);
+        // the out-of-range SNP 3 fails before any SNP is read
+        assert!(chunks.next().unwrap().is_err());
+        assert!(chunks.next().is_none());
+    }
 
     #[test]
     fn test_bed_data_line_to_chorm_start_end_val() {
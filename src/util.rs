@@ -1,16 +1,43 @@
+use flate2::read::MultiGzDecoder;
 use std::{
+    cmp::Ordering,
     fs::{File, OpenOptions},
-    io::BufReader,
+    io::{BufRead, BufReader},
 };
 
 use crate::{bed::Bed, bedgraph::BedGraph, error::Error};
 
+/// The first two bytes of a gzip file.
+const GZIP_MAGIC_BYTES: [u8; 2] = [0x1f, 0x8b];
+
 pub enum TrackVariant {
     Bed(Bed),
     BedGraph(BedGraph),
 }
 
-pub fn get_buf(filename: &str) -> Result<BufReader<File>, Error> {
+/// Opens the file for buffered reading, where a gzip-compressed file, as
+/// detected by its first two bytes, is decompressed transparently. Use
+/// `get_file_buf` for files that need seeking.
+pub fn get_buf(filename: &str) -> Result<Box<dyn BufRead>, Error> {
+    let mut buf = get_file_buf(filename)?;
+    let is_gzipped = match buf.fill_buf() {
+        Ok(bytes) => bytes.starts_with(&GZIP_MAGIC_BYTES),
+        Err(io_error) => {
+            return Err(Error::IO {
+                why: format!("failed to read {}: {}", filename, io_error),
+                io_error,
+            })
+        }
+    };
+    if is_gzipped {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(buf))))
+    } else {
+        Ok(Box::new(buf))
+    }
+}
+
+/// Opens the uncompressed file for buffered reading.
+pub fn get_file_buf(filename: &str) -> Result<BufReader<File>, Error> {
     match OpenOptions::new().read(true).open(filename) {
         Err(io_error) => Err(Error::IO {
             why: format!("failed to open {}: {}", filename, io_error),
@@ -20,6 +47,46 @@ pub fn get_buf(filename: &str) -> Result<BufReader<File>, Error> {
     }
 }
 
+/// Same as `get_file_buf` but also returns the size of the file in bytes,
+/// which is read from the same opened file.
+pub fn get_buf_with_len(
+    filename: &str,
+) -> Result<(BufReader<File>, u64), Error> {
+    let buf = get_file_buf(filename)?;
+    match buf.get_ref().metadata() {
+        Err(io_error) => Err(Error::IO {
+            why: format!(
+                "failed to read the metadata of {}: {}",
+                filename, io_error
+            ),
+            io_error,
+        }),
+        Ok(metadata) => Ok((buf, metadata.len())),
+    }
+}
+
+/// Returns the paths `(prefix.bed, prefix.bim, prefix.fam)` of the bfile with
+/// the `prefix`, where any trailing path separators and a trailing dot are
+/// removed from the `prefix` first, e.g. both `dir/` and `dir.` give
+/// `dir.bed`. Returns an error if nothing is left of the last component of
+/// the `prefix`, e.g. for `/`, `.` or `./`.
+pub fn bfile_paths(prefix: &str) -> Result<(String, String, String), Error> {
+    let trimmed = prefix.trim_end_matches(std::path::is_separator);
+    let trimmed = trimmed.strip_suffix('.').unwrap_or(trimmed);
+    let file_name = trimmed.rsplit(std::path::is_separator).next().unwrap();
+    if file_name.is_empty() || file_name == "." || file_name == ".." {
+        return Err(Error::Generic(format!(
+            "the bfile prefix {:?} has no file name",
+            prefix
+        )));
+    }
+    Ok((
+        format!("{}.bed", trimmed),
+        format!("{}.bim", trimmed),
+        format!("{}.fam", trimmed),
+    ))
+}
+
 #[derive(Eq, PartialEq, Copy, Clone, Debug, Hash)]
 pub enum Strand {
     Positive,
@@ -39,3 +106,52 @@ impl Strand {
         }
     }
 }
+
+/// Compares chromosome names in their natural order, i.e. the numbered
+/// chromosomes come first in numerical order, followed by X, Y and M (or MT),
+/// and then any other chromosome in lexicographical order, e.g.
+/// chr1 < chr2 < chr10 < chrX < chrY < chrM < chrUn. The `chr` prefix is
+/// optional.
+pub fn natural_chrom_cmp(a: &str, b: &str) -> Ordering {
+    fn rank(chrom: &str) -> (u8, u64) {
+        let name = chrom.strip_prefix("chr").unwrap_or(chrom);
+        if let Ok(n) = name.parse::<u64>() {
+            return (0, n);
+        }
+        match name {
+            "X" => (1, 0),
+            "Y" => (1, 1),
+            "M" | "MT" => (1, 2),
+            _ => (2, 0),
+        }
+    }
+    rank(a).cmp(&rank(b)).then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bfile_paths;
+
+    #[test]
+    fn test_bfile_paths() {
+        fn paths(prefix: &str) -> (String, String, String) {
+            (
+                format!("{}.bed", prefix),
+                format!("{}.bim", prefix),
+                format!("{}.fam", prefix),
+            )
+        }
+        assert_eq!(bfile_paths("data").unwrap(), paths("data"));
+        assert_eq!(bfile_paths("./data").unwrap(), paths("./data"));
+        assert_eq!(bfile_paths("dir/").unwrap(), paths("dir"));
+        assert_eq!(bfile_paths("dir//").unwrap(), paths("dir"));
+        assert_eq!(bfile_paths("data.").unwrap(), paths("data"));
+        assert_eq!(
+            bfile_paths("/tmp/chr1.data").unwrap(),
+            paths("/tmp/chr1.data")
+        );
+        for prefix in ["", "/", ".", "./", "..", "dir/.", "dir/..", "dir/..."] {
+            assert!(bfile_paths(prefix).is_err());
+        }
+    }
+}